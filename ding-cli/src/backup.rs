@@ -0,0 +1,98 @@
+//! Single-file archive of a whole linkding library.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use ding_rs::{Bookmark, BookmarkRequest, DingClient, Tag, TagRequest, UserProfile};
+
+const BACKUP_VERSION: u32 = 1;
+
+#[derive(Deserialize, Serialize)]
+pub struct Backup {
+    pub version: u32,
+    pub created: DateTime<Utc>,
+    pub bookmarks: Vec<Bookmark>,
+    pub tags: Vec<Tag>,
+    pub profile: Option<UserProfile>,
+}
+
+#[derive(Default)]
+pub struct RestoreReport {
+    pub bookmarks: usize,
+    pub tags: usize,
+}
+
+pub async fn create(client: &DingClient) -> Result<Backup> {
+    let mut bookmarks = client.all_bookmarks(Default::default()).await?;
+    bookmarks.extend(client.all_archived(Default::default()).await?);
+    Ok(Backup {
+        version: BACKUP_VERSION,
+        created: Utc::now(),
+        bookmarks,
+        tags: client.all_tags(Default::default()).await?,
+        profile: Some(client.user_profile().await?),
+    })
+}
+
+fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|x| x == "zst")
+}
+
+/// Write the backup as JSON, zstd compressed if the file name ends in `.zst`.
+pub fn write(backup: &Backup, path: &Path) -> Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    if is_compressed(path) {
+        let mut encoder = zstd::Encoder::new(file, 0)?;
+        serde_json::to_writer(&mut encoder, backup)?;
+        encoder.finish()?.flush()?;
+    } else {
+        let mut file = file;
+        serde_json::to_writer(&mut file, backup)?;
+        file.flush()?;
+    }
+    Ok(())
+}
+
+pub fn read(path: &Path) -> Result<Backup> {
+    let file = BufReader::new(File::open(path)?);
+    let reader: Box<dyn Read> = if is_compressed(path) {
+        Box::new(zstd::Decoder::with_buffer(file)?)
+    } else {
+        Box::new(file)
+    };
+    let backup: Backup = serde_json::from_reader(reader)?;
+    if backup.version > BACKUP_VERSION {
+        bail!(
+            "Backup version {} is newer than the supported version {BACKUP_VERSION}",
+            backup.version
+        );
+    }
+    Ok(backup)
+}
+
+/// Recreate tags and bookmarks from `backup`. Linkding updates bookmarks whose
+/// URL already exists, so restoring twice doesn't duplicate anything. User
+/// preferences can't be changed through the API and are not restored.
+pub async fn restore(client: &DingClient, backup: &Backup) -> Result<RestoreReport> {
+    let mut report = RestoreReport::default();
+    for tag in &backup.tags {
+        client
+            .create_tag(TagRequest {
+                name: tag.name.clone(),
+            })
+            .await?;
+        report.tags += 1;
+    }
+    for bookmark in &backup.bookmarks {
+        client
+            .create_bookmark(BookmarkRequest::from(bookmark))
+            .await?;
+        report.bookmarks += 1;
+    }
+    Ok(report)
+}
@@ -0,0 +1,216 @@
+//! Running one operation over many bookmarks: a few at a time, with a
+//! progress line on stderr and a report of what succeeded, failed or was
+//! skipped. Failed requests are retried by the client, according to
+//! `--retries`, which knows which of them are safe to send again.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::io::IsTerminal;
+
+use anyhow::Result;
+use crossterm::style::Stylize;
+use futures::{stream, StreamExt};
+use serde::Serialize;
+
+use crate::failure::{self, Failure};
+
+pub const DEFAULT_CONCURRENCY: usize = 4;
+const BAR_WIDTH: usize = 30;
+
+pub enum Outcome<T> {
+    Done(T),
+    /// Nothing to do for the item, for the given reason.
+    Skipped(String),
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Problem {
+    pub item: String,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Report<T> {
+    pub succeeded: Vec<T>,
+    pub failed: Vec<Problem>,
+    pub skipped: Vec<Problem>,
+}
+
+impl<T> Report<T> {
+    pub fn total(&self) -> usize {
+        self.succeeded.len() + self.failed.len() + self.skipped.len()
+    }
+
+    /// Print the failures, the skipped items by reason and `<done> N of M
+    /// <items>` on stderr. Fails with [`Failure::partial`] if any item
+    /// failed.
+    pub fn finish(&self, done: &str, items: &str) -> Result<()> {
+        for problem in &self.failed {
+            eprintln!("{} {}: {}", "Failed".red(), problem.item, problem.reason);
+        }
+        let mut skipped = BTreeMap::<_, usize>::new();
+        for problem in &self.skipped {
+            *skipped.entry(&problem.reason).or_default() += 1;
+        }
+        for (reason, count) in skipped {
+            eprintln!("{} {count} {items}: {reason}", "Skipped".yellow());
+        }
+        let total = self.total();
+        eprintln!("{done} {} of {total} {items}", self.succeeded.len());
+        if !self.failed.is_empty() {
+            return Err(Failure::partial(self.failed.len(), total, items).into());
+        }
+        Ok(())
+    }
+}
+
+pub struct Bulk {
+    label: String,
+    concurrency: usize,
+    checkpoint: Option<Box<dyn Fn(usize)>>,
+}
+
+impl Bulk {
+    /// `label` names the operation on the progress line, e.g. `Archiving`.
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            concurrency: DEFAULT_CONCURRENCY,
+            checkpoint: None,
+        }
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Call `checkpoint` with the number of leading items done without
+    /// failing each time it grows, so that an interrupted run can be picked
+    /// up after them.
+    pub fn checkpoint(mut self, checkpoint: impl Fn(usize) + 'static) -> Self {
+        self.checkpoint = Some(Box::new(checkpoint));
+        self
+    }
+
+    /// Run `op` on every item, keeping their order in the report. `name`
+    /// identifies an item in failure messages.
+    pub async fn run<I, T, Fut>(
+        &self,
+        items: impl IntoIterator<Item = I>,
+        name: impl Fn(&I) -> String,
+        op: impl Fn(I) -> Fut,
+    ) -> Report<T>
+    where
+        I: Clone,
+        Fut: Future<Output = Result<Outcome<T>>>,
+    {
+        let items = items.into_iter().collect::<Vec<_>>();
+        let progress = Progress::new(&self.label, items.len());
+        let (mut leading, mut failed) = (0, false);
+        let results = stream::iter(&items)
+            .map(|item| {
+                let (op, progress) = (&op, &progress);
+                async move {
+                    let result = op(item.clone()).await;
+                    progress.tick(result.is_err());
+                    result
+                }
+            })
+            .buffered(self.concurrency)
+            .inspect(|result| {
+                failed |= result.is_err();
+                if !failed {
+                    leading += 1;
+                    if let Some(checkpoint) = &self.checkpoint {
+                        checkpoint(leading);
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+            .await;
+        progress.clear();
+        let mut report = Report {
+            succeeded: vec![],
+            failed: vec![],
+            skipped: vec![],
+        };
+        for (item, result) in items.iter().zip(results) {
+            match result {
+                Ok(Outcome::Done(x)) => report.succeeded.push(x),
+                Ok(Outcome::Skipped(reason)) => report.skipped.push(Problem {
+                    item: name(item),
+                    reason,
+                }),
+                Err(err) => report.failed.push(Problem {
+                    item: name(item),
+                    reason: failure::message(&err),
+                }),
+            }
+        }
+        report
+    }
+}
+
+/// `Archiving [=========>          ] 12/40, 1 failed` on stderr, redrawn as
+/// items finish. Only shown on a terminal.
+struct Progress<'a> {
+    label: &'a str,
+    total: usize,
+    done: Cell<usize>,
+    failed: Cell<usize>,
+    visible: bool,
+}
+
+impl<'a> Progress<'a> {
+    fn new(label: &'a str, total: usize) -> Self {
+        let progress = Self {
+            label,
+            total,
+            done: Cell::new(0),
+            failed: Cell::new(0),
+            visible: total > 1 && std::io::stderr().is_terminal(),
+        };
+        progress.draw();
+        progress
+    }
+
+    fn tick(&self, failed: bool) {
+        self.done.set(self.done.get() + 1);
+        if failed {
+            self.failed.set(self.failed.get() + 1);
+        }
+        self.draw();
+    }
+
+    fn draw(&self) {
+        if !self.visible {
+            return;
+        }
+        let done = self.done.get();
+        let filled = BAR_WIDTH * done / self.total;
+        let bar = match filled {
+            BAR_WIDTH => "=".repeat(BAR_WIDTH),
+            _ => format!(
+                "{}>{}",
+                "=".repeat(filled),
+                " ".repeat(BAR_WIDTH - filled - 1)
+            ),
+        };
+        let failed = match self.failed.get() {
+            0 => String::new(),
+            failed => format!(", {failed} failed"),
+        };
+        eprint!(
+            "\r\x1b[2K{} [{bar}] {done}/{}{failed}",
+            self.label, self.total
+        );
+    }
+
+    fn clear(&self) {
+        if self.visible {
+            eprint!("\r\x1b[2K");
+        }
+    }
+}
@@ -0,0 +1,506 @@
+//! Local SQLite copy of the library, filled by `ding sync` and used by read
+//! commands when running offline.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use ding_rs::{Bookmark, BookmarkRequest, Tag};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS bookmarks (
+    id INTEGER PRIMARY KEY,
+    is_archived INTEGER NOT NULL,
+    date_added TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE VIRTUAL TABLE IF NOT EXISTS search USING fts5(
+    title, description, notes, url, tags,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+CREATE VIRTUAL TABLE IF NOT EXISTS search_vocab USING fts5vocab(search, row);
+CREATE TABLE IF NOT EXISTS pending (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    base_modified TEXT,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS snoozed (
+    id INTEGER PRIMARY KEY,
+    until TEXT NOT NULL
+);
+";
+
+/// A write made while offline, replayed against the server by `ding sync`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PendingOp {
+    Create { request: BookmarkRequest },
+    Update { id: u64, request: BookmarkRequest },
+    Archive { id: u64 },
+    Unarchive { id: u64 },
+    Delete { id: u64 },
+}
+
+impl PendingOp {
+    pub fn bookmark_id(&self) -> Option<u64> {
+        match self {
+            PendingOp::Create { .. } => None,
+            PendingOp::Update { id, .. }
+            | PendingOp::Archive { id }
+            | PendingOp::Unarchive { id }
+            | PendingOp::Delete { id } => Some(*id),
+        }
+    }
+}
+
+pub struct Pending {
+    pub seq: i64,
+    /// `date_modified` of the cached bookmark when the op was queued.
+    pub base_modified: Option<DateTime<Utc>>,
+    pub op: PendingOp,
+}
+
+/// Relative weight of the `search` columns when ranking results.
+const SEARCH_BOOSTS: [f64; 5] = [10.0, 2.0, 1.0, 3.0, 5.0];
+
+pub struct Cache {
+    conn: Connection,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub bookmark: Bookmark,
+    /// Matched part of the notes, with matches wrapped in `[` `]`.
+    pub snippet: Option<String>,
+}
+
+impl Cache {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|x| x.join("ding").join("cache.sqlite3"))
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn open_default() -> Result<Self> {
+        let path = Self::default_path().ok_or_else(|| anyhow!("No cache directory found"))?;
+        Self::open(&path)
+    }
+
+    /// Replace the cached library with a fresh full download started at
+    /// `synced_at`.
+    pub fn replace_all(
+        &mut self,
+        bookmarks: &[Bookmark],
+        tags: &[Tag],
+        synced_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM bookmarks", [])?;
+        tx.execute("DELETE FROM search", [])?;
+        store(&tx, bookmarks, tags, synced_at)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Merge bookmarks changed since the last sync into the cache.
+    pub fn update(
+        &mut self,
+        bookmarks: &[Bookmark],
+        tags: &[Tag],
+        synced_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        store(&tx, bookmarks, tags, synced_at)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn bookmark_count(&self) -> Result<u64> {
+        Ok(self
+            .conn
+            .query_row("SELECT COUNT(*) FROM bookmarks", [], |x| x.get(0))?)
+    }
+
+    pub fn last_sync(&self) -> Result<Option<DateTime<Utc>>> {
+        let value: Option<String> = self
+            .conn
+            .query_row("SELECT value FROM meta WHERE key = 'last_sync'", [], |x| {
+                x.get(0)
+            })
+            .optional()?;
+        Ok(value
+            .map(|x| DateTime::parse_from_rfc3339(&x))
+            .transpose()?
+            .map(|x| x.with_timezone(&Utc)))
+    }
+
+    /// Cached bookmarks, newest first, filtered like a linkding search query.
+    pub fn bookmarks(&self, archived: bool, query: Option<&str>) -> Result<Vec<Bookmark>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT data FROM bookmarks WHERE is_archived = ?1 ORDER BY date_added DESC, id DESC",
+        )?;
+        let rows = stmt.query_map(params![archived], |x| x.get::<_, String>(0))?;
+        let mut bookmarks = vec![];
+        for row in rows {
+            let bookmark: Bookmark = serde_json::from_str(&row?)?;
+            if query.is_none_or(|x| matches_query(&bookmark, x)) {
+                bookmarks.push(bookmark);
+            }
+        }
+        Ok(bookmarks)
+    }
+
+    pub fn bookmark(&self, id: u64) -> Result<Option<Bookmark>> {
+        let data: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT data FROM bookmarks WHERE id = ?1",
+                params![id],
+                |x| x.get(0),
+            )
+            .optional()?;
+        Ok(data.map(|x| serde_json::from_str(&x)).transpose()?)
+    }
+
+    pub fn bookmark_by_url(&self, url: &Url) -> Result<Option<Bookmark>> {
+        let data: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT data FROM bookmarks WHERE json_extract(data, '$.url') = ?1",
+                params![url.as_str()],
+                |x| x.get(0),
+            )
+            .optional()?;
+        Ok(data.map(|x| serde_json::from_str(&x)).transpose()?)
+    }
+
+    /// Queue `op` for the next sync and apply it to the cached copy, returning
+    /// the bookmark as it looks locally afterwards.
+    pub fn queue(&self, op: &PendingOp) -> Result<Option<Bookmark>> {
+        let cached = match op.bookmark_id() {
+            Some(id) => self.bookmark(id)?,
+            None => None,
+        };
+        self.conn.execute(
+            "INSERT INTO pending (base_modified, data) VALUES (?1, ?2)",
+            params![
+                cached.as_ref().map(|x| x.date_modified.to_rfc3339()),
+                serde_json::to_string(op)?
+            ],
+        )?;
+        let Some(mut bookmark) = cached else {
+            return Ok(None);
+        };
+        match op {
+            PendingOp::Create { .. } => {}
+            PendingOp::Update { request, .. } => apply_request(&mut bookmark, request),
+            PendingOp::Archive { .. } => bookmark.is_archived = true,
+            PendingOp::Unarchive { .. } => bookmark.is_archived = false,
+            PendingOp::Delete { id } => {
+                self.conn
+                    .execute("DELETE FROM bookmarks WHERE id = ?1", params![id])?;
+                self.conn
+                    .execute("DELETE FROM search WHERE rowid = ?1", params![id])?;
+                return Ok(Some(bookmark));
+            }
+        }
+        self.conn.execute(
+            "UPDATE bookmarks SET is_archived = ?2, data = ?3 WHERE id = ?1",
+            params![
+                bookmark.id,
+                bookmark.is_archived,
+                serde_json::to_string(&bookmark)?
+            ],
+        )?;
+        index_bookmark(&self.conn, &bookmark)?;
+        Ok(Some(bookmark))
+    }
+
+    /// Full-text search over the cached bookmarks, best matches first.
+    ///
+    /// Every term must match, as a word prefix. With `fuzzy`, a term also
+    /// matches indexed words within a small edit distance.
+    pub fn search(
+        &self,
+        terms: &[String],
+        archived: bool,
+        fuzzy: bool,
+        limit: u64,
+    ) -> Result<Vec<SearchHit>> {
+        let mut groups = vec![];
+        for term in terms {
+            let term = term.to_lowercase();
+            let mut words = vec![format!("\"{}\"*", term.replace('"', "\"\""))];
+            if fuzzy {
+                words.extend(
+                    self.similar_words(&term)?
+                        .into_iter()
+                        .map(|x| format!("\"{}\"", x.replace('"', "\"\""))),
+                );
+            }
+            groups.push(format!("({})", words.join(" OR ")));
+        }
+        if groups.is_empty() {
+            return Ok(vec![]);
+        }
+        let boosts = SEARCH_BOOSTS.map(|x| x.to_string()).join(", ");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT bookmarks.data, snippet(search, 2, '[', ']', '…', 12)
+             FROM search JOIN bookmarks ON bookmarks.id = search.rowid
+             WHERE search MATCH ?1 AND bookmarks.is_archived = ?2
+             ORDER BY bm25(search, {boosts})
+             LIMIT ?3"
+        ))?;
+        let rows = stmt.query_map(params![groups.join(" AND "), archived, limit], |x| {
+            Ok((x.get::<_, String>(0)?, x.get::<_, String>(1)?))
+        })?;
+        rows.map(|x| {
+            let (data, snippet) = x?;
+            Ok(SearchHit {
+                bookmark: serde_json::from_str(&data)?,
+                snippet: snippet.contains('[').then_some(snippet),
+            })
+        })
+        .collect()
+    }
+
+    fn similar_words(&self, term: &str) -> Result<Vec<String>> {
+        let max_distance = if term.chars().count() > 7 { 2 } else { 1 };
+        let mut stmt = self.conn.prepare("SELECT term FROM search_vocab")?;
+        let rows = stmt.query_map([], |x| x.get::<_, String>(0))?;
+        let mut words = vec![];
+        for word in rows {
+            let word = word?;
+            if word != term && edit_distance(&word, term) <= max_distance {
+                words.push(word);
+            }
+        }
+        Ok(words)
+    }
+
+    pub fn pending(&self) -> Result<Vec<Pending>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT seq, base_modified, data FROM pending ORDER BY seq")?;
+        let rows = stmt.query_map([], |x| {
+            Ok((
+                x.get::<_, i64>(0)?,
+                x.get::<_, Option<String>>(1)?,
+                x.get::<_, String>(2)?,
+            ))
+        })?;
+        rows.map(|x| {
+            let (seq, base_modified, data) = x?;
+            Ok(Pending {
+                seq,
+                base_modified: base_modified
+                    .map(|x| DateTime::parse_from_rfc3339(&x))
+                    .transpose()?
+                    .map(|x| x.with_timezone(&Utc)),
+                op: serde_json::from_str(&data)?,
+            })
+        })
+        .collect()
+    }
+
+    pub fn remove_pending(&self, seq: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM pending WHERE seq = ?1", params![seq])?;
+        Ok(())
+    }
+
+    /// Hide the bookmark `id` until `until`. Kept across syncs, as the server
+    /// knows nothing about it.
+    pub fn snooze(&self, id: u64, until: DateTime<Utc>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO snoozed (id, until) VALUES (?1, ?2)",
+            params![id, until.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Snoozed bookmark ids and when they wake up, soonest first.
+    pub fn snoozed(&self) -> Result<Vec<(u64, DateTime<Utc>)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, until FROM snoozed ORDER BY until, id")?;
+        let rows = stmt.query_map([], |x| Ok((x.get::<_, u64>(0)?, x.get::<_, String>(1)?)))?;
+        rows.map(|x| {
+            let (id, until) = x?;
+            Ok((
+                id,
+                DateTime::parse_from_rfc3339(&until)?.with_timezone(&Utc),
+            ))
+        })
+        .collect()
+    }
+
+    pub fn unsnooze(&self, id: u64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM snoozed WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn tags(&self) -> Result<Vec<Tag>> {
+        let mut stmt = self.conn.prepare("SELECT data FROM tags ORDER BY id")?;
+        let rows = stmt.query_map([], |x| x.get::<_, String>(0))?;
+        rows.map(|x| Ok(serde_json::from_str(&x?)?)).collect()
+    }
+}
+
+fn store(
+    conn: &Connection,
+    bookmarks: &[Bookmark],
+    tags: &[Tag],
+    synced_at: DateTime<Utc>,
+) -> Result<()> {
+    let mut insert = conn.prepare(
+        "INSERT OR REPLACE INTO bookmarks (id, is_archived, date_added, data)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for bookmark in bookmarks {
+        insert.execute(params![
+            bookmark.id,
+            bookmark.is_archived,
+            bookmark.date_added.to_rfc3339(),
+            serde_json::to_string(bookmark)?
+        ])?;
+        index_bookmark(conn, bookmark)?;
+    }
+    conn.execute("DELETE FROM tags", [])?;
+    let mut insert = conn.prepare("INSERT INTO tags (id, data) VALUES (?1, ?2)")?;
+    for tag in tags {
+        insert.execute(params![tag.id, serde_json::to_string(tag)?])?;
+    }
+    conn.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('last_sync', ?1)",
+        params![synced_at.to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+fn index_bookmark(conn: &Connection, bookmark: &Bookmark) -> Result<()> {
+    conn.execute("DELETE FROM search WHERE rowid = ?1", params![bookmark.id])?;
+    conn.execute(
+        "INSERT INTO search (rowid, title, description, notes, url, tags)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            bookmark.id,
+            [&bookmark.title, &bookmark.website_title]
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("\n"),
+            [&bookmark.description, &bookmark.website_description]
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("\n"),
+            bookmark.notes.clone().unwrap_or_default(),
+            bookmark.url.as_str(),
+            bookmark.tag_names.join(" "),
+        ],
+    )?;
+    Ok(())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+fn apply_request(bookmark: &mut Bookmark, request: &BookmarkRequest) {
+    let request = request.clone();
+    if let Some(url) = request.url {
+        bookmark.url = url;
+    }
+    bookmark.title = request.title.or(bookmark.title.take());
+    bookmark.description = request.description.or(bookmark.description.take());
+    bookmark.notes = request.notes.or(bookmark.notes.take());
+    bookmark.is_archived = request.is_archived.unwrap_or(bookmark.is_archived);
+    bookmark.unread = request.unread.unwrap_or(bookmark.unread);
+    bookmark.shared = request.shared.unwrap_or(bookmark.shared);
+    if let Some(tag_names) = request.tag_names {
+        bookmark.tag_names = tag_names;
+    }
+}
+
+/// Local approximation of linkding's search syntax: plain terms match the
+/// URL, titles, description, notes or a tag name, `#tag` requires a tag, and
+/// `!unread` and `!untagged` filter on state.
+pub fn matches_query(bookmark: &Bookmark, query: &str) -> bool {
+    let text = [
+        Some(bookmark.url.as_str()),
+        bookmark.title.as_deref(),
+        bookmark.website_title.as_deref(),
+        bookmark.description.as_deref(),
+        bookmark.website_description.as_deref(),
+        bookmark.notes.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n")
+    .to_lowercase();
+    query.split_whitespace().all(|term| {
+        if let Some(tag) = term.strip_prefix('#') {
+            bookmark
+                .tag_names
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case(tag))
+        } else if term == "!unread" {
+            bookmark.unread
+        } else if term == "!untagged" {
+            bookmark.tag_names.is_empty()
+        } else {
+            text.contains(&term.to_lowercase())
+                || bookmark
+                    .tag_names
+                    .iter()
+                    .any(|x| x.eq_ignore_ascii_case(term))
+        }
+    })
+}
+
+pub fn describe_age(since: DateTime<Utc>) -> String {
+    let age = Utc::now() - since;
+    let age = if age.num_days() > 0 {
+        format!("{} days", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{} hours", age.num_hours())
+    } else {
+        format!("{} minutes", age.num_minutes())
+    };
+    format!("{} ({age} ago)", since.format("%Y-%m-%d %H:%M UTC"))
+}
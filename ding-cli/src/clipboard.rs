@@ -0,0 +1,44 @@
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use url::Url;
+
+pub fn read_text() -> Result<String> {
+    Ok(arboard::Clipboard::new()?.get_text()?)
+}
+
+/// First http(s) URL found in `text`, ignoring surrounding punctuation.
+pub fn extract_url(text: &str) -> Option<Url> {
+    text.split_whitespace()
+        .map(|x| x.trim_matches(|c: char| "<>()[]{}\"'`,;".contains(c)))
+        .filter_map(|x| Url::parse(x).ok())
+        .find(|x| matches!(x.scheme(), "http" | "https"))
+}
+
+pub fn read_url() -> Result<Url> {
+    let text = read_text()?;
+    extract_url(&text).ok_or_else(|| anyhow!("No URL found in clipboard"))
+}
+
+/// Copy `text` to the clipboard. Over SSH, or when no clipboard is
+/// reachable, fall back to an OSC 52 escape so the local terminal picks it up.
+pub fn write_text(text: &str) -> Result<()> {
+    let over_ssh = std::env::var_os("SSH_CONNECTION").is_some();
+    if !over_ssh {
+        if let Ok(()) = arboard::Clipboard::new().and_then(|mut x| x.set_text(text)) {
+            return Ok(());
+        }
+    }
+    write_osc52(text)
+}
+
+fn write_osc52(text: &str) -> Result<()> {
+    let payload = base64::engine::general_purpose::STANDARD.encode(text);
+    let sequence = format!("\x1b]52;c;{payload}\x07");
+    match std::fs::OpenOptions::new().write(true).open("/dev/tty") {
+        Ok(mut tty) => tty.write_all(sequence.as_bytes())?,
+        Err(_) => std::io::stderr().write_all(sequence.as_bytes())?,
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! Dynamic completion candidates for tag names and bookmark IDs.
+//!
+//! Completion runs on every <TAB>, so the server is only asked when the cached
+//! copy is older than [`CACHE_TTL`]. The host and token are taken from the
+//! environment since the command line is not parsed at that point.
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use clap_complete::engine::CompletionCandidate;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use ding_rs::{BookmarksRequest, DingClient};
+
+const CACHE_TTL: Duration = Duration::from_secs(300);
+const RECENT_BOOKMARKS: u64 = 50;
+
+#[derive(Default, Deserialize, Serialize)]
+struct CompletionCache {
+    tags: Vec<String>,
+    bookmarks: Vec<(u64, String)>,
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|x| x.join("ding").join("completion.json"))
+}
+
+fn read_cache(max_age: Option<Duration>) -> Option<CompletionCache> {
+    let path = cache_path()?;
+    if let Some(max_age) = max_age {
+        let modified = path.metadata().and_then(|x| x.modified()).ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        if age > max_age {
+            return None;
+        }
+    }
+    serde_json::from_slice(&std::fs::read(path).ok()?).ok()
+}
+
+fn fetch() -> Result<CompletionCache> {
+    let host = Url::parse(&std::env::var("DING_HOST")?)?;
+    let client = DingClient::new(host, std::env::var("DING_TOKEN")?);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        let tags = client.all_tags(Default::default()).await?;
+        let bookmarks = client
+            .bookmarks(BookmarksRequest {
+                limit: Some(RECENT_BOOKMARKS),
+                ..Default::default()
+            })
+            .await?;
+        Ok(CompletionCache {
+            tags: tags.into_iter().map(|x| x.name).collect(),
+            bookmarks: bookmarks
+                .results
+                .into_iter()
+                .map(|x| {
+                    let title = x.title.or(x.website_title).unwrap_or(x.url.to_string());
+                    (x.id, title)
+                })
+                .collect(),
+        })
+    })
+}
+
+fn load() -> CompletionCache {
+    if let Some(cache) = read_cache(Some(CACHE_TTL)) {
+        return cache;
+    }
+    match fetch() {
+        Ok(cache) => {
+            if let Some(path) = cache_path() {
+                let _ = std::fs::create_dir_all(path.parent().expect("cache path has a parent"));
+                let _ = serde_json::to_vec(&cache).map(|x| std::fs::write(path, x));
+            }
+            cache
+        }
+        // Offline or not configured, a stale list is better than nothing.
+        Err(_) => read_cache(None).unwrap_or_default(),
+    }
+}
+
+pub fn tags() -> Vec<CompletionCandidate> {
+    load()
+        .tags
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Tags prefixed with `+` and `-` for the `tag` command.
+pub fn tag_changes() -> Vec<CompletionCandidate> {
+    load()
+        .tags
+        .into_iter()
+        .flat_map(|x| [format!("+{x}"), format!("-{x}")])
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+pub fn bookmark_ids() -> Vec<CompletionCandidate> {
+    load()
+        .bookmarks
+        .into_iter()
+        .map(|(id, title)| CompletionCandidate::new(id.to_string()).help(Some(title.into())))
+        .collect()
+}
@@ -0,0 +1,162 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use url::Url;
+
+use ding_rs::TrackingParams;
+
+use crate::output::{DateDisplay, Layout};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub add: AddConfig,
+    pub tracking: TrackingConfig,
+    pub rules: RulesConfig,
+    pub pick: PickConfig,
+    pub output: OutputConfig,
+    pub hooks: HooksConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AddConfig {
+    /// Remove tracking parameters from URLs before saving them.
+    pub strip_tracking: bool,
+    /// Save where short links lead, as with `ding add --expand`.
+    pub expand_short_links: bool,
+    /// Hosts whose links are expanded, instead of the built-in list of
+    /// common shorteners.
+    pub shorteners: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrackingConfig {
+    /// Parameters removed in addition to the built-in list.
+    pub deny: Vec<String>,
+    /// Parameters kept even if they are on the built-in list.
+    pub allow: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RulesConfig {
+    /// Default `unread` state for new bookmarks.
+    pub unread: Option<bool>,
+    /// Default `shared` state for new bookmarks.
+    pub shared: Option<bool>,
+    pub rule: Vec<RuleConfig>,
+}
+
+/// Tags and flags applied to bookmarks whose URL, domain and title match the
+/// given regular expressions.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleConfig {
+    pub domain: Option<String>,
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub unread: Option<bool>,
+    pub shared: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PickConfig {
+    /// Picker used by `ding pick`. It gets one tab separated line per
+    /// bookmark on stdin and prints the selected lines.
+    pub command: Vec<String>,
+}
+
+impl Default for PickConfig {
+    fn default() -> Self {
+        Self {
+            command: [
+                "fzf",
+                "--multi",
+                "--delimiter=\t",
+                "--with-nth=2..",
+                "--prompt=ding> ",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Bookmark layout of the human format: `oneline`, `normal` or `long`.
+    pub layout: Layout,
+    /// How the human format shows dates: `relative`, `absolute` or `hidden`.
+    /// `--verbose` always shows them absolute.
+    pub date_display: DateDisplay,
+    /// strftime format of absolute dates, shown in the local time zone.
+    pub date_format: String,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            layout: Layout::default(),
+            date_display: DateDisplay::default(),
+            date_format: "%Y-%m-%d %H:%M".to_string(),
+        }
+    }
+}
+
+/// Hooks run after bookmarks were added, deleted or archived, e.g.
+/// `on_add = [{ exec = "notes-sync" }, { url = "https://example.com/hook" }]`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Also run for each bookmark `import` creates.
+    pub on_add: Vec<HookConfig>,
+    pub on_delete: Vec<HookConfig>,
+    pub on_archive: Vec<HookConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum HookConfig {
+    /// Shell command getting the bookmark as JSON on stdin and the event in
+    /// `DING_EVENT`.
+    Exec(String),
+    /// URL the bookmark is POSTed to as JSON, with the event in the
+    /// `X-Ding-Event` header.
+    Url(Url),
+}
+
+impl TrackingConfig {
+    pub fn params(&self) -> TrackingParams {
+        let mut params = TrackingParams::default();
+        params.deny.extend(self.deny.iter().cloned());
+        params.allow.extend(self.allow.iter().cloned());
+        params
+    }
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|x| x.join("ding").join("config.toml"))
+    }
+
+    /// Load the config from `path`, or from the default location if it exists.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config {}", path.display()))
+    }
+}
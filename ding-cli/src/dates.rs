@@ -0,0 +1,173 @@
+//! Dates and durations given on the command line.
+
+use chrono::{
+    DateTime, Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, TimeDelta, Utc, Weekday,
+};
+
+/// Parse an RFC 3339 time, an ISO date (midnight, local time), `now`,
+/// `today`, `yesterday` or `<n> <unit>s ago`, e.g. `2 weeks ago`.
+pub fn parse(s: &str) -> Result<DateTime<Utc>, String> {
+    let s = s.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return local_midnight(date);
+    }
+    let today = Local::now().date_naive();
+    match s.to_lowercase().as_str() {
+        "now" => Ok(Utc::now()),
+        "today" => local_midnight(today),
+        "yesterday" => local_midnight(today - Days::new(1)),
+        words => ago(words).ok_or_else(|| {
+            format!("invalid date `{s}`, expected e.g. 2024-01-31, yesterday or `2 weeks ago`")
+        }),
+    }
+}
+
+/// Parse a time in the future: `tomorrow`, a weekday such as `saturday` or
+/// `next saturday` (midnight, local time), `in <n> <unit>s`, e.g. `in 2
+/// weeks`, or a date or time as for [`parse`].
+pub fn parse_future(s: &str) -> Result<DateTime<Utc>, String> {
+    let words = s.trim().to_lowercase();
+    let today = Local::now().date_naive();
+    let weekday = words.strip_prefix("next ").unwrap_or(&words);
+    let time = if words == "tomorrow" {
+        local_midnight(today + Days::new(1))?
+    } else if let Ok(weekday) = weekday.parse::<Weekday>() {
+        // The coming one, or a week from now on the same weekday.
+        let days = (weekday.days_since(today.weekday()) + 6) % 7 + 1;
+        local_midnight(today + Days::new(days.into()))?
+    } else if let Some(time) = words.strip_prefix("in ").and_then(|x| shift(x, true)) {
+        time
+    } else {
+        parse(s).map_err(|_| {
+            format!("invalid date `{s}`, expected e.g. 2024-01-31, next saturday or `in 2 weeks`")
+        })?
+    };
+    if time <= Utc::now() {
+        return Err(format!("`{s}` is not in the future"));
+    }
+    Ok(time)
+}
+
+fn local_midnight(date: NaiveDate) -> Result<DateTime<Utc>, String> {
+    NaiveDateTime::from(date)
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|x| x.with_timezone(&Utc))
+        .ok_or_else(|| format!("{date} has no midnight in the local time zone"))
+}
+
+fn ago(words: &str) -> Option<DateTime<Utc>> {
+    shift(words.strip_suffix(" ago")?, false)
+}
+
+/// Now moved by `<n> <unit>s`, forward or back.
+fn shift(words: &str, forward: bool) -> Option<DateTime<Utc>> {
+    let [count, unit] = words.split_whitespace().collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let count: u32 = count.parse().ok()?;
+    let delta = |x: TimeDelta| if forward { x } else { -x };
+    let now = Utc::now();
+    match unit.strip_suffix('s').unwrap_or(unit) {
+        "second" | "sec" => Some(now + delta(TimeDelta::seconds(count.into()))),
+        "minute" | "min" => Some(now + delta(TimeDelta::minutes(count.into()))),
+        "hour" => Some(now + delta(TimeDelta::hours(count.into()))),
+        "day" => Some(now + delta(TimeDelta::days(count.into()))),
+        "week" => Some(now + delta(TimeDelta::weeks(count.into()))),
+        "month" => shift_months(now, count, forward),
+        "year" => shift_months(now, count.checked_mul(12)?, forward),
+        _ => None,
+    }
+}
+
+fn shift_months(time: DateTime<Utc>, count: u32, forward: bool) -> Option<DateTime<Utc>> {
+    match forward {
+        true => time.checked_add_months(Months::new(count)),
+        false => time.checked_sub_months(Months::new(count)),
+    }
+}
+
+/// Parse a duration such as `90s`, `5m`, `2h` or `1d`. A bare number is in
+/// seconds.
+pub fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+    let s = s.trim();
+    let split = s.find(|x: char| !x.is_ascii_digit()).unwrap_or(s.len());
+    let (count, unit) = s.split_at(split);
+    let count: u64 = count
+        .parse()
+        .map_err(|_| format!("invalid duration `{s}`, expected e.g. 30s, 5m or 1h"))?;
+    let seconds = match unit.trim() {
+        "" | "s" | "sec" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown unit in `{s}`, expected s, m, h or d")),
+    };
+    Ok(std::time::Duration::from_secs(count * seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parses_times_and_dates() {
+        assert_eq!(
+            parse("2024-01-31T12:00:00+02:00").unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 31, 10, 0, 0).unwrap()
+        );
+        let midnight = Local.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap();
+        assert_eq!(parse(" 2024-01-31 ").unwrap(), midnight);
+    }
+
+    #[test]
+    fn parses_relative_days() {
+        let today = Local::now().date_naive();
+        assert_eq!(parse("today").unwrap(), local_midnight(today).unwrap());
+        assert_eq!(
+            parse("Yesterday").unwrap(),
+            local_midnight(today - Days::new(1)).unwrap()
+        );
+    }
+
+    #[test]
+    fn parses_times_ago() {
+        let close = |s: &str, expected: DateTime<Utc>| {
+            let time = parse(s).unwrap();
+            assert!(
+                (time - expected).abs() < TimeDelta::seconds(5),
+                "{s}: {time}"
+            );
+        };
+        let now = Utc::now();
+        close("90 seconds ago", now - TimeDelta::seconds(90));
+        close("1 hour ago", now - TimeDelta::hours(1));
+        close("2 weeks ago", now - TimeDelta::weeks(2));
+        close(
+            "3 months ago",
+            now.checked_sub_months(Months::new(3)).unwrap(),
+        );
+        close(
+            "1 year ago",
+            now.checked_sub_months(Months::new(12)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn rejects_other_input() {
+        for s in [
+            "",
+            "2024-13-01",
+            "2 weeks",
+            "two weeks ago",
+            "3 fortnights ago",
+        ] {
+            assert!(parse(s).is_err(), "{s}");
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::Result;
+use url::Url;
+
+use ding_rs::{Bookmark, BookmarkRequest, DingClient, TrackingParams};
+
+pub fn normalize_url(url: &Url, tracking: Option<&TrackingParams>) -> String {
+    let mut url = match tracking {
+        Some(tracking) => tracking.strip(url),
+        None => url.clone(),
+    };
+    url.set_fragment(None);
+    let host = url
+        .host_str()
+        .map(|x| x.trim_start_matches("www.").to_string());
+    if let Some(host) = host {
+        // Stripping a prefix from a valid host always yields a valid host.
+        let _ = url.set_host(Some(&host));
+    }
+    url.as_str().trim_end_matches('/').to_string()
+}
+
+/// Group bookmarks sharing the same normalized URL. Every group is ordered by
+/// `date_added`, so the first item is the one that will be kept.
+pub fn find_duplicates(
+    bookmarks: Vec<Bookmark>,
+    tracking: Option<&TrackingParams>,
+) -> Vec<Vec<Bookmark>> {
+    let mut groups: HashMap<String, Vec<Bookmark>> = HashMap::new();
+    for bookmark in bookmarks {
+        groups
+            .entry(normalize_url(&bookmark.url, tracking))
+            .or_default()
+            .push(bookmark);
+    }
+    let mut groups = groups
+        .into_values()
+        .filter(|x| x.len() > 1)
+        .map(|mut x| {
+            x.sort_by_key(|x| (x.date_added, x.id));
+            x
+        })
+        .collect::<Vec<_>>();
+    groups.sort_by_key(|x| x[0].id);
+    groups
+}
+
+pub fn merge_request(group: &[Bookmark]) -> BookmarkRequest {
+    let mut tag_names: Vec<String> = vec![];
+    let mut notes: Vec<String> = vec![];
+    for bookmark in group {
+        for tag in &bookmark.tag_names {
+            if !tag_names.contains(tag) {
+                tag_names.push(tag.clone());
+            }
+        }
+        if let Some(note) = &bookmark.notes {
+            if !notes.contains(note) {
+                notes.push(note.clone());
+            }
+        }
+    }
+    BookmarkRequest {
+        tag_names: Some(tag_names),
+        notes: (!notes.is_empty()).then(|| notes.join("\n\n")),
+        ..Default::default()
+    }
+}
+
+pub async fn merge_group(client: &DingClient, group: &[Bookmark]) -> Result<Bookmark> {
+    let (keep, rest) = group.split_first().expect("empty duplicate group");
+    let bookmark = client
+        .update_bookmark(keep.id, merge_request(group))
+        .await?;
+    for duplicate in rest {
+        client.delete_bookmark(duplicate.id).await?;
+    }
+    Ok(bookmark)
+}
+
+pub enum Answer {
+    Yes,
+    No,
+    Quit,
+}
+
+pub fn confirm(question: &str) -> Result<Answer> {
+    print!("{question} [y/N/q] ");
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(match line.trim().to_lowercase().as_str() {
+        "y" | "yes" => Answer::Yes,
+        "q" | "quit" => Answer::Quit,
+        _ => Answer::No,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn bookmark(id: u64, url: &str, day: u32, tags: &[&str], notes: &str) -> Bookmark {
+        serde_json::from_value(json!({
+            "id": id,
+            "url": url,
+            "notes": notes,
+            "tag_names": tags,
+            "date_added": format!("2024-01-{day:02}T00:00:00Z"),
+            "date_modified": "2024-02-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn normalize(url: &str, tracking: Option<&TrackingParams>) -> String {
+        normalize_url(&Url::parse(url).unwrap(), tracking)
+    }
+
+    #[test]
+    fn normalized_urls() {
+        let cases = [
+            ("https://example.com/", "https://example.com"),
+            ("https://www.example.com/a/", "https://example.com/a"),
+            ("https://example.com/a#section", "https://example.com/a"),
+            ("HTTPS://Example.COM/a", "https://example.com/a"),
+            ("https://example.com/a?id=1", "https://example.com/a?id=1"),
+            (
+                "https://example.com/a?utm_source=x",
+                "https://example.com/a?utm_source=x",
+            ),
+            // Different schemes, paths and hosts stay apart.
+            ("http://example.com/", "http://example.com"),
+            ("https://example.com/A", "https://example.com/A"),
+            ("https://www2.example.com/", "https://www2.example.com"),
+        ];
+        for (url, normalized) in cases {
+            assert_eq!(normalize(url, None), normalized, "{url}");
+        }
+    }
+
+    #[test]
+    fn normalized_urls_without_tracking() {
+        let tracking = TrackingParams::default();
+        assert_eq!(
+            normalize(
+                "https://www.example.com/a/?id=1&utm_source=x#top",
+                Some(&tracking)
+            ),
+            "https://example.com/a/?id=1"
+        );
+        assert_eq!(
+            normalize("https://example.com/a/?fbclid=1", Some(&tracking)),
+            "https://example.com/a"
+        );
+    }
+
+    #[test]
+    fn duplicates_are_grouped_oldest_first() {
+        let bookmarks = vec![
+            bookmark(1, "https://example.com/a", 3, &[], ""),
+            bookmark(2, "https://other.example/", 1, &[], ""),
+            bookmark(3, "https://www.example.com/a/", 2, &[], ""),
+            bookmark(4, "https://example.com/a#x", 2, &[], ""),
+            bookmark(5, "https://example.com/b", 1, &[], ""),
+        ];
+        let groups = find_duplicates(bookmarks, None)
+            .iter()
+            .map(|x| x.iter().map(|x| x.id).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(groups, vec![vec![3, 4, 1]]);
+    }
+
+    #[test]
+    fn merged_tags_and_notes() {
+        let group = [
+            bookmark(1, "https://example.com/", 1, &["b", "a"], "First"),
+            bookmark(2, "https://example.com/", 2, &["a", "c"], ""),
+            bookmark(3, "https://example.com/", 3, &["c"], "First"),
+            bookmark(4, "https://example.com/", 4, &[], "Second"),
+        ];
+        assert_eq!(
+            merge_request(&group),
+            BookmarkRequest {
+                tag_names: Some(vec!["b".into(), "a".into(), "c".into()]),
+                notes: Some("First\n\nSecond".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(merge_request(&group[1..2]).notes, None);
+    }
+}
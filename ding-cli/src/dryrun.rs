@@ -0,0 +1,148 @@
+//! `--dry-run`: requests that would change something on the server are
+//! printed instead of sent, and answered the way the server likely would, so
+//! that commands carry on as usual. Reads still go to the server.
+
+use chrono::Utc;
+use crossterm::style::Stylize;
+use serde_json::{json, Map, Value};
+
+use ding_rs::transport::{
+    BoxFuture, HttpRequest, HttpResponse, HttpTransport, Method, ReqwestTransport, TransportError,
+};
+
+pub struct DryRun {
+    inner: ReqwestTransport,
+}
+
+impl DryRun {
+    pub fn new(inner: ReqwestTransport) -> Self {
+        Self { inner }
+    }
+
+    async fn respond(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let path = request.url.path();
+        if request.method == Method::Delete
+            || path.ends_with("/archive/")
+            || path.ends_with("/unarchive/")
+        {
+            return Ok(response(204, vec![]));
+        }
+        let mut object = match request.method {
+            Method::Post => created(path),
+            _ => {
+                // An update answers with the whole object, changed.
+                let mut get = HttpRequest::new(Method::Get, request.url.clone());
+                get.headers = request
+                    .headers
+                    .iter()
+                    .filter(|(name, _)| !name.eq_ignore_ascii_case("content-type"))
+                    .cloned()
+                    .collect();
+                let resp = self.inner.send(get).await?;
+                if !resp.is_success() {
+                    return Ok(resp);
+                }
+                match serde_json::from_slice(&resp.body) {
+                    Ok(Value::Object(object)) => object,
+                    _ => return Ok(resp),
+                }
+            }
+        };
+        if let Some(Value::Object(changes)) = request
+            .body
+            .as_deref()
+            .and_then(|x| serde_json::from_slice(x).ok())
+        {
+            object.extend(changes);
+        }
+        let status = if request.method == Method::Post {
+            201
+        } else {
+            200
+        };
+        let body = serde_json::to_vec(&object).expect("JSON values always serialize");
+        Ok(response(status, body))
+    }
+}
+
+impl HttpTransport for DryRun {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>> {
+        if request.method == Method::Get {
+            return self.inner.send(request);
+        }
+        Box::pin(async move {
+            eprintln!("{}", describe(&request));
+            self.respond(request).await
+        })
+    }
+}
+
+/// `<METHOD> <path>` and the body, as they would be sent.
+fn describe(request: &HttpRequest) -> String {
+    let mut path = request.url.path().to_string();
+    if let Some(query) = request.url.query() {
+        path = format!("{path}?{query}");
+    }
+    let mut out = format!("{} {path}", request.method).yellow().to_string();
+    let Some(body) = &request.body else {
+        return out;
+    };
+    match serde_json::from_slice::<Value>(body) {
+        Ok(json) => {
+            out += "\n";
+            out += &serde_json::to_string_pretty(&json).expect("JSON values always serialize");
+        }
+        Err(_) => {
+            let content_type = request
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                .map_or("application/octet-stream", |(_, value)| value);
+            out += &format!("\n<{} bytes of {content_type}>", body.len());
+        }
+    }
+    out
+}
+
+/// What the server would answer to creating something at `path`, before the
+/// fields of the request are added.
+fn created(path: &str) -> Map<String, Value> {
+    let now = Utc::now();
+    let object = if path.ends_with("/assets/upload/") {
+        let bookmark = path
+            .split('/')
+            .rev()
+            .nth(3)
+            .and_then(|x| x.parse::<u64>().ok())
+            .unwrap_or_default();
+        json!({ "id": 0, "bookmark": bookmark, "date_created": now })
+    } else if path.ends_with("/bundles/") {
+        json!({
+            "id": 0,
+            "name": "",
+            "search": "",
+            "any_tags": "",
+            "all_tags": "",
+            "excluded_tags": "",
+            "order": 0,
+            "date_created": now,
+            "date_modified": now,
+        })
+    } else if path.ends_with("/tags/") {
+        json!({ "id": 0, "date_added": now })
+    } else {
+        json!({ "id": 0, "date_added": now, "date_modified": now })
+    };
+    match object {
+        Value::Object(object) => object,
+        _ => unreachable!("built as an object"),
+    }
+}
+
+fn response(status: u16, body: Vec<u8>) -> HttpResponse {
+    HttpResponse {
+        status,
+        headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+        body,
+    }
+}
@@ -0,0 +1,25 @@
+//! A feed of the matching bookmarks as a file, e.g. for a "links" page of a
+//! static site or for feed readers, without the server's feed token.
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+use ding_rs::Bookmark;
+
+use crate::feed::{atom, json_feed, FeedInfo};
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum FeedFormat {
+    #[default]
+    Atom,
+    /// JSON Feed 1.1
+    Jsonfeed,
+}
+
+/// The feed of `bookmarks`, in their order, as it should be written.
+pub fn render(format: FeedFormat, info: &FeedInfo, bookmarks: &[Bookmark]) -> Result<String> {
+    Ok(match format {
+        FeedFormat::Atom => atom(info, bookmarks),
+        FeedFormat::Jsonfeed => serde_json::to_string_pretty(&json_feed(info, bookmarks))? + "\n",
+    })
+}
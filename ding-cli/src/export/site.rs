@@ -0,0 +1,98 @@
+//! The bookmarks as a data file for static site generators, e.g. for Hugo's
+//! `data/` directory or Zola's `load_data`, so that a links page can be built
+//! from the server. Notes are left out, as the page is usually public.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use url::Url;
+
+use ding_rs::Bookmark;
+
+use crate::feed;
+use crate::group::{self, GroupBy};
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum DataFormat {
+    #[default]
+    Yaml,
+    Toml,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    id: u64,
+    title: &'a str,
+    url: &'a Url,
+    domain: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    tags: &'a [String],
+    date_added: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct Group<'a> {
+    name: &'a str,
+    bookmarks: Vec<Entry<'a>>,
+}
+
+/// Either `bookmarks` or `groups` is set. TOML has no top-level lists, so the
+/// lists are always under a key.
+#[derive(Serialize)]
+struct SiteData<'a> {
+    /// When the newest change to a bookmark was made, so that the file only
+    /// changes along with the bookmarks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated: Option<DateTime<Utc>>,
+    count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bookmarks: Option<Vec<Entry<'a>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<Group<'a>>>,
+}
+
+fn entry(bookmark: &Bookmark) -> Entry<'_> {
+    Entry {
+        id: bookmark.id,
+        title: feed::title(bookmark),
+        url: &bookmark.url,
+        domain: group::domain(bookmark),
+        description: feed::summary(bookmark),
+        tags: &bookmark.tag_names,
+        date_added: bookmark.date_added,
+    }
+}
+
+/// The data file for `bookmarks`, in their order, optionally grouped.
+pub fn render(
+    bookmarks: &[Bookmark],
+    group_by: Option<GroupBy>,
+    format: DataFormat,
+) -> Result<String> {
+    let groups = group_by.map(|by| group::group(bookmarks.to_vec(), by));
+    let data = SiteData {
+        updated: bookmarks.iter().map(|x| x.date_modified).max(),
+        count: bookmarks.len(),
+        bookmarks: groups
+            .is_none()
+            .then(|| bookmarks.iter().map(entry).collect()),
+        groups: groups.as_ref().map(|groups| {
+            groups
+                .0
+                .iter()
+                .map(|x| Group {
+                    name: &x.name,
+                    bookmarks: x.bookmarks.iter().map(entry).collect(),
+                })
+                .collect()
+        }),
+    };
+    Ok(match format {
+        DataFormat::Yaml => serde_yaml::to_string(&data)?,
+        DataFormat::Toml => toml::to_string_pretty(&data)?,
+        DataFormat::Json => serde_json::to_string_pretty(&data)? + "\n",
+    })
+}
@@ -0,0 +1,138 @@
+//! One markdown file per bookmark with YAML frontmatter, usable as an
+//! Obsidian vault.
+//!
+//! A state file in the vault remembers which file belongs to which bookmark
+//! and its `date_modified`, so later exports only rewrite changed bookmarks.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use ding_rs::Bookmark;
+
+const STATE_FILE: &str = ".ding-export.json";
+
+#[derive(Default, Deserialize, Serialize)]
+struct State {
+    files: HashMap<u64, StateEntry>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct StateEntry {
+    file: String,
+    date_modified: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct Frontmatter<'a> {
+    id: u64,
+    title: &'a str,
+    url: &'a Url,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    tags: &'a [String],
+    unread: bool,
+    shared: bool,
+    archived: bool,
+    date_added: DateTime<Utc>,
+    date_modified: DateTime<Utc>,
+}
+
+#[derive(Default)]
+pub struct VaultReport {
+    pub written: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+fn title(bookmark: &Bookmark) -> String {
+    bookmark
+        .title
+        .clone()
+        .or(bookmark.website_title.clone())
+        .unwrap_or(bookmark.url.to_string())
+}
+
+fn file_name(bookmark: &Bookmark) -> String {
+    let slug = title(bookmark)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let slug = slug.chars().take(60).collect::<String>();
+    let slug = slug.trim_end_matches('-');
+    format!("{slug}-{}.md", bookmark.id)
+}
+
+fn render(bookmark: &Bookmark) -> Result<String> {
+    let title = title(bookmark);
+    let frontmatter = serde_yaml::to_string(&Frontmatter {
+        id: bookmark.id,
+        title: &title,
+        url: &bookmark.url,
+        description: bookmark
+            .description
+            .as_deref()
+            .or(bookmark.website_description.as_deref()),
+        tags: &bookmark.tag_names,
+        unread: bookmark.unread,
+        shared: bookmark.shared,
+        archived: bookmark.is_archived,
+        date_added: bookmark.date_added,
+        date_modified: bookmark.date_modified,
+    })?;
+    let notes = bookmark.notes.as_deref().unwrap_or_default();
+    Ok(format!("---\n{frontmatter}---\n\n{notes}\n"))
+}
+
+/// Export `bookmarks` into `dir`. Unless `full` is set, bookmarks unchanged
+/// since the previous export are skipped. With `prune`, files of bookmarks no
+/// longer in `bookmarks` are deleted.
+pub fn export(bookmarks: &[Bookmark], dir: &Path, full: bool, prune: bool) -> Result<VaultReport> {
+    std::fs::create_dir_all(dir)?;
+    let state_path = dir.join(STATE_FILE);
+    let mut state: State = match std::fs::read(&state_path) {
+        Ok(data) => serde_json::from_slice(&data)?,
+        Err(_) => State::default(),
+    };
+    let mut report = VaultReport::default();
+    for bookmark in bookmarks {
+        let name = file_name(bookmark);
+        if let Some(entry) = state.files.get(&bookmark.id) {
+            if !full && entry.date_modified >= bookmark.date_modified && dir.join(&name).exists() {
+                report.unchanged += 1;
+                continue;
+            }
+            if entry.file != name {
+                let _ = std::fs::remove_file(dir.join(&entry.file));
+            }
+        }
+        std::fs::write(dir.join(&name), render(bookmark)?)?;
+        state.files.insert(
+            bookmark.id,
+            StateEntry {
+                file: name,
+                date_modified: bookmark.date_modified,
+            },
+        );
+        report.written += 1;
+    }
+    if prune {
+        let ids = bookmarks.iter().map(|x| x.id).collect::<Vec<_>>();
+        state.files.retain(|id, entry| {
+            if ids.contains(id) {
+                return true;
+            }
+            let _ = std::fs::remove_file(dir.join(&entry.file));
+            report.removed += 1;
+            false
+        });
+    }
+    std::fs::write(state_path, serde_json::to_vec_pretty(&state)?)?;
+    Ok(report)
+}
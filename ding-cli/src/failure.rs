@@ -0,0 +1,210 @@
+//! Reporting errors to scripts. Every failure falls in a class with its own
+//! exit code, and with a JSON output format the error is printed as JSON on
+//! stderr:
+//!
+//! ```json
+//! {"error": {"kind": "unauthorized", "status": 401, "exit_code": 3, "message": "..."}}
+//! ```
+
+use std::fmt;
+use std::process::ExitCode;
+
+use crossterm::style::Stylize;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use ding_rs::errors::DingError;
+
+use crate::output::OutputFormat;
+
+/// The exit codes are stable, so wrappers can rely on them.
+pub const EXIT_CODES: &str = "\
+Exit codes:
+  0  success
+  1  other errors
+  2  invalid arguments or input
+  3  the server rejected the token
+  4  the bookmark or other object was not found
+  5  the server could not be reached
+  6  the server failed or sent an invalid response
+  7  some items of a bulk operation failed";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Other,
+    Usage,
+    Unauthorized,
+    NotFound,
+    Network,
+    Server,
+    Partial,
+}
+
+/// An error of the CLI itself, with its class given explicitly.
+#[derive(Debug)]
+pub struct Failure {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl Failure {
+    pub fn usage(message: impl Into<String>) -> Self {
+        Self {
+            kind: ErrorKind::Usage,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            kind: ErrorKind::NotFound,
+            message: message.into(),
+        }
+    }
+
+    /// `failed` of `total` items of a bulk operation failed.
+    pub fn partial(failed: usize, total: usize, items: &str) -> Self {
+        Self {
+            kind: ErrorKind::Partial,
+            message: format!("{failed} of {total} {items} failed"),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+impl ErrorKind {
+    pub fn of(err: &anyhow::Error) -> Self {
+        if let Some(failure) = err.chain().find_map(|x| x.downcast_ref::<Failure>()) {
+            return failure.kind;
+        }
+        let Some(err) = err.chain().find_map(|x| x.downcast_ref::<DingError>()) else {
+            return match err.chain().any(is_invalid_input) {
+                true => ErrorKind::Usage,
+                false => ErrorKind::Other,
+            };
+        };
+        match err {
+            DingError::Transport { .. } => ErrorKind::Network,
+            DingError::InvalidRequest { .. } => ErrorKind::Usage,
+            DingError::Status { status, .. } => match status {
+                400 => ErrorKind::Usage,
+                401 | 403 => ErrorKind::Unauthorized,
+                404 => ErrorKind::NotFound,
+                500.. => ErrorKind::Server,
+                _ => ErrorKind::Other,
+            },
+            DingError::Decode { .. } => ErrorKind::Server,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::Unauthorized => 3,
+            ErrorKind::NotFound => 4,
+            ErrorKind::Network => 5,
+            ErrorKind::Server => 6,
+            ErrorKind::Partial => 7,
+        }
+    }
+}
+
+/// Whether `err` is a file or stdin the user gave failing to parse.
+fn is_invalid_input(err: &(dyn std::error::Error + 'static)) -> bool {
+    err.downcast_ref::<serde_json::Error>()
+        .is_some_and(|x| !x.is_io())
+        || err.is::<serde_yaml::Error>()
+        || err.is::<toml::de::Error>()
+        || err.is::<url::ParseError>()
+        || err.is::<chrono::ParseError>()
+}
+
+/// Print `err` in the style of `format` and give the exit code of its class.
+pub fn report(err: &anyhow::Error, format: OutputFormat) -> ExitCode {
+    let kind = ErrorKind::of(err);
+    match format {
+        OutputFormat::Json | OutputFormat::FlattenJson => eprintln!("{}", to_json(err)),
+        _ => eprintln!("{} {}", "Error:".red(), message(err)),
+    }
+    ExitCode::from(kind.exit_code())
+}
+
+/// The `{"error": {...}}` object printed for `err` with a JSON output format.
+pub fn to_json(err: &anyhow::Error) -> Value {
+    let kind = ErrorKind::of(err);
+    let status = err
+        .chain()
+        .find_map(|x| x.downcast_ref::<DingError>())
+        .and_then(|x| x.status());
+    json!({
+        "error": {
+            "kind": kind,
+            "status": status,
+            "exit_code": kind.exit_code(),
+            "message": message(err),
+        }
+    })
+}
+
+/// `err` and its causes, leaving out causes its message already includes.
+pub fn message(err: &anyhow::Error) -> String {
+    let mut parts: Vec<String> = vec![];
+    for cause in err.chain() {
+        let text = cause.to_string();
+        if !parts.last().is_some_and(|x| x.ends_with(&text)) {
+            parts.push(text);
+        }
+    }
+    parts.join(": ")
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn missing_credentials_are_usage_errors() {
+        let err = anyhow::Error::from(Failure::usage("No server given, set --host or DING_HOST"));
+        assert_eq!(ErrorKind::of(&err).exit_code(), 2);
+        assert_eq!(
+            to_json(&err),
+            json!({
+                "error": {
+                    "kind": "usage",
+                    "status": null,
+                    "exit_code": 2,
+                    "message": "No server given, set --host or DING_HOST",
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_input_is_a_usage_error() {
+        let err = serde_json::from_str::<Value>("{bad")
+            .context("Invalid JSON on stdin")
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Usage);
+        let err = toml::from_str::<Value>("= 1").unwrap_err();
+        assert_eq!(ErrorKind::of(&err.into()), ErrorKind::Usage);
+    }
+
+    #[test]
+    fn other_errors_keep_exit_code_1() {
+        let err = anyhow::anyhow!("Offline cache is empty, run `sync` first");
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Other);
+        assert_eq!(to_json(&err)["error"]["exit_code"], 1);
+    }
+}
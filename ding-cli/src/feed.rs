@@ -0,0 +1,117 @@
+//! Atom and JSON Feed documents built from bookmarks, for feed readers and
+//! dashboards that should not hold the API token.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use url::Url;
+
+use ding_rs::Bookmark;
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum FeedFormat {
+    /// The server's own RSS feed, read with the feed token
+    #[default]
+    Rss,
+    Atom,
+    /// JSON Feed 1.1
+    Json,
+}
+
+/// What a feed is about; `link` is the server's web UI.
+pub struct FeedInfo {
+    pub title: String,
+    pub link: Url,
+}
+
+pub fn title(bookmark: &Bookmark) -> &str {
+    bookmark
+        .title
+        .as_deref()
+        .or(bookmark.website_title.as_deref())
+        .unwrap_or(bookmark.url.as_str())
+}
+
+pub fn summary(bookmark: &Bookmark) -> Option<&str> {
+    bookmark
+        .description
+        .as_deref()
+        .or(bookmark.website_description.as_deref())
+}
+
+/// A stable id for `bookmark` on the server at `link`.
+fn entry_id(link: &Url, bookmark: &Bookmark) -> String {
+    format!("{link}#bookmark-{}", bookmark.id)
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn atom(info: &FeedInfo, bookmarks: &[Bookmark]) -> String {
+    let updated = bookmarks
+        .iter()
+        .map(|x| x.date_modified)
+        .max()
+        .unwrap_or_else(Utc::now);
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out += "<feed xmlns=\"http://www.w3.org/2005/Atom\">\n";
+    out += &format!("  <title>{}</title>\n", escape(&info.title));
+    out += &format!("  <id>{}</id>\n", escape(info.link.as_str()));
+    out += &format!("  <link href=\"{}\"/>\n", escape(info.link.as_str()));
+    out += &format!("  <updated>{}</updated>\n", timestamp(updated));
+    out += "  <author><name>ding</name></author>\n";
+    for bookmark in bookmarks {
+        out += "  <entry>\n";
+        out += &format!("    <title>{}</title>\n", escape(title(bookmark)));
+        out += &format!("    <id>{}</id>\n", escape(&entry_id(&info.link, bookmark)));
+        out += &format!("    <link href=\"{}\"/>\n", escape(bookmark.url.as_str()));
+        out += &format!(
+            "    <published>{}</published>\n",
+            timestamp(bookmark.date_added)
+        );
+        out += &format!(
+            "    <updated>{}</updated>\n",
+            timestamp(bookmark.date_modified)
+        );
+        if let Some(summary) = summary(bookmark) {
+            out += &format!("    <summary>{}</summary>\n", escape(summary));
+        }
+        for tag in &bookmark.tag_names {
+            out += &format!("    <category term=\"{}\"/>\n", escape(tag));
+        }
+        out += "  </entry>\n";
+    }
+    out += "</feed>\n";
+    out
+}
+
+pub fn json_feed(info: &FeedInfo, bookmarks: &[Bookmark]) -> Value {
+    let items = bookmarks
+        .iter()
+        .map(|x| {
+            json!({
+                "id": entry_id(&info.link, x),
+                "url": x.url,
+                "title": title(x),
+                "content_text": summary(x).unwrap_or_default(),
+                "date_published": timestamp(x.date_added),
+                "date_modified": timestamp(x.date_modified),
+                "tags": x.tag_names,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": info.title,
+        "home_page_url": info.link,
+        "items": items,
+    })
+}
@@ -0,0 +1,152 @@
+//! Listing bookmarks under section headers, e.g. by tag or by month.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::Local;
+use clap::ValueEnum;
+use crossterm::style::Stylize;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::{Map, Value};
+
+use ding_rs::Bookmark;
+
+use crate::output::{markdown_item, to_csv, to_tsv, ToOutput};
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum GroupBy {
+    /// Under each of its tags; untagged bookmarks come last
+    Tag,
+    /// By host, without `www.`
+    Domain,
+    /// By the month they were added, newest first
+    Month,
+}
+
+pub struct BookmarkGroup {
+    pub name: String,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// Bookmark groups, serialized as an object of lists keyed by group name.
+pub struct Groups(pub Vec<BookmarkGroup>);
+
+const UNTAGGED: &str = "(untagged)";
+
+/// Group `bookmarks`, keeping their order within each group.
+pub fn group(bookmarks: Vec<Bookmark>, by: GroupBy) -> Groups {
+    let mut groups: BTreeMap<String, Vec<Bookmark>> = BTreeMap::new();
+    let mut untagged = vec![];
+    for bookmark in bookmarks {
+        let names = match by {
+            GroupBy::Tag => bookmark.tag_names.clone(),
+            GroupBy::Domain => vec![domain(&bookmark)],
+            GroupBy::Month => vec![bookmark
+                .date_added
+                .with_timezone(&Local)
+                .format("%Y-%m")
+                .to_string()],
+        };
+        if names.is_empty() {
+            untagged.push(bookmark);
+            continue;
+        }
+        for name in names {
+            groups.entry(name).or_default().push(bookmark.clone());
+        }
+    }
+    let mut groups = groups
+        .into_iter()
+        .map(|(name, bookmarks)| BookmarkGroup { name, bookmarks })
+        .collect::<Vec<_>>();
+    if let GroupBy::Month = by {
+        groups.reverse();
+    }
+    if !untagged.is_empty() {
+        groups.push(BookmarkGroup {
+            name: UNTAGGED.to_string(),
+            bookmarks: untagged,
+        });
+    }
+    Groups(groups)
+}
+
+/// Host of the bookmark, lowercase and without `www.`.
+pub fn domain(bookmark: &Bookmark) -> String {
+    bookmark
+        .url
+        .host_str()
+        .map(|x| x.trim_start_matches("www.").to_lowercase())
+        .unwrap_or_default()
+}
+
+impl Serialize for Groups {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for group in &self.0 {
+            map.serialize_entry(&group.name, &group.bookmarks)?;
+        }
+        map.end()
+    }
+}
+
+impl ToOutput for Groups {
+    fn to_human_format(&self) -> Result<String> {
+        Ok(self
+            .0
+            .iter()
+            .map(|x| {
+                let header = format!(
+                    "{} {}",
+                    x.name.clone().yellow().bold(),
+                    format!("({})", x.bookmarks.len()).dark_grey()
+                );
+                Ok(format!("{header}\n{}", x.bookmarks.to_human_format()?))
+            })
+            .collect::<Result<Vec<String>>>()?
+            .join("\n\n"))
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(self.rows()?)
+    }
+    /// One row per bookmark and group, with the group name first.
+    fn to_tsv_format(&self) -> Result<String> {
+        to_tsv(self.rows()?)
+    }
+    /// A section per group, with the bookmarks as a list of links.
+    fn to_markdown_format(&self) -> Result<String> {
+        Ok(self
+            .0
+            .iter()
+            .map(|x| {
+                let items = x.bookmarks.iter().map(markdown_item).collect::<Vec<_>>();
+                format!(
+                    "## {} ({})\n\n{}",
+                    x.name,
+                    x.bookmarks.len(),
+                    items.join("\n")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n"))
+    }
+}
+
+impl Groups {
+    /// The bookmarks as flat records, one per bookmark and group, with a
+    /// `group` field first.
+    fn rows(&self) -> Result<Value> {
+        let mut rows = vec![];
+        for group in &self.0 {
+            for bookmark in &group.bookmarks {
+                let mut row = Map::new();
+                row.insert("group".to_string(), Value::String(group.name.clone()));
+                if let Value::Object(fields) = serde_json::to_value(bookmark)? {
+                    row.extend(fields);
+                }
+                rows.push(Value::Object(row));
+            }
+        }
+        Ok(Value::Array(rows))
+    }
+}
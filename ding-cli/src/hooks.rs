@@ -0,0 +1,130 @@
+//! Shell commands and webhooks run after the CLI changed a bookmark, set up
+//! in the `[hooks]` section of the config. A failing hook is reported but does
+//! not fail the command; the change is already made.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use crossterm::style::Stylize;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use ding_rs::Bookmark;
+
+use crate::config::{HookConfig, HooksConfig};
+
+/// How long an exec hook may run before it is killed.
+pub const EXEC_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    Add,
+    Delete,
+    Archive,
+}
+
+impl Event {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Event::Add => "add",
+            Event::Delete => "delete",
+            Event::Archive => "archive",
+        }
+    }
+}
+
+pub struct Hooks<'a> {
+    config: &'a HooksConfig,
+    client: reqwest::Client,
+}
+
+impl<'a> Hooks<'a> {
+    pub fn new(config: &'a HooksConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        Self { config, client }
+    }
+
+    /// Run the hooks of `event` for `bookmark`, one after another.
+    pub async fn run(&self, event: Event, bookmark: &Bookmark) {
+        let hooks = match event {
+            Event::Add => &self.config.on_add,
+            Event::Delete => &self.config.on_delete,
+            Event::Archive => &self.config.on_archive,
+        };
+        for hook in hooks {
+            let result = match hook {
+                HookConfig::Exec(exec) => run_command(exec, bookmark, Some(event)).await,
+                HookConfig::Url(url) => self.post(url, event, bookmark).await,
+            };
+            if let Err(err) = result {
+                eprintln!(
+                    "{} {err:#}",
+                    format!("{} hook failed:", event.as_str()).yellow()
+                );
+            }
+        }
+    }
+
+    async fn post(&self, url: &url::Url, event: Event, bookmark: &Bookmark) -> Result<()> {
+        let resp = self
+            .client
+            .post(url.clone())
+            .header("Content-Type", "application/json")
+            .header("X-Ding-Event", event.as_str())
+            .body(serde_json::to_vec(bookmark)?)
+            .send()
+            .await
+            .with_context(|| format!("Failed to POST to {url}"))?;
+        if !resp.status().is_success() {
+            bail!("{url} answered with HTTP status {}", resp.status());
+        }
+        Ok(())
+    }
+}
+
+/// Run `exec` through the shell with `bookmark` as JSON on stdin and the
+/// event, if any, in `DING_EVENT`. It is killed after [`EXEC_TIMEOUT`].
+pub async fn run_command(exec: &str, bookmark: &Bookmark, event: Option<Event>) -> Result<()> {
+    #[cfg(windows)]
+    let mut command = Command::new("cmd");
+    #[cfg(windows)]
+    command.arg("/C");
+    #[cfg(not(windows))]
+    let mut command = Command::new("sh");
+    #[cfg(not(windows))]
+    command.arg("-c");
+    if let Some(event) = event {
+        command.env("DING_EVENT", event.as_str());
+    }
+    let mut child = command
+        .arg(exec)
+        .stdin(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run `{exec}`"))?;
+    let input = serde_json::to_vec(bookmark)?;
+    let finished = async {
+        if let Some(mut stdin) = child.stdin.take() {
+            // The command may not read its input.
+            let _ = stdin.write_all(&input).await;
+        }
+        child.wait().await
+    };
+    let status = match tokio::time::timeout(EXEC_TIMEOUT, finished).await {
+        Ok(status) => status?,
+        Err(_) => bail!(
+            "`{exec}` was stopped after running for {}s",
+            EXEC_TIMEOUT.as_secs()
+        ),
+    };
+    if !status.success() {
+        bail!("`{exec}` exited with {status}");
+    }
+    Ok(())
+}
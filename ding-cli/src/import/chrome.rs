@@ -0,0 +1,148 @@
+//! Bookmarks from the `Bookmarks` JSON file of Chrome and Chromium.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Deserialize;
+use url::Url;
+
+use ding_rs::BookmarkRequest;
+
+use super::{is_importable, tag_name, Adapter};
+
+#[derive(Deserialize)]
+struct File {
+    roots: Roots,
+}
+
+#[derive(Deserialize)]
+struct Roots {
+    bookmark_bar: Option<Node>,
+    other: Option<Node>,
+    synced: Option<Node>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Node {
+    Url {
+        name: String,
+        url: String,
+    },
+    Folder {
+        name: String,
+        #[serde(default)]
+        children: Vec<Node>,
+    },
+}
+
+/// The `Bookmarks` file of the default Chrome or Chromium profile, if one can
+/// be found.
+pub fn default_path() -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    let config = dirs::config_dir()?;
+    let local = dirs::data_local_dir()?;
+    [
+        config.join("google-chrome"),
+        config.join("chromium"),
+        home.join("Library/Application Support/Google/Chrome"),
+        home.join("Library/Application Support/Chromium"),
+        local.join("Google/Chrome/User Data"),
+        local.join("Chromium/User Data"),
+    ]
+    .into_iter()
+    .map(|x| x.join("Default/Bookmarks"))
+    .find(|x| x.exists())
+}
+
+/// Bookmarks are tagged with the names of the folders they are in.
+pub struct Chrome;
+
+impl Adapter for Chrome {
+    fn parse(&self, data: &str) -> Result<Vec<BookmarkRequest>> {
+        let file: File = serde_json::from_str(data)?;
+        let mut requests = vec![];
+        for root in [file.roots.bookmark_bar, file.roots.other, file.roots.synced] {
+            // The roots themselves are not folders the user made.
+            if let Some(Node::Folder { children, .. }) = root {
+                for node in children {
+                    collect(node, &mut vec![], &mut requests);
+                }
+            }
+        }
+        Ok(requests)
+    }
+}
+
+fn collect(node: Node, folders: &mut Vec<String>, requests: &mut Vec<BookmarkRequest>) {
+    match node {
+        Node::Url { name, url } => {
+            let Ok(url) = Url::parse(&url) else {
+                return;
+            };
+            if !is_importable(&url) {
+                return;
+            }
+            requests.push(BookmarkRequest {
+                title: (!name.is_empty()).then_some(name),
+                tag_names: (!folders.is_empty()).then(|| folders.clone()),
+                ..BookmarkRequest::new(url)
+            });
+        }
+        Node::Folder { name, children } => {
+            let tag = tag_name(&name);
+            let pushed = tag.is_some();
+            folders.extend(tag);
+            for child in children {
+                collect(child, folders, requests);
+            }
+            if pushed {
+                folders.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folders_become_tags() {
+        let data = r#"{
+            "checksum": "0",
+            "roots": {
+                "bookmark_bar": {"type": "folder", "name": "Bookmarks bar", "children": [
+                    {"type": "url", "name": "Example", "url": "https://example.com/"},
+                    {"type": "folder", "name": "Dev Tools", "children": [
+                        {"type": "folder", "name": "Rust", "children": [
+                            {"type": "url", "name": "", "url": "https://docs.rs/"}
+                        ]},
+                        {"type": "url", "name": "Settings", "url": "chrome://settings/"}
+                    ]}
+                ]},
+                "other": {"type": "folder", "name": "Other bookmarks", "children": [
+                    {"type": "url", "name": "Later", "url": "https://later.example/"}
+                ]}
+            },
+            "version": 1
+        }"#;
+        assert_eq!(
+            Chrome.parse(data).unwrap(),
+            vec![
+                BookmarkRequest {
+                    title: Some("Example".to_string()),
+                    ..BookmarkRequest::new(Url::parse("https://example.com/").unwrap())
+                },
+                BookmarkRequest {
+                    tag_names: Some(vec!["Dev-Tools".to_string(), "Rust".to_string()]),
+                    ..BookmarkRequest::new(Url::parse("https://docs.rs/").unwrap())
+                },
+                BookmarkRequest {
+                    title: Some("Later".to_string()),
+                    ..BookmarkRequest::new(Url::parse("https://later.example/").unwrap())
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Bookmarks from a Firefox profile's `places.sqlite`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use url::Url;
+
+use ding_rs::BookmarkRequest;
+
+use super::{is_importable, tag_name};
+
+const TYPE_BOOKMARK: i64 = 1;
+
+const TAGS_ROOT: &str = "tags________";
+
+const ROOTS: [&str; 6] = [
+    "root________",
+    "menu________",
+    "toolbar_____",
+    "unfiled_____",
+    "mobile______",
+    TAGS_ROOT,
+];
+
+struct Item {
+    parent: i64,
+    title: Option<String>,
+    guid: String,
+}
+
+/// The `places.sqlite` of the default Firefox profile, if one can be found.
+pub fn default_path() -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    let dirs = [
+        Some(home.join(".mozilla/firefox")),
+        Some(home.join("snap/firefox/common/.mozilla/firefox")),
+        Some(home.join("Library/Application Support/Firefox/Profiles")),
+        dirs::config_dir().map(|x| x.join("Mozilla/Firefox/Profiles")),
+    ];
+    let mut profiles = dirs
+        .into_iter()
+        .flatten()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .flatten()
+        .map(|x| x.path().join("places.sqlite"))
+        .filter(|x| x.exists())
+        .collect::<Vec<_>>();
+    // Prefer the profile Firefox creates by default.
+    profiles.sort_by_key(|x| {
+        !x.parent()
+            .and_then(|x| x.file_name())
+            .is_some_and(|x| x.to_string_lossy().ends_with(".default-release"))
+    });
+    profiles.into_iter().next()
+}
+
+/// Read the bookmarks of `path`. Firefox tags always become tags; with
+/// `folders_as_tags` the names of the folders a bookmark is in are added
+/// too.
+pub fn read(path: &Path, folders_as_tags: bool) -> Result<Vec<BookmarkRequest>> {
+    // A running Firefox keeps the database locked, so read a copy.
+    let copy = std::env::temp_dir().join(format!("ding-places-{}.sqlite", std::process::id()));
+    std::fs::copy(path, &copy).with_context(|| format!("Cannot read {}", path.display()))?;
+    let wal = path.with_extension("sqlite-wal");
+    if wal.exists() {
+        std::fs::copy(&wal, copy.with_extension("sqlite-wal"))?;
+    }
+    let result = read_copy(&copy, folders_as_tags);
+    let _ = std::fs::remove_file(copy.with_extension("sqlite-wal"));
+    let _ = std::fs::remove_file(&copy);
+    result
+}
+
+fn read_copy(path: &Path, folders_as_tags: bool) -> Result<Vec<BookmarkRequest>> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+    bookmarks(&conn, folders_as_tags)
+}
+
+fn bookmarks(conn: &Connection, folders_as_tags: bool) -> Result<Vec<BookmarkRequest>> {
+    let mut items = HashMap::new();
+    let mut stmt = conn.prepare("SELECT id, parent, title, guid FROM moz_bookmarks")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            Item {
+                parent: row.get(1)?,
+                title: row.get(2)?,
+                guid: row.get(3)?,
+            },
+        ))
+    })?;
+    for row in rows {
+        let (id, item) = row?;
+        items.insert(id, item);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT b.parent, b.title, p.id, p.url FROM moz_bookmarks b
+         JOIN moz_places p ON p.id = b.fk
+         WHERE b.type = ?1
+         ORDER BY b.dateAdded",
+    )?;
+    let rows = stmt.query_map([TYPE_BOOKMARK], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, Option<String>>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut order = vec![];
+    let mut requests: HashMap<i64, (BookmarkRequest, Vec<String>)> = HashMap::new();
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in rows {
+        let (parent, title, place, url) = row?;
+        let Ok(url) = Url::parse(&url) else {
+            continue;
+        };
+        if !is_importable(&url) {
+            continue;
+        }
+        let folder = items.get(&parent);
+        let is_tag = folder
+            .and_then(|x| items.get(&x.parent))
+            .is_some_and(|x| x.guid == TAGS_ROOT);
+        if is_tag {
+            // Tagging an URL adds a bookmark for it in the tag's folder.
+            let tag = folder.and_then(|x| x.title.as_deref()).and_then(tag_name);
+            tags.entry(place).or_default().extend(tag);
+            continue;
+        }
+        let (_, names) = requests.entry(place).or_insert_with(|| {
+            order.push(place);
+            let request = BookmarkRequest {
+                title: title.filter(|x| !x.is_empty()),
+                ..BookmarkRequest::new(url)
+            };
+            (request, vec![])
+        });
+        if folders_as_tags {
+            names.extend(folders(&items, parent));
+        }
+    }
+
+    Ok(order
+        .into_iter()
+        .filter_map(|place| {
+            let (request, mut names) = requests.remove(&place)?;
+            names.extend(tags.remove(&place).unwrap_or_default());
+            names.sort();
+            names.dedup();
+            Some(BookmarkRequest {
+                tag_names: (!names.is_empty()).then_some(names),
+                ..request
+            })
+        })
+        .collect())
+}
+
+/// Names of the folders containing `parent`, without the built-in roots.
+fn folders(items: &HashMap<i64, Item>, mut parent: i64) -> Vec<String> {
+    let mut names = vec![];
+    while let Some(item) = items.get(&parent) {
+        if ROOTS.contains(&item.guid.as_str()) {
+            break;
+        }
+        names.extend(item.title.as_deref().and_then(tag_name));
+        parent = item.parent;
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A profile with a bookmark in the toolbar that is also tagged, one in a
+    /// folder of the menu, and a `place:` query Firefox adds by itself.
+    fn places() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT);
+             CREATE TABLE moz_bookmarks (
+                 id INTEGER PRIMARY KEY, type INTEGER, fk INTEGER, parent INTEGER,
+                 title TEXT, guid TEXT, dateAdded INTEGER
+             );
+             INSERT INTO moz_places VALUES
+                 (1, 'https://example.com/'),
+                 (2, 'https://docs.rs/'),
+                 (3, 'place:sort=8&maxResults=10');
+             INSERT INTO moz_bookmarks VALUES
+                 (1, 2, NULL, 0, '', 'root________', 0),
+                 (2, 2, NULL, 1, 'menu', 'menu________', 0),
+                 (3, 2, NULL, 1, 'toolbar', 'toolbar_____', 0),
+                 (4, 2, NULL, 1, 'tags', 'tags________', 0),
+                 (5, 2, NULL, 2, 'Dev Tools', 'folder000001', 0),
+                 (6, 2, NULL, 4, 'rust', 'tagfolder001', 0),
+                 (10, 1, 1, 3, 'Example', 'bookmark0001', 1),
+                 (11, 1, 2, 5, '', 'bookmark0002', 2),
+                 (12, 1, 1, 6, NULL, 'bookmark0003', 3),
+                 (13, 1, 3, 3, 'Most Visited', 'bookmark0004', 4);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn expected(docs_tags: Option<Vec<String>>) -> Vec<BookmarkRequest> {
+        vec![
+            BookmarkRequest {
+                title: Some("Example".to_string()),
+                tag_names: Some(vec!["rust".to_string()]),
+                ..BookmarkRequest::new(Url::parse("https://example.com/").unwrap())
+            },
+            BookmarkRequest {
+                tag_names: docs_tags,
+                ..BookmarkRequest::new(Url::parse("https://docs.rs/").unwrap())
+            },
+        ]
+    }
+
+    #[test]
+    fn reads_bookmarks_and_tags() {
+        assert_eq!(bookmarks(&places(), false).unwrap(), expected(None));
+    }
+
+    #[test]
+    fn folders_as_tags() {
+        assert_eq!(
+            bookmarks(&places(), true).unwrap(),
+            expected(Some(vec!["Dev-Tools".to_string()]))
+        );
+    }
+}
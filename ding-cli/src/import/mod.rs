@@ -0,0 +1,49 @@
+//! Readers for bookmarks exported by other tools. Each one turns its source
+//! into plain [`BookmarkRequest`]s; creating them is left to the caller.
+//!
+//! [`BookmarkRequest`]: ding_rs::BookmarkRequest
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use ding_rs::BookmarkRequest;
+
+pub mod chrome;
+pub mod firefox;
+pub mod netscape;
+pub mod resume;
+pub mod shaarli;
+pub mod shiori;
+pub mod wallabag;
+
+/// An export file format of another tool.
+pub trait Adapter {
+    /// Parse the contents of an export file.
+    fn parse(&self, data: &str) -> Result<Vec<BookmarkRequest>>;
+}
+
+/// Read the export file at `path` with `adapter`.
+pub fn read_file(adapter: &dyn Adapter, path: &Path) -> Result<Vec<BookmarkRequest>> {
+    let data =
+        std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    adapter.parse(&data)
+}
+
+/// Turn a folder or tag name from another tool into a linkding tag, which
+/// cannot contain whitespace.
+pub fn tag_name(name: &str) -> Option<String> {
+    let tag = name.split_whitespace().collect::<Vec<_>>().join("-");
+    (!tag.is_empty()).then_some(tag)
+}
+
+/// Whether the export file looks like JSON rather than HTML.
+pub fn is_json(data: &str) -> bool {
+    data.trim_start().starts_with(['[', '{'])
+}
+
+/// Whether a URL is worth importing; skips `place:`, `javascript:` and the
+/// like.
+pub fn is_importable(url: &url::Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+}
@@ -0,0 +1,152 @@
+//! The Netscape bookmark file format, the HTML export of browsers, Shaarli
+//! and Shiori.
+
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use regex::Regex;
+use url::Url;
+
+use ding_rs::BookmarkRequest;
+
+use super::{is_importable, tag_name, Adapter};
+
+static LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)<DT><A\s+([^>]*)>(.*?)</A>"#).unwrap());
+static FOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)<DT><H3[^>]*>(.*?)</H3>"#).unwrap());
+static ATTR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"([A-Za-z_]+)\s*=\s*"([^"]*)""#).unwrap());
+
+/// Folders become tags, as do the comma separated `TAGS` of a link. `PRIVATE`
+/// and `TOREAD` map to shared and unread, and a `<DD>` following a link
+/// becomes its notes.
+pub struct Netscape;
+
+impl Adapter for Netscape {
+    fn parse(&self, data: &str) -> Result<Vec<BookmarkRequest>> {
+        let mut requests: Vec<BookmarkRequest> = vec![];
+        let mut folders: Vec<Option<String>> = vec![];
+        let mut pending_folder = None;
+        // Whether the last link can still receive a `<DD>` description.
+        let mut open_link = false;
+        for line in data.lines() {
+            let trimmed = line.trim();
+            let upper = trimmed.to_uppercase();
+            if let Some(captures) = FOLDER.captures(trimmed) {
+                pending_folder = Some(tag_name(&decode(&captures[1])));
+                open_link = false;
+            } else if upper.starts_with("<DL") {
+                folders.push(pending_folder.take().flatten());
+            } else if upper.starts_with("</DL") {
+                folders.pop();
+                open_link = false;
+            } else if let Some(captures) = LINK.captures(trimmed) {
+                open_link = false;
+                let Some(request) = link(&captures[1], &captures[2], &folders) else {
+                    continue;
+                };
+                requests.push(request);
+                open_link = true;
+            } else if upper.starts_with("<DD>") && open_link {
+                let notes = decode(&trimmed[4..]);
+                if let Some(request) = requests.last_mut() {
+                    request.notes = (!notes.is_empty()).then_some(notes);
+                }
+            } else if open_link && !trimmed.is_empty() && !upper.starts_with('<') {
+                // Multi-line descriptions continue until the next element.
+                if let Some(notes) = requests.last_mut().and_then(|x| x.notes.as_mut()) {
+                    notes.push('\n');
+                    notes.push_str(&decode(trimmed));
+                }
+            }
+        }
+        Ok(requests)
+    }
+}
+
+fn link(attrs: &str, title: &str, folders: &[Option<String>]) -> Option<BookmarkRequest> {
+    let mut url = None;
+    let mut tags = folders.iter().flatten().cloned().collect::<Vec<_>>();
+    let mut shared = None;
+    let mut unread = None;
+    for captures in ATTR.captures_iter(attrs) {
+        let value = decode(&captures[2]);
+        match captures[1].to_uppercase().as_str() {
+            "HREF" => url = Url::parse(&value).ok(),
+            "TAGS" => tags.extend(value.split([',', ' ']).filter_map(tag_name)),
+            "PRIVATE" => shared = Some(value == "0"),
+            "TOREAD" => unread = Some(value == "1"),
+            _ => {}
+        }
+    }
+    let url = url.filter(is_importable)?;
+    let title = decode(title);
+    tags.sort();
+    tags.dedup();
+    Some(BookmarkRequest {
+        title: (!title.is_empty()).then_some(title),
+        tag_names: (!tags.is_empty()).then_some(tags),
+        shared,
+        unread,
+        ..BookmarkRequest::new(url)
+    })
+}
+
+/// Decode the few HTML entities exporters produce.
+fn decode(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#039;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><A HREF="https://example.com/" ADD_DATE="1700000000" PRIVATE="0" TOREAD="1" TAGS="rust,cli">Example &amp; Co</A>
+    <DD>First line
+    second line
+    <DT><H3>Dev Tools</H3>
+    <DL><p>
+        <DT><A HREF="https://docs.rs/" PRIVATE="1">Docs</A>
+        <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+    </DL><p>
+    <DT><A HREF="https://blank.example/"></A>
+</DL><p>
+"#;
+
+    #[test]
+    fn parses_links_folders_and_notes() {
+        let requests = Netscape.parse(EXPORT).unwrap();
+        assert_eq!(
+            requests,
+            vec![
+                BookmarkRequest {
+                    title: Some("Example & Co".to_string()),
+                    notes: Some("First line\nsecond line".to_string()),
+                    tag_names: Some(vec!["cli".to_string(), "rust".to_string()]),
+                    shared: Some(true),
+                    unread: Some(true),
+                    ..BookmarkRequest::new(Url::parse("https://example.com/").unwrap())
+                },
+                BookmarkRequest {
+                    title: Some("Docs".to_string()),
+                    tag_names: Some(vec!["Dev-Tools".to_string()]),
+                    shared: Some(false),
+                    ..BookmarkRequest::new(Url::parse("https://docs.rs/").unwrap())
+                },
+                BookmarkRequest::new(Url::parse("https://blank.example/").unwrap()),
+            ]
+        );
+    }
+}
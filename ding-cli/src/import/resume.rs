@@ -0,0 +1,129 @@
+//! Where an interrupted import stopped, so that `import --resume` can skip
+//! the rows created already. Export files are told apart by their hash;
+//! importing a changed file starts from the first row. Browser profiles are
+//! rewritten by the browser all the time, so they are told apart by their
+//! path, and the import resumes after the last bookmark it got through.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use ding_rs::BookmarkRequest;
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    file: PathBuf,
+    /// The leading rows that were imported, or skipped as already on the
+    /// server.
+    rows: usize,
+    /// The URL of the last of those rows, for browser profiles.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last: Option<Url>,
+}
+
+#[derive(Clone)]
+pub struct Checkpoint {
+    file: PathBuf,
+    hash: String,
+    /// The URLs of the rows of a browser profile, empty for export files.
+    urls: Vec<Url>,
+    /// The rows to skip, from an earlier run.
+    pub skip: usize,
+    /// Whether an earlier run was found but cannot be resumed, as the
+    /// bookmark it stopped after is gone from the profile.
+    pub lost: bool,
+}
+
+fn sha256(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|x| format!("{x:02x}"))
+        .collect()
+}
+
+impl Checkpoint {
+    /// The checkpoint for importing the export file `file`, starting after
+    /// the rows an earlier run got through if `resume` is set.
+    pub fn new(file: &Path, resume: bool) -> Result<Self> {
+        let data =
+            std::fs::read(file).with_context(|| format!("Cannot read {}", file.display()))?;
+        let mut checkpoint = Self {
+            file: file.to_path_buf(),
+            hash: sha256(&data),
+            urls: vec![],
+            skip: 0,
+            lost: false,
+        };
+        if resume {
+            checkpoint.skip = checkpoint.load().map_or(0, |x| x.rows);
+        }
+        Ok(checkpoint)
+    }
+
+    /// The checkpoint for importing `requests` from the browser profile at
+    /// `file`, starting after the bookmark an earlier run stopped at if
+    /// `resume` is set.
+    pub fn for_profile(file: &Path, requests: &[BookmarkRequest], resume: bool) -> Self {
+        let path = std::fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+        let mut checkpoint = Self {
+            file: file.to_path_buf(),
+            hash: sha256(path.to_string_lossy().as_bytes()),
+            urls: requests.iter().filter_map(|x| x.url.clone()).collect(),
+            skip: 0,
+            lost: false,
+        };
+        if let Some(last) = resume
+            .then(|| checkpoint.load())
+            .flatten()
+            .and_then(|x| x.last)
+        {
+            match checkpoint.urls.iter().position(|x| *x == last) {
+                Some(row) => checkpoint.skip = row + 1,
+                None => checkpoint.lost = true,
+            }
+        }
+        checkpoint
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        dirs::state_dir().or_else(dirs::data_local_dir).map(|x| {
+            x.join("ding")
+                .join("imports")
+                .join(format!("{}.json", self.hash))
+        })
+    }
+
+    fn load(&self) -> Option<State> {
+        let data = std::fs::read(self.path()?).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// Note that `rows` rows after the skipped ones are done. Failing to
+    /// write is not an error; a later `--resume` just starts earlier.
+    pub fn save(&self, rows: usize) {
+        let Some(path) = self.path() else {
+            return;
+        };
+        let rows = self.skip + rows;
+        let state = State {
+            file: self.file.clone(),
+            rows,
+            last: rows.checked_sub(1).and_then(|x| self.urls.get(x)).cloned(),
+        };
+        if let (Some(dir), Ok(data)) = (path.parent(), serde_json::to_vec(&state)) {
+            if std::fs::create_dir_all(dir).is_ok() {
+                let _ = std::fs::write(path, data);
+            }
+        }
+    }
+
+    /// Forget the import once every row is done.
+    pub fn clear(&self) {
+        if let Some(path) = self.path() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
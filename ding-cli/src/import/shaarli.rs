@@ -0,0 +1,104 @@
+//! Shaarli exports, either the HTML export or the JSON of its `/api/v1/links`.
+
+use anyhow::Result;
+use serde::Deserialize;
+use url::Url;
+
+use ding_rs::BookmarkRequest;
+
+use super::netscape::Netscape;
+use super::{is_importable, is_json, tag_name, Adapter};
+
+#[derive(Deserialize)]
+struct Link {
+    url: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    private: bool,
+}
+
+/// Shaarli descriptions are notes written by the user, so they become notes.
+pub struct Shaarli;
+
+impl Adapter for Shaarli {
+    fn parse(&self, data: &str) -> Result<Vec<BookmarkRequest>> {
+        if !is_json(data) {
+            return Netscape.parse(data);
+        }
+        let links: Vec<Link> = serde_json::from_str(data)?;
+        Ok(links
+            .into_iter()
+            .filter_map(|link| {
+                let url = Url::parse(&link.url).ok().filter(is_importable)?;
+                let tags = link
+                    .tags
+                    .iter()
+                    .filter_map(|x| tag_name(x))
+                    .collect::<Vec<_>>();
+                Some(BookmarkRequest {
+                    title: (!link.title.is_empty()).then_some(link.title),
+                    notes: (!link.description.is_empty()).then_some(link.description),
+                    tag_names: (!tags.is_empty()).then_some(tags),
+                    shared: Some(!link.private),
+                    ..BookmarkRequest::new(url)
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_api_json() {
+        let data = r#"[
+            {"id": 1, "url": "https://example.com/", "title": "Example",
+             "description": "My note", "tags": ["rust", "command line"], "private": true},
+            {"id": 2, "url": "?abc123", "title": "Note", "private": false},
+            {"id": 3, "url": "https://plain.example/"}
+        ]"#;
+        assert_eq!(
+            Shaarli.parse(data).unwrap(),
+            vec![
+                BookmarkRequest {
+                    title: Some("Example".to_string()),
+                    notes: Some("My note".to_string()),
+                    tag_names: Some(vec!["rust".to_string(), "command-line".to_string()]),
+                    shared: Some(false),
+                    ..BookmarkRequest::new(Url::parse("https://example.com/").unwrap())
+                },
+                BookmarkRequest {
+                    shared: Some(true),
+                    ..BookmarkRequest::new(Url::parse("https://plain.example/").unwrap())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_html_export() {
+        let data = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+<DT><A HREF="https://example.com/" PRIVATE="1" TAGS="rust">Example</A>
+<DD>My note
+</DL><p>
+"#;
+        assert_eq!(
+            Shaarli.parse(data).unwrap(),
+            vec![BookmarkRequest {
+                title: Some("Example".to_string()),
+                notes: Some("My note".to_string()),
+                tag_names: Some(vec!["rust".to_string()]),
+                shared: Some(false),
+                ..BookmarkRequest::new(Url::parse("https://example.com/").unwrap())
+            }]
+        );
+    }
+}
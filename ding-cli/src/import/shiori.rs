@@ -0,0 +1,110 @@
+//! Shiori exports, either the HTML of `shiori export` or the JSON its API
+//! returns for bookmarks.
+
+use anyhow::Result;
+use serde::Deserialize;
+use url::Url;
+
+use ding_rs::BookmarkRequest;
+
+use super::netscape::Netscape;
+use super::{is_importable, is_json, tag_name, Adapter};
+
+#[derive(Deserialize)]
+struct Bookmark {
+    url: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    excerpt: String,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    #[serde(default)]
+    public: u8,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    name: String,
+}
+
+/// The API wraps bookmarks in an object, the database dump does not.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum File {
+    Page { bookmarks: Vec<Bookmark> },
+    List(Vec<Bookmark>),
+}
+
+/// Shiori excerpts are taken from the page, so they become descriptions.
+pub struct Shiori;
+
+impl Adapter for Shiori {
+    fn parse(&self, data: &str) -> Result<Vec<BookmarkRequest>> {
+        if !is_json(data) {
+            return Netscape.parse(data);
+        }
+        let bookmarks = match serde_json::from_str(data)? {
+            File::Page { bookmarks } => bookmarks,
+            File::List(bookmarks) => bookmarks,
+        };
+        Ok(bookmarks
+            .into_iter()
+            .filter_map(|bookmark| {
+                let url = Url::parse(&bookmark.url).ok().filter(is_importable)?;
+                let tags = bookmark
+                    .tags
+                    .iter()
+                    .filter_map(|x| tag_name(&x.name))
+                    .collect::<Vec<_>>();
+                Some(BookmarkRequest {
+                    title: (!bookmark.title.is_empty()).then_some(bookmark.title),
+                    description: (!bookmark.excerpt.is_empty()).then_some(bookmark.excerpt),
+                    tag_names: (!tags.is_empty()).then_some(tags),
+                    shared: Some(bookmark.public == 1),
+                    ..BookmarkRequest::new(url)
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expected() -> Vec<BookmarkRequest> {
+        vec![
+            BookmarkRequest {
+                title: Some("Example".to_string()),
+                description: Some("From the page".to_string()),
+                tag_names: Some(vec!["rust".to_string(), "to-read".to_string()]),
+                shared: Some(true),
+                ..BookmarkRequest::new(Url::parse("https://example.com/").unwrap())
+            },
+            BookmarkRequest {
+                shared: Some(false),
+                ..BookmarkRequest::new(Url::parse("https://private.example/").unwrap())
+            },
+        ]
+    }
+
+    const BOOKMARKS: &str = r#"[
+        {"id": 1, "url": "https://example.com/", "title": "Example",
+         "excerpt": "From the page", "public": 1,
+         "tags": [{"id": 1, "name": "rust"}, {"id": 2, "name": "to read"}]},
+        {"id": 2, "url": "https://private.example/", "public": 0},
+        {"id": 3, "url": "ftp://files.example/", "public": 1}
+    ]"#;
+
+    #[test]
+    fn parses_bookmark_list() {
+        assert_eq!(Shiori.parse(BOOKMARKS).unwrap(), expected());
+    }
+
+    #[test]
+    fn parses_api_page() {
+        let data = format!(r#"{{"page": 1, "maxPage": 1, "bookmarks": {BOOKMARKS}}}"#);
+        assert_eq!(Shiori.parse(&data).unwrap(), expected());
+    }
+}
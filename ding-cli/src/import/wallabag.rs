@@ -0,0 +1,128 @@
+//! The JSON export of wallabag.
+
+use anyhow::Result;
+use serde::Deserialize;
+use url::Url;
+
+use ding_rs::BookmarkRequest;
+
+use super::{is_importable, tag_name, Adapter};
+
+#[derive(Deserialize)]
+struct Entry {
+    url: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    is_archived: Flag,
+    #[serde(default)]
+    is_starred: Flag,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+}
+
+#[derive(Deserialize)]
+struct Annotation {
+    #[serde(default)]
+    quote: String,
+    #[serde(default)]
+    text: String,
+}
+
+/// Older wallabag versions export flags as numbers.
+#[derive(Deserialize, Default)]
+#[serde(untagged)]
+enum Flag {
+    Bool(bool),
+    Int(u8),
+    #[default]
+    Missing,
+}
+
+impl Flag {
+    fn is_set(&self) -> bool {
+        matches!(self, Flag::Bool(true) | Flag::Int(1..))
+    }
+}
+
+/// Archived entries in wallabag are ones that have been read, so they are
+/// imported as read rather than archived. Starred entries get a `starred`
+/// tag and annotations are kept as notes.
+pub struct Wallabag;
+
+impl Adapter for Wallabag {
+    fn parse(&self, data: &str) -> Result<Vec<BookmarkRequest>> {
+        let entries: Vec<Entry> = serde_json::from_str(data)?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| {
+                let url = Url::parse(&entry.url).ok().filter(is_importable)?;
+                let mut tags = entry
+                    .tags
+                    .iter()
+                    .filter_map(|x| tag_name(x))
+                    .collect::<Vec<_>>();
+                if entry.is_starred.is_set() {
+                    tags.push("starred".to_string());
+                }
+                let notes = entry
+                    .annotations
+                    .iter()
+                    .map(|x| match x.text.is_empty() {
+                        true => format!("> {}", x.quote),
+                        false => format!("> {}\n\n{}", x.quote, x.text),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                Some(BookmarkRequest {
+                    title: (!entry.title.is_empty()).then_some(entry.title),
+                    notes: (!notes.is_empty()).then_some(notes),
+                    tag_names: (!tags.is_empty()).then_some(tags),
+                    unread: Some(!entry.is_archived.is_set()),
+                    ..BookmarkRequest::new(url)
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_export() {
+        let data = r#"[
+            {"id": 1, "url": "https://example.com/", "title": "Example",
+             "is_archived": 1, "is_starred": 1, "tags": ["rust", "long read"],
+             "annotations": [
+                 {"quote": "A quote", "text": "My comment"},
+                 {"quote": "Another quote", "text": ""}
+             ]},
+            {"id": 2, "url": "https://unread.example/", "is_archived": false, "is_starred": false},
+            {"id": 3, "url": "not a url"}
+        ]"#;
+        assert_eq!(
+            Wallabag.parse(data).unwrap(),
+            vec![
+                BookmarkRequest {
+                    title: Some("Example".to_string()),
+                    notes: Some("> A quote\n\nMy comment\n\n> Another quote".to_string()),
+                    tag_names: Some(vec![
+                        "rust".to_string(),
+                        "long-read".to_string(),
+                        "starred".to_string(),
+                    ]),
+                    unread: Some(false),
+                    ..BookmarkRequest::new(Url::parse("https://example.com/").unwrap())
+                },
+                BookmarkRequest {
+                    unread: Some(true),
+                    ..BookmarkRequest::new(Url::parse("https://unread.example/").unwrap())
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use crossterm::style::Stylize;
+use reqwest::{redirect, StatusCode};
+use serde::Serialize;
+use url::Url;
+
+use ding_rs::{Bookmark, BookmarkRequest, DingClient};
+
+use crate::bulk::{Bulk, Outcome};
+use crate::output::{to_csv, ToOutput};
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum LinkStatus {
+    Ok,
+    Moved { location: Url },
+    Broken { code: u16 },
+    Timeout,
+    Error { message: String },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LinkReport {
+    pub id: u64,
+    pub url: Url,
+    #[serde(flatten)]
+    pub status: LinkStatus,
+}
+
+impl LinkReport {
+    pub fn is_ok(&self) -> bool {
+        matches!(self.status, LinkStatus::Ok)
+    }
+
+    pub fn is_dead(&self) -> bool {
+        matches!(
+            self.status,
+            LinkStatus::Broken { .. } | LinkStatus::Timeout | LinkStatus::Error { .. }
+        )
+    }
+}
+
+impl ToOutput for Vec<LinkReport> {
+    fn to_human_format(&self) -> Result<String> {
+        Ok(self
+            .iter()
+            .map(|x| {
+                let status = match &x.status {
+                    LinkStatus::Ok => "ok".to_string().green(),
+                    LinkStatus::Moved { location } => format!("moved -> {location}").yellow(),
+                    LinkStatus::Broken { code } => format!("broken ({code})").red(),
+                    LinkStatus::Timeout => "timeout".to_string().red(),
+                    LinkStatus::Error { message } => format!("error: {message}").red(),
+                };
+                format!(
+                    "{} {} {}",
+                    format!("(ID: {})", x.id).green().bold(),
+                    x.url,
+                    status
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(serde_json::to_value(self)?)
+    }
+}
+
+pub struct LinkChecker {
+    client: reqwest::Client,
+    concurrency: usize,
+}
+
+impl LinkChecker {
+    pub fn new(timeout: Duration, concurrency: usize) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(redirect::Policy::limited(10))
+            .build()?;
+        Ok(Self {
+            client,
+            concurrency: concurrency.max(1),
+        })
+    }
+
+    pub async fn check_all(&self, bookmarks: &[Bookmark]) -> Vec<LinkReport> {
+        let mut reports = Bulk::new("Checking")
+            .concurrency(self.concurrency)
+            .run(
+                bookmarks,
+                |x| x.url.to_string(),
+                |x| async move {
+                    Ok(Outcome::Done(LinkReport {
+                        id: x.id,
+                        url: x.url.clone(),
+                        status: self.check(&x.url).await,
+                    }))
+                },
+            )
+            .await
+            .succeeded;
+        reports.sort_by_key(|x| x.id);
+        reports
+    }
+
+    pub async fn check(&self, url: &Url) -> LinkStatus {
+        // Plenty of servers reject HEAD outright, so retry those with GET.
+        let resp = match self.client.head(url.clone()).send().await {
+            Ok(resp)
+                if resp.status() != StatusCode::METHOD_NOT_ALLOWED
+                    && resp.status() != StatusCode::NOT_IMPLEMENTED
+                    && resp.status() != StatusCode::FORBIDDEN =>
+            {
+                Ok(resp)
+            }
+            _ => self.client.get(url.clone()).send().await,
+        };
+        match resp {
+            Ok(resp) if !resp.status().is_success() => LinkStatus::Broken {
+                code: resp.status().as_u16(),
+            },
+            Ok(resp) if !same_location(resp.url(), url) => LinkStatus::Moved {
+                location: resp.url().clone(),
+            },
+            Ok(_) => LinkStatus::Ok,
+            Err(err) if err.is_timeout() => LinkStatus::Timeout,
+            Err(err) => LinkStatus::Error {
+                message: err.without_url().to_string(),
+            },
+        }
+    }
+}
+
+fn same_location(a: &Url, b: &Url) -> bool {
+    let strip = |x: &Url| {
+        let mut x = x.clone();
+        x.set_fragment(None);
+        x.as_str().trim_end_matches('/').to_string()
+    };
+    strip(a) == strip(b)
+}
+
+pub async fn tag_dead_links(
+    client: &DingClient,
+    bookmarks: &[Bookmark],
+    reports: &[LinkReport],
+    tag: &str,
+) -> Result<()> {
+    for report in reports.iter().filter(|x| x.is_dead()) {
+        let Some(bookmark) = bookmarks.iter().find(|x| x.id == report.id) else {
+            continue;
+        };
+        if bookmark.tag_names.iter().any(|x| x == tag) {
+            continue;
+        }
+        let mut tag_names = bookmark.tag_names.clone();
+        tag_names.push(tag.to_string());
+        client
+            .update_bookmark(
+                bookmark.id,
+                BookmarkRequest {
+                    tag_names: Some(tag_names),
+                    ..Default::default()
+                },
+            )
+            .await?;
+    }
+    Ok(())
+}
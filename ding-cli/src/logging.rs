@@ -0,0 +1,33 @@
+//! Log output for `--verbose`. `-v` logs every request, `-vv` adds headers
+//! and bodies. `DING_LOG` takes a full filter directive instead.
+
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use tracing_subscriber::EnvFilter;
+
+pub fn init(verbose: u8, log_file: Option<&Path>) -> Result<()> {
+    let level = match verbose {
+        0 => "off",
+        1 => "info",
+        _ => "debug",
+    };
+    let filter = match std::env::var("DING_LOG") {
+        Ok(directives) => EnvFilter::try_new(directives)?,
+        Err(_) => EnvFilter::new(format!("ding_rs={level},ding_cli={level}")),
+    };
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            builder
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .init();
+        }
+        None => builder.with_writer(std::io::stderr).init(),
+    }
+    Ok(())
+}
@@ -1,206 +1,667 @@
-use anyhow::Result;
-use clap::builder::ArgPredicate;
-use clap::{CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
+mod backup;
+mod bulk;
+mod cache;
+mod clipboard;
+mod completion;
+mod config;
+mod dates;
+mod dedupe;
+mod dryrun;
+mod export;
+mod failure;
+mod feed;
+mod group;
+mod hooks;
+mod import;
+mod linkcheck;
+mod logging;
+mod menu;
+mod native;
+mod output;
+mod pick;
+mod qr;
+mod queue;
+mod readable;
+mod rules;
+mod selector;
+mod serve;
+mod shortlink;
+mod snooze;
+mod status;
+mod sync;
+mod tagpicker;
+mod watch;
+mod wayback;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use clap::builder::{ArgPredicate, PossibleValuesParser, TypedValueParser};
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
+use clap_complete::engine::ArgValueCandidates;
 use colored_json::to_colored_json_auto;
 use crossterm::style::Stylize;
-use iocraft::ElementExt;
-use serde::Serialize;
+use futures::{stream, StreamExt};
+use std::collections::HashSet;
+use std::future::Future;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 use url::Url;
 
 use ding_rs::{
-    Bookmark, BookmarkRequest, BookmarksRequest, DingClient, Tag, TagRequest, TagsRequest,
+    errors::DingError, http_cache::DiskCache, transport::ReqwestTransport, Bookmark,
+    BookmarkRequest, BookmarkSort, BookmarksRequest, DingClient, FeedKind, RetryPolicy, Tag,
+    TagRequest, TagsRequest, Upserted,
 };
 
-#[derive(ValueEnum, Clone, Default)]
-enum OutputFormat {
-    #[default]
-    Human,
-    Json,
-    FlattenJson,
-    Csv,
+use failure::Failure;
+use hooks::{Event, Hooks};
+use output::{DomainCount, OutputFormat, ToOutput};
+
+const ADD_CONCURRENCY: usize = 4;
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+#[command(propagate_version = true, after_help = failure::EXIT_CODES)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    #[arg(short='H', long, env = "DING_HOST", global = true, hide_env_values = true, value_hint = ValueHint::Url)]
+    host: Option<Url>,
+
+    #[arg(long, env = "DING_TOKEN", global = true, hide_env_values = true)]
+    token: Option<String>,
+
+    /// Send at most this many API requests per second
+    #[arg(long, env = "DING_MAX_RPS", global = true)]
+    max_rps: Option<f64>,
+
+    /// Reach the server through this proxy, e.g. socks5h://127.0.0.1:9050
+    /// for Tor; HTTP_PROXY, HTTPS_PROXY and ALL_PROXY are used otherwise
+    #[arg(long, env = "DING_PROXY", global = true, value_hint = ValueHint::Url)]
+    proxy: Option<Url>,
+
+    /// Give up on requests without a response after this long, e.g. 30s;
+    /// also the per-page limit of `linkcheck` (10s) and `archive-content`
+    /// (30s)
+    #[arg(long, env = "DING_TIMEOUT", global = true, value_parser = dates::parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Send each request that could not connect, timed out or hit a busy
+    /// server again up to this many times. Requests creating something are
+    /// only sent again if they cannot have taken effect
+    #[arg(long, env = "DING_RETRIES", global = true, default_value_t = 0)]
+    retries: u32,
+
+    /// Wait this long before the first retry, doubling for each further one
+    #[arg(long, env = "DING_RETRY_DELAY", global = true, default_value = "1s", value_parser = dates::parse_duration)]
+    retry_delay: Duration,
+
+    /// Keep API responses on disk and only download them again when the
+    /// server says they changed (ETag / Last-Modified)
+    #[arg(long, env = "DING_HTTP_CACHE", global = true)]
+    http_cache: bool,
+
+    /// Log requests to stderr, twice to include headers and bodies
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Write the log to this file instead of stderr
+    #[arg(long, global = true, value_hint = ValueHint::FilePath)]
+    log_file: Option<PathBuf>,
+
+    #[arg(short = 'F', long, global = true, default_value_t, value_enum)]
+    output_format: OutputFormat,
+
+    /// Show one line per bookmark in the human format
+    #[arg(long, global = true, conflicts_with = "long")]
+    oneline: bool,
+
+    /// Show snapshot URLs and all dates in the human format
+    #[arg(long, global = true)]
+    long: bool,
+
+    #[arg(long, env = "DING_CONFIG", global = true, value_hint = ValueHint::FilePath)]
+    config: Option<PathBuf>,
+
+    /// Read from the local cache filled by `sync` instead of the server
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Print the requests that would change something on the server instead
+    /// of sending them
+    #[arg(long, global = true, conflicts_with = "offline")]
+    dry_run: bool,
 }
 
-trait ToOutput: Serialize {
-    fn to_human_format(&self) -> Result<String>;
-    fn to_csv_format(&self) -> Result<String>;
-    fn to_json_format(&self) -> Result<String> {
-        Ok(to_colored_json_auto(&self)?)
-    }
-    fn to_flatten_json_format(&self) -> Result<String> {
-        Ok(serde_json::to_string(self)?)
-    }
-    fn to_format(&self, format: OutputFormat) -> Result<String> {
-        match format {
-            OutputFormat::Human => self.to_human_format(),
-            OutputFormat::Json => self.to_json_format(),
-            OutputFormat::FlattenJson => self.to_flatten_json_format(),
-            OutputFormat::Csv => self.to_csv_format(),
+#[derive(ValueEnum, Clone, Copy)]
+enum CompletionShell {
+    Bash,
+    Elvish,
+    Fish,
+    Powershell,
+    Zsh,
+    Nushell,
+}
+
+impl CompletionShell {
+    fn generate(self, cmd: &mut clap::Command, name: String, buf: &mut dyn std::io::Write) {
+        match self {
+            CompletionShell::Bash => {
+                clap_complete::generate(clap_complete::Shell::Bash, cmd, name, buf)
+            }
+            CompletionShell::Elvish => {
+                clap_complete::generate(clap_complete::Shell::Elvish, cmd, name, buf)
+            }
+            CompletionShell::Fish => {
+                clap_complete::generate(clap_complete::Shell::Fish, cmd, name, buf)
+            }
+            CompletionShell::Powershell => {
+                clap_complete::generate(clap_complete::Shell::PowerShell, cmd, name, buf)
+            }
+            CompletionShell::Zsh => {
+                clap_complete::generate(clap_complete::Shell::Zsh, cmd, name, buf)
+            }
+            CompletionShell::Nushell => {
+                clap_complete::generate(clap_complete_nushell::Nushell, cmd, name, buf)
+            }
         }
     }
 }
 
-impl ToOutput for Bookmark {
-    fn to_human_format(&self) -> Result<String> {
-        let title = match (&self.website_title, &self.title) {
-            (_, Some(title)) => title,
-            (Some(title), None) => title,
-            (None, None) => &self.url.to_string(),
-        };
-        let description = match (&self.website_description, &self.description) {
-            (_, Some(description)) => description,
-            (Some(description), None) => description,
-            (None, None) => "-",
-        };
-        let notes = if let Some(notes) = &self.notes {
-            notes
-        } else {
-            "-"
-        };
-        let (width, _) = crossterm::terminal::size()?;
-        let formated_description = iocraft::prelude::element! {
-            iocraft::prelude::Box(
-                border_style: iocraft::prelude::BorderStyle::None,
-                max_width: width,
-                padding_left: iocraft::prelude::Padding::Length(2),
-                padding_right: iocraft::prelude::Padding::Length(2),
-            ) {
-                iocraft::prelude::Text(content: format!("{} {}", "Description:".to_string().magenta(), description))
-            }
-        }.to_string();
-        let formated_notes = iocraft::prelude::element! {
-            iocraft::prelude::Box(
-                border_style: iocraft::prelude::BorderStyle::None,
-                max_width: width,
-                padding_left: iocraft::prelude::Padding::Length(2),
-                padding_right: iocraft::prelude::Padding::Length(2),
-            ) {
-                iocraft::prelude::Text(content: format!("{} {}", "Notes:".to_string().magenta(), notes))
-            }
-        }.to_string();
-        Ok(format!(
-            "{} {}\n  {} {}\n  {} {}\n{}{}",
-            format!(
-                "(ID: {}{}{})",
-                self.id,
-                if self.is_archived { ",📦" } else { "" },
-                if self.unread { ",📕" } else { ",📖" }
-            )
-            .green()
-            .bold(),
-            title.clone().bold().blue(),
-            "Url:".to_string().magenta(),
-            self.url,
-            "Tags:".to_string().magenta(),
-            self.tag_names.join(" "),
-            formated_description,
-            formated_notes,
-        ))
-    }
-    fn to_csv_format(&self) -> Result<String> {
-        todo!()
-    }
+#[derive(Args)]
+struct BookmarkFields {
+    #[arg(short = 'T', long)]
+    title: Option<String>,
+
+    #[arg(short, long)]
+    description: Option<String>,
+
+    #[arg(short, long)]
+    notes: Option<String>,
+
+    #[arg(short, long)]
+    is_archived: Option<bool>,
+
+    #[arg(short, long)]
+    unread: Option<bool>,
+
+    #[arg(short, long)]
+    shared: Option<bool>,
+
+    #[arg(short, long, add = ArgValueCandidates::new(completion::tags))]
+    tag_names: Option<Vec<String>>,
 }
 
-impl ToOutput for Vec<Bookmark> {
-    fn to_human_format(&self) -> Result<String> {
-        Ok(self
-            .iter()
-            .map(|x| x.to_human_format())
-            .collect::<Result<Vec<String>>>()?
-            .join("\n"))
-    }
-    fn to_csv_format(&self) -> Result<String> {
-        todo!()
+impl BookmarkFields {
+    /// Override the fields of `req` that were given on the command line.
+    fn apply(&self, req: BookmarkRequest) -> BookmarkRequest {
+        BookmarkRequest {
+            url: req.url,
+            title: self.title.clone().or(req.title),
+            description: self.description.clone().or(req.description),
+            notes: self.notes.clone().or(req.notes),
+            is_archived: self.is_archived.or(req.is_archived),
+            unread: self.unread.or(req.unread),
+            shared: self.shared.or(req.shared),
+            tag_names: self.tag_names.clone().or(req.tag_names),
+        }
     }
 }
 
-impl ToOutput for Vec<Tag> {
-    fn to_human_format(&self) -> Result<String> {
-        Ok(self
-            .iter()
-            .map(|x| x.name.clone())
-            .collect::<Vec<_>>()
-            .join("\n"))
-    }
-    fn to_json_format(&self) -> Result<String> {
-        Ok(to_colored_json_auto(
-            &self.iter().map(|x| x.name.clone()).collect::<Vec<_>>(),
-        )?)
-    }
-    fn to_flatten_json_format(&self) -> Result<String> {
-        Ok(serde_json::to_string(
-            &self.iter().map(|x| x.name.clone()).collect::<Vec<_>>(),
-        )?)
+/// Date limits, each an ISO date or time, `today`, `yesterday` or e.g.
+/// `2 weeks ago`.
+#[derive(Args)]
+struct DateFilters {
+    /// Only bookmarks added after this date
+    #[arg(long, value_parser = dates::parse)]
+    added_after: Option<DateTime<Utc>>,
+
+    /// Only bookmarks added before this date
+    #[arg(long, value_parser = dates::parse)]
+    added_before: Option<DateTime<Utc>>,
+
+    /// Only bookmarks modified after this date
+    #[arg(long, value_parser = dates::parse)]
+    modified_after: Option<DateTime<Utc>>,
+
+    /// Only bookmarks modified before this date
+    #[arg(long, value_parser = dates::parse)]
+    modified_before: Option<DateTime<Utc>>,
+}
+
+impl DateFilters {
+    fn is_empty(&self) -> bool {
+        self.added_after.is_none()
+            && self.added_before.is_none()
+            && self.modified_after.is_none()
+            && self.modified_before.is_none()
     }
-    fn to_csv_format(&self) -> Result<String> {
-        todo!()
+
+    /// Whether `bookmark` is within every limit. Servers filter by the
+    /// `after` limits themselves, but older ones ignore them, so results are
+    /// always checked again.
+    fn matches(&self, bookmark: &Bookmark) -> bool {
+        self.added_after.is_none_or(|x| bookmark.date_added > x)
+            && self.added_before.is_none_or(|x| bookmark.date_added < x)
+            && self
+                .modified_after
+                .is_none_or(|x| bookmark.date_modified > x)
+            && self
+                .modified_before
+                .is_none_or(|x| bookmark.date_modified < x)
     }
 }
 
-#[derive(Parser)]
-#[command(version, about, long_about = None)]
-#[command(propagate_version = true)]
-struct Cli {
-    #[command(subcommand)]
-    command: Commands,
-
-    #[arg(short='H', long, env = "DING_HOST", global = true, hide_env_values = true, value_hint = ValueHint::Url)]
-    host: Option<Url>,
+/// The bookmark a command acts on, given by id or by URL, or the bookmarks
+/// matching a search.
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct BookmarkTarget {
+    #[arg(short, long, add = ArgValueCandidates::new(completion::bookmark_ids))]
+    id: Option<u64>,
 
-    #[arg(long, env = "DING_TOKEN", global = true, hide_env_values = true)]
-    token: Option<String>,
+    /// Find the bookmark by its URL instead
+    #[arg(short, long, value_hint = ValueHint::Url)]
+    url: Option<Url>,
 
-    #[arg(short, long, global = true, default_value_t)]
-    verbose: bool,
+    /// Act on every bookmark matching this search instead
+    #[arg(short, long)]
+    query: Option<String>,
+}
 
-    #[arg(short = 'F', long, global = true, default_value_t, value_enum)]
-    output_format: OutputFormat,
+impl BookmarkTarget {
+    /// The id of the bookmark, looking the URL up on the server, or in the
+    /// sync cache when offline.
+    async fn resolve(&self, cli: &Cli) -> Result<u64> {
+        if let Some(id) = self.id {
+            return Ok(id);
+        }
+        let url = self
+            .url
+            .as_ref()
+            .expect("bulk targets are handled separately");
+        let bookmark = online_or_cached(
+            cli,
+            |client| async move { Ok(client.bookmark_by_url(url).await?) },
+            |cache| cache.bookmark_by_url(url),
+        )
+        .await?;
+        bookmark
+            .map(|x| x.id)
+            .ok_or_else(|| Failure::not_found(format!("No bookmark saved for {url}")).into())
+    }
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Move bookmarks out of the archive
     Unarchive {
-        #[arg(short, long, conflicts_with = "url")]
-        id: u64,
+        #[command(flatten)]
+        target: BookmarkTarget,
     },
+    /// Archive bookmarks
     Archive {
-        #[arg(short, long, conflicts_with = "url")]
-        id: u64,
+        #[command(flatten)]
+        target: BookmarkTarget,
     },
+    /// Delete bookmarks
     Delete {
-        #[arg(short, long, conflicts_with = "url")]
+        #[command(flatten)]
+        target: BookmarkTarget,
+
+        /// Confirm deleting every bookmark matching --query
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Pick a random bookmark
+    Random {
+        #[arg(short, long)]
+        query: Option<String>,
+
+        /// Only pick bookmarks with this tag
+        #[arg(short, long, add = ArgValueCandidates::new(completion::tags))]
+        tag: Option<String>,
+
+        /// Only pick unread bookmarks
+        #[arg(short, long)]
+        unread: bool,
+
+        #[arg(short = 'A', long)]
+        archived: bool,
+
+        /// Open the bookmark in the browser
+        #[arg(short, long)]
+        open: bool,
+    },
+    /// Show the oldest unread bookmark, to read next
+    Next {
+        /// Open the bookmark in the browser
+        #[arg(short, long)]
+        open: bool,
+
+        /// Bring back the skipped bookmarks
+        #[arg(long)]
+        reset: bool,
+    },
+    /// Mark the bookmark shown by `next` as read
+    Done {
+        /// Archive it as well
+        #[arg(short, long)]
+        archive: bool,
+    },
+    /// Skip the bookmark shown by `next` and show the one after it
+    Skip {
+        /// Open the bookmark in the browser
+        #[arg(short, long)]
+        open: bool,
+    },
+    /// Hide a bookmark from `next` until a date, after which `wake` tags it
+    /// for the inbox. Only `next` knows about snoozing, which is kept
+    /// locally; `bookmarks`, the TUI and the server still list it
+    Snooze {
+        #[arg(
+            required_unless_present = "list",
+            add = ArgValueCandidates::new(completion::bookmark_ids)
+        )]
+        id: Option<u64>,
+
+        /// When to wake it, e.g. tomorrow, next saturday, `in 2 weeks` or
+        /// 2024-01-31
+        #[arg(short, long, required_unless_present = "list", value_parser = dates::parse_future)]
+        until: Option<DateTime<Utc>>,
+
+        /// List the snoozed bookmarks instead
+        #[arg(short, long, conflicts_with_all = ["id", "until"])]
+        list: bool,
+    },
+    /// Tag snoozed bookmarks that are due for the inbox
+    Wake {
+        /// Wake these bookmarks now, whatever their date
+        #[arg(add = ArgValueCandidates::new(completion::bookmark_ids))]
+        ids: Vec<u64>,
+
+        /// Tag that marks bookmarks in the inbox
+        #[arg(long, default_value = "inbox", add = ArgValueCandidates::new(completion::tags))]
+        inbox_tag: String,
+    },
+    /// Copy the bookmark URL to the clipboard
+    Yank {
+        #[arg(add = ArgValueCandidates::new(completion::bookmark_ids))]
         id: u64,
     },
+    /// Show the bookmark URL as a QR code, e.g. to open it on a phone
+    Qr {
+        #[arg(add = ArgValueCandidates::new(completion::bookmark_ids))]
+        id: u64,
+
+        /// Write the code to a PNG file instead
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        png: Option<PathBuf>,
+
+        /// Draw dark modules as blocks, for light terminal backgrounds
+        #[arg(long, conflicts_with = "png")]
+        invert: bool,
+    },
     AddTag {
         #[arg(short, long)]
         name: String,
     },
     Add {
-        url: Url,
+        /// One or more URLs sharing the same options
+        #[arg(required_unless_present_any = ["clipboard", "json"])]
+        url: Vec<Url>,
+
+        /// Take the URL from the clipboard
+        #[arg(short = 'c', long, conflicts_with = "url")]
+        clipboard: bool,
+
+        /// Read the bookmark as JSON from a file, or from stdin with `-`
+        #[arg(long, conflicts_with_all = ["url", "clipboard"], value_hint = ValueHint::FilePath)]
+        json: Option<PathBuf>,
+
+        #[command(flatten)]
+        fields: BookmarkFields,
+
+        /// Remove utm_* and similar tracking parameters from the URL
+        #[arg(long)]
+        strip_tracking: bool,
+
+        /// If a URL is bookmarked already, add the tags and notes to the
+        /// existing bookmark
+        #[arg(long)]
+        update_if_exists: bool,
+
+        /// Pick the tags from a list of the existing ones, starting with the
+        /// ones given by --tag-names
+        #[arg(long)]
+        interactive_tags: bool,
+
+        /// Read the title, description and canonical URL from the page
+        /// here, for when the server cannot
+        #[arg(long)]
+        scrape: bool,
+
+        /// Save where short links such as t.co or bit.ly lead, keeping the
+        /// short link in the notes
+        #[arg(long)]
+        expand: bool,
+    },
+    /// Change fields of an existing bookmark
+    Edit {
+        #[arg(add = ArgValueCandidates::new(completion::bookmark_ids))]
+        id: u64,
+
+        #[arg(long)]
+        url: Option<Url>,
+
+        /// Read the changes as JSON from a file, or from stdin with `-`
+        #[arg(long, value_hint = ValueHint::FilePath)]
+        json: Option<PathBuf>,
 
-        #[arg(short = 'T', long)]
-        title: Option<String>,
+        #[command(flatten)]
+        fields: BookmarkFields,
+    },
+    /// Add or remove tags, e.g. `ding tag 5 +rust +cli -todo`
+    Tag {
+        #[arg(add = ArgValueCandidates::new(completion::bookmark_ids))]
+        id: u64,
+
+        /// `+name` adds a tag, `-name` removes it, a bare name is added
+        #[arg(
+            required = true,
+            allow_hyphen_values = true,
+            add = ArgValueCandidates::new(completion::tag_changes)
+        )]
+        changes: Vec<String>,
+    },
+    /// Add and remove tags on every bookmark matching a query
+    Retag {
+        #[arg(short, long)]
+        query: Option<String>,
+
+        #[arg(short = 'A', long)]
+        archived: bool,
+
+        #[arg(long = "add", value_name = "TAG", add = ArgValueCandidates::new(completion::tags))]
+        add_tags: Vec<String>,
+
+        #[arg(long = "remove", value_name = "TAG", add = ArgValueCandidates::new(completion::tags))]
+        remove_tags: Vec<String>,
+
+        /// Number of bookmarks updated at the same time
+        #[arg(short, long, default_value_t = 4)]
+        concurrency: usize,
+    },
+    /// Auto-tagging rules from the config file
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommands,
+    },
+    /// Clean-up recipes for old bookmarks, previewed with --dry-run
+    Maintain {
+        #[command(subcommand)]
+        command: MaintainCommands,
+    },
+    /// Replay offline changes and download all bookmarks and tags into the
+    /// local cache
+    Sync {
+        /// Replay offline changes even if the bookmark changed on the server
+        #[arg(long)]
+        force: bool,
+
+        /// Download everything instead of only bookmarks changed since the
+        /// last sync
+        #[arg(long)]
+        full: bool,
+    },
+    /// Full-text search in the local cache filled by `sync`
+    Search {
+        #[arg(required = true)]
+        terms: Vec<String>,
+
+        #[arg(short = 'A', long)]
+        archived: bool,
+
+        /// Also match words with small typos
+        #[arg(short, long)]
+        fuzzy: bool,
 
+        #[arg(short, long, default_value_t = 20)]
+        limit: u64,
+    },
+    /// Show a single bookmark
+    Show {
+        #[arg(add = ArgValueCandidates::new(completion::bookmark_ids))]
+        id: u64,
+    },
+    /// Save all bookmarks, tags and preferences to a file, zstd compressed if
+    /// it ends in `.zst`
+    Backup {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+    },
+    /// Recreate bookmarks and tags from a backup file
+    Restore {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+    },
+    /// Print a static completion script.
+    ///
+    /// For completion of tag names and bookmark IDs, source the output of
+    /// `COMPLETE=<shell> ding-cli` instead.
+    Completion { shell: CompletionShell },
+    /// Find bookmarks pointing to the same page and merge them
+    Dedupe {
+        /// Merge every duplicate group without asking
         #[arg(short, long)]
-        description: Option<String>,
+        auto: bool,
 
+        /// Ignore utm_* and other tracking parameters when comparing URLs
+        #[arg(short, long)]
+        strip_tracking: bool,
+    },
+    /// Export bookmarks to other formats
+    Export {
+        #[command(subcommand)]
+        command: ExportCommands,
+    },
+    /// Show whether a URL is bookmarked and the metadata the server finds
+    Check { url: Url },
+    /// Select bookmarks with fzf or the built-in selector and act on them
+    Pick {
         #[arg(short, long)]
-        notes: Option<String>,
+        query: Option<String>,
+
+        #[arg(short = 'A', long)]
+        archived: bool,
+
+        #[arg(short, long, value_enum, default_value_t)]
+        action: pick::PickAction,
+
+        /// Picker command, overriding `pick.command` from the config
+        #[arg(short, long, value_hint = ValueHint::CommandString)]
+        picker: Option<String>,
+
+        /// Use the built-in selector instead of an external picker
+        #[arg(short, long, conflicts_with = "picker")]
+        builtin: bool,
+    },
+    /// Import bookmarks from other tools
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
 
+        /// Continue an interrupted import of the same file after the rows
+        /// it got through
+        #[arg(long, global = true)]
+        resume: bool,
+    },
+    /// Check bookmark URLs and report dead, timed out or moved links
+    Linkcheck {
         #[arg(short, long)]
-        is_archived: Option<bool>,
+        query: Option<String>,
+
+        #[arg(short = 'A', long)]
+        archived: bool,
+
+        /// Number of links checked at the same time
+        #[arg(short, long, default_value_t = 8)]
+        concurrency: usize,
+
+        /// Also list links that are fine
+        #[arg(long)]
+        show_ok: bool,
+
+        /// Tag broken and unreachable bookmarks
+        #[arg(long, num_args = 0..=1, default_missing_value = "dead-link")]
+        tag_broken: Option<String>,
+    },
+    /// Download bookmarked pages and keep their readable text under the data
+    /// directory
+    ArchiveContent {
+        #[arg(required_unless_present = "query", conflicts_with_all = ["query", "archived"])]
+        ids: Vec<u64>,
 
         #[arg(short, long)]
-        unread: Option<bool>,
+        query: Option<String>,
+
+        #[arg(short = 'A', long)]
+        archived: bool,
 
+        /// Also upload the readable copy as an asset of the bookmark
         #[arg(short, long)]
-        shared: Option<bool>,
+        upload: bool,
 
+        /// Download pages that were already saved again
         #[arg(short, long)]
-        tag_names: Option<Vec<String>>,
+        force: bool,
     },
-    Completion {
-        shell: clap_complete::Shell,
+    /// Save bookmarked pages on the Wayback Machine and note the snapshot URL
+    /// on each bookmark
+    Wayback {
+        #[arg(required_unless_present = "query", conflicts_with_all = ["query", "archived"])]
+        ids: Vec<u64>,
+
+        #[arg(short, long)]
+        query: Option<String>,
+
+        #[arg(short = 'A', long)]
+        archived: bool,
+
+        /// Snapshot bookmarks that have a snapshot already
+        #[arg(short, long)]
+        force: bool,
+
+        /// How long to wait for each snapshot, e.g. 90s or 5m
+        #[arg(long, default_value = "3m", value_parser = dates::parse_duration)]
+        max_wait: Duration,
+
+        /// Time between checks for a finished snapshot
+        #[arg(long, default_value = "10s", value_parser = dates::parse_duration)]
+        poll_interval: Duration,
     },
     Tags {
         #[arg(short, long)]
@@ -216,6 +677,119 @@ enum Commands {
         )]
         all: bool,
     },
+    /// List bundles, saved searches of newer servers
+    Bundles,
+    /// Show the server version and which newer API features it supports
+    Server,
+    /// Print a feed of bookmarks for feed readers and dashboards
+    Feed {
+        #[arg(
+            default_value = "all",
+            value_parser = PossibleValuesParser::new(FeedKind::ALL.map(|x| x.as_str()))
+                .map(|x| x.parse::<FeedKind>().expect("only possible values are accepted"))
+        )]
+        kind: FeedKind,
+
+        /// `rss` is the server's own feed; `atom` and `json` are built from
+        /// the API
+        #[arg(long, value_enum, default_value_t)]
+        format: feed::FeedFormat,
+
+        /// Feed token from the server's integration settings, needed for the
+        /// server's feeds except `public`
+        #[arg(long, env = "DING_FEED_TOKEN", hide_env_values = true)]
+        feed_token: Option<String>,
+
+        /// Print the URL of the server's feed instead of its content
+        #[arg(long, conflicts_with = "format")]
+        url: bool,
+
+        /// Number of bookmarks in `atom` and `json` feeds
+        #[arg(short, long, default_value = "100")]
+        limit: u64,
+    },
+    /// Serve a browser extension over the native messaging protocol on
+    /// stdin and stdout
+    NativeHost {
+        /// What the browser passes, e.g. the extension's origin; ignored
+        #[arg(hide = true, allow_hyphen_values = true, trailing_var_arg = true)]
+        caller: Vec<String>,
+    },
+    /// Choose a bookmark in rofi, dmenu or wofi and open or copy it
+    Menu {
+        #[arg(short, long, value_enum, default_value_t)]
+        backend: menu::MenuBackend,
+
+        #[arg(short, long, value_enum, default_value_t)]
+        action: menu::MenuAction,
+
+        #[arg(short, long)]
+        query: Option<String>,
+
+        /// Show each bookmark's tags in front of its title
+        #[arg(long)]
+        prefix_tags: bool,
+
+        /// Reuse the bookmark list fetched less than this many seconds ago
+        #[arg(long, default_value_t = 300)]
+        max_age: u64,
+
+        /// Fetch the bookmark list even if a recent one is kept
+        #[arg(short, long)]
+        refresh: bool,
+    },
+    /// Serve the sync cache as a read-only JSON API and HTML list for local
+    /// tools
+    Serve {
+        #[arg(short, long, default_value = "127.0.0.1:7212")]
+        listen: std::net::SocketAddr,
+    },
+    /// Count unread, inbox and all bookmarks, e.g. for a shell prompt
+    Status {
+        /// Print `unread=N inbox=N total=N` on one line
+        #[arg(long)]
+        porcelain: bool,
+
+        /// Tag that marks bookmarks in the inbox
+        #[arg(long, default_value = "inbox", add = ArgValueCandidates::new(completion::tags))]
+        inbox_tag: String,
+
+        /// Reuse counts fetched less than this many seconds ago
+        #[arg(long)]
+        max_age: Option<u64>,
+    },
+    /// Announce new bookmarks with desktop notifications and/or a hook, e.g.
+    /// from a systemd user service
+    Watch {
+        /// Time between polls, e.g. 30s, 5m or 1h
+        #[arg(short, long, default_value = "5m", value_parser = dates::parse_duration)]
+        interval: Duration,
+
+        /// Only announce bookmarks matching this search
+        #[arg(short, long)]
+        query: Option<String>,
+
+        /// Only announce unread bookmarks
+        #[arg(short, long)]
+        unread: bool,
+
+        /// Run this shell command for every new bookmark, with the bookmark
+        /// as JSON on stdin
+        #[arg(long)]
+        exec: Option<String>,
+
+        /// Do not show desktop notifications
+        #[arg(long)]
+        no_notify: bool,
+
+        /// Also wake snoozed bookmarks when they are due
+        #[arg(long)]
+        wake: bool,
+
+        /// Tag that marks bookmarks in the inbox
+        #[arg(long, default_value = "inbox", add = ArgValueCandidates::new(completion::tags))]
+        inbox_tag: String,
+    },
     Bookmarks {
         #[arg(short, long)]
         query: Option<String>,
@@ -240,33 +814,668 @@ enum Commands {
 
         #[arg(short = 'A', long)]
         archived: bool,
+
+        /// Only show bookmarks on this host (subdomains included)
+        #[arg(short = 'D', long)]
+        domain: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            value_parser = PossibleValuesParser::new(BookmarkSort::ALL.map(|x| x.as_str()))
+                .map(|x| x.parse::<BookmarkSort>().expect("only possible values are accepted"))
+        )]
+        sort: Option<BookmarkSort>,
+
+        /// Only show bookmarks matching the bundle with this id
+        #[arg(short, long)]
+        bundle: Option<u64>,
+
+        #[command(flatten)]
+        dates: DateFilters,
+
+        /// Show the bookmarks under a header per group
+        #[arg(long, value_enum)]
+        group_by: Option<group::GroupBy>,
+
+        /// Only print how many bookmarks match
+        #[arg(long, conflicts_with_all = ["limit", "offset", "all", "group_by", "ids"])]
+        count: bool,
+
+        /// Only print the ids, one per line
+        #[arg(long, conflicts_with = "group_by")]
+        ids: bool,
+    },
+    /// Count bookmarks per hostname
+    Domains {
+        #[arg(short, long)]
+        query: Option<String>,
+
+        #[arg(short = 'A', long)]
+        archived: bool,
     },
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+#[derive(Subcommand)]
+enum ExportCommands {
+    /// One markdown file per bookmark with YAML frontmatter, e.g. for Obsidian
+    Vault {
+        #[arg(value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
 
-    match &cli.command {
-        Commands::Archive { id } => {
-            let client = create_client(&cli)?;
-            let bookmark = archive_bookmark(&client, *id).await?;
-            println!("{}", bookmark.to_format(cli.output_format)?);
-        }
-        Commands::Unarchive { id } => {
-            let client = create_client(&cli)?;
-            let bookmark = unarchive_bookmark(&client, *id).await?;
-            println!("{}", bookmark.to_format(cli.output_format)?);
-        }
-        Commands::Delete { id } => {
-            let client = create_client(&cli)?;
-            let bookmark = delete_bookmark(&client, *id).await?;
-            println!("{}", bookmark.to_format(cli.output_format)?);
-        }
-        Commands::Tags { all, limit, offset } => {
-            let client = create_client(&cli)?;
-            let tags = get_tags(&client, *all, *limit, *offset).await?;
-            println!("{}", tags.to_format(cli.output_format)?);
+        #[arg(short, long)]
+        query: Option<String>,
+
+        /// Include archived bookmarks
+        #[arg(short = 'A', long)]
+        archived: bool,
+
+        /// Rewrite every file, not only bookmarks changed since the last export
+        #[arg(long)]
+        full: bool,
+
+        /// Delete files of bookmarks that are no longer exported
+        #[arg(long)]
+        prune: bool,
+    },
+    /// An Atom or JSON Feed of the matching bookmarks, newest first
+    Feed {
+        #[arg(short, long)]
+        query: Option<String>,
+
+        /// Include archived bookmarks
+        #[arg(short = 'A', long)]
+        archived: bool,
+
+        #[arg(short, long, value_enum, default_value_t)]
+        format: export::feed::FeedFormat,
+
+        /// Write the feed to this file instead of stdout
+        #[arg(short, long, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+
+        /// Title of the feed
+        #[arg(long, default_value = "Bookmarks")]
+        title: String,
+
+        /// Page the feed belongs to, e.g. your site's links page; defaults to
+        /// the server
+        #[arg(long)]
+        link: Option<Url>,
+
+        /// Only the newest bookmarks
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+    /// A data file of the matching bookmarks for static site generators such
+    /// as Hugo or Zola, newest first
+    SiteData {
+        #[arg(short, long)]
+        query: Option<String>,
+
+        /// Include archived bookmarks
+        #[arg(short = 'A', long)]
+        archived: bool,
+
+        #[arg(short, long, value_enum, default_value_t)]
+        format: export::site::DataFormat,
+
+        #[arg(short, long, value_enum)]
+        group_by: Option<group::GroupBy>,
+
+        /// Write the data to this file instead of stdout
+        #[arg(short, long, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ImportSource {
+    /// Chrome or Chromium `Bookmarks` file, folders become tags; defaults to
+    /// the default profile's
+    Chrome {
+        #[arg(value_hint = ValueHint::FilePath)]
+        path: Option<PathBuf>,
+    },
+    /// Firefox profile database; defaults to the default profile's
+    Firefox {
+        #[arg(value_hint = ValueHint::FilePath)]
+        places: Option<PathBuf>,
+
+        /// Add the names of the containing folders as tags
+        #[arg(short, long)]
+        folders: bool,
+    },
+    /// Wallabag JSON export
+    Wallabag {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+    },
+    /// Shiori HTML export or API JSON
+    Shiori {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+    },
+    /// Shaarli HTML export or API JSON
+    Shaarli {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+    },
+    /// Netscape bookmark HTML, as exported by most browsers
+    Netscape {
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum MaintainCommands {
+    /// Archive old bookmarks, e.g. `--older-than 180d --tag inbox`
+    AutoArchive {
+        #[command(flatten)]
+        filter: MaintainFilter,
+    },
+    /// Delete old archived bookmarks
+    PurgeArchived {
+        #[command(flatten)]
+        filter: MaintainFilter,
+
+        /// Confirm deleting them
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Mark old unread bookmarks as read
+    MarkRead {
+        #[command(flatten)]
+        filter: MaintainFilter,
+    },
+}
+
+/// The bookmarks a maintenance recipe acts on.
+#[derive(Args)]
+struct MaintainFilter {
+    /// Only bookmarks added longer ago than this, e.g. 180d
+    #[arg(long, value_parser = dates::parse_duration)]
+    older_than: Duration,
+
+    /// Only bookmarks with this tag, may be repeated
+    #[arg(short, long, add = ArgValueCandidates::new(completion::tags))]
+    tag: Vec<String>,
+
+    /// Only unread bookmarks, or only read ones with `false`
+    #[arg(short, long)]
+    unread: Option<bool>,
+
+    #[arg(short, long)]
+    query: Option<String>,
+}
+
+impl MaintainFilter {
+    /// The search for the server; [`Self::matches`] does the rest.
+    fn request(&self) -> BookmarksRequest {
+        let query = self
+            .query
+            .iter()
+            .cloned()
+            .chain(self.tag.iter().map(|x| format!("#{x}")))
+            .collect::<Vec<_>>()
+            .join(" ");
+        BookmarksRequest {
+            query: (!query.is_empty()).then_some(query),
+            ..Default::default()
+        }
+    }
+
+    fn matches(&self, bookmark: &Bookmark) -> bool {
+        let cutoff = Utc::now() - self.older_than;
+        bookmark.date_added < cutoff
+            && self.unread.is_none_or(|x| bookmark.unread == x)
+            && self.tag.iter().all(|tag| {
+                bookmark
+                    .tag_names
+                    .iter()
+                    .any(|x| x.eq_ignore_ascii_case(tag))
+            })
+    }
+}
+
+#[derive(Subcommand)]
+enum RulesCommands {
+    /// Run the rules over existing bookmarks
+    Apply {
+        #[arg(short, long)]
+        query: Option<String>,
+
+        #[arg(short = 'A', long)]
+        archived: bool,
+    },
+}
+
+fn main() -> ExitCode {
+    clap_complete::CompleteEnv::with_factory(Cli::command).complete();
+    let cli = Cli::parse();
+    let output_format = cli.output_format.clone();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => failure::report(&err, output_format),
+    }
+}
+
+#[tokio::main]
+async fn run(cli: Cli) -> Result<()> {
+    logging::init(cli.verbose, cli.log_file.as_deref())?;
+    let config = config::Config::load(cli.config.as_deref())?;
+    let layout = match (cli.oneline, cli.long) {
+        (true, _) => output::Layout::Oneline,
+        (_, true) => output::Layout::Long,
+        _ => config.output.layout,
+    };
+    let date_display = match cli.verbose {
+        0 => config.output.date_display,
+        _ => output::DateDisplay::Absolute,
+    };
+    // Hooks would act on changes that were not made.
+    let no_hooks = config::HooksConfig::default();
+    let hooks = &Hooks::new(match cli.dry_run {
+        true => &no_hooks,
+        false => &config.hooks,
+    });
+    output::init_human_style(output::HumanStyle {
+        layout,
+        date_display,
+        date_format: config.output.date_format.clone(),
+    });
+
+    match &cli.command {
+        Commands::Archive { target } => {
+            if let Some(query) = &target.query {
+                let client = create_client(&cli)?;
+                let bookmarks = client.all_bookmarks(query_request(query)).await?;
+                return bulk_by_id(&cli, bookmarks, "Archiving", "Archived", |id| {
+                    archive_bookmark(&client, hooks, id)
+                })
+                .await;
+            }
+            let id = target.resolve(&cli).await?;
+            let bookmark = online_or_queued(
+                &cli,
+                |client| async move { archive_bookmark(&client, hooks, id).await },
+                cache::PendingOp::Archive { id },
+            )
+            .await?;
+            print_optional(bookmark, cli.output_format)?;
+        }
+        Commands::Unarchive { target } => {
+            if let Some(query) = &target.query {
+                let client = create_client(&cli)?;
+                let bookmarks = client.all_archived(query_request(query)).await?;
+                return bulk_by_id(&cli, bookmarks, "Unarchiving", "Unarchived", |id| {
+                    unarchive_bookmark(&client, id)
+                })
+                .await;
+            }
+            let id = target.resolve(&cli).await?;
+            let bookmark = online_or_queued(
+                &cli,
+                |client| async move { unarchive_bookmark(&client, id).await },
+                cache::PendingOp::Unarchive { id },
+            )
+            .await?;
+            print_optional(bookmark, cli.output_format)?;
+        }
+        Commands::Delete { target, yes } => {
+            if let Some(query) = &target.query {
+                let client = create_client(&cli)?;
+                let mut bookmarks = client.all_bookmarks(query_request(query)).await?;
+                bookmarks.extend(client.all_archived(query_request(query)).await?);
+                if !yes {
+                    return Err(Failure::usage(format!(
+                        "{} bookmarks match, add --yes to delete them",
+                        bookmarks.len()
+                    ))
+                    .into());
+                }
+                return bulk_by_id(&cli, bookmarks, "Deleting", "Deleted", |id| {
+                    delete_bookmark(&client, hooks, id)
+                })
+                .await;
+            }
+            let id = target.resolve(&cli).await?;
+            let bookmark = online_or_queued(
+                &cli,
+                |client| async move { delete_bookmark(&client, hooks, id).await },
+                cache::PendingOp::Delete { id },
+            )
+            .await?;
+            print_optional(bookmark, cli.output_format)?;
+        }
+        Commands::Random {
+            query,
+            tag,
+            unread,
+            archived,
+            open,
+        } => {
+            let client = create_client(&cli)?;
+            let query = join_query([
+                query.clone(),
+                tag.as_ref().map(|x| format!("#{x}")),
+                unread.then(|| "!unread".to_string()),
+            ]);
+            let Some(bookmark) = random_bookmark(&client, query, *archived).await? else {
+                return Err(Failure::not_found("No bookmarks found").into());
+            };
+            if *open {
+                open::that(bookmark.url.as_str())?;
+            }
+            println!("{}", bookmark.to_format(cli.output_format)?);
+        }
+        Commands::Next { open, reset } => {
+            let mut queue = queue::Queue::load(host(&cli)?.as_str());
+            if *reset {
+                queue.skipped.clear();
+            }
+            show_next(&cli, &mut queue, *open).await?;
+        }
+        Commands::Done { archive } => {
+            let mut queue = queue::Queue::load(host(&cli)?.as_str());
+            let Some(id) = queue.current else {
+                return Err(
+                    Failure::usage("No bookmark in progress, run `ding next` first").into(),
+                );
+            };
+            let req = BookmarkRequest {
+                unread: Some(false),
+                ..Default::default()
+            };
+            let mut bookmark = online_or_queued(
+                &cli,
+                |client| {
+                    let req = req.clone();
+                    async move { Ok(client.update_bookmark(id, req).await?) }
+                },
+                cache::PendingOp::Update {
+                    id,
+                    request: req.clone(),
+                },
+            )
+            .await?;
+            if *archive {
+                bookmark = online_or_queued(
+                    &cli,
+                    |client| async move { archive_bookmark(&client, hooks, id).await },
+                    cache::PendingOp::Archive { id },
+                )
+                .await?;
+            }
+            if !cli.dry_run {
+                queue.current = None;
+                queue.save()?;
+            }
+            print_optional(bookmark, cli.output_format)?;
+        }
+        Commands::Skip { open } => {
+            let mut queue = queue::Queue::load(host(&cli)?.as_str());
+            let Some(id) = queue.current.take() else {
+                return Err(
+                    Failure::usage("No bookmark in progress, run `ding next` first").into(),
+                );
+            };
+            queue.skipped.push(id);
+            show_next(&cli, &mut queue, *open).await?;
+        }
+        Commands::Snooze { id, until, list } => {
+            let cache = cache::Cache::open_default()?;
+            if *list {
+                let snoozed = cache
+                    .snoozed()?
+                    .into_iter()
+                    .map(|(id, until)| snooze::Snoozed { id, until })
+                    .collect::<Vec<_>>();
+                println!("{}", snoozed.to_format(cli.output_format)?);
+            } else {
+                let (Some(id), Some(until)) = (*id, *until) else {
+                    unreachable!("required unless --list")
+                };
+                let bookmark = online_or_cached(
+                    &cli,
+                    |client| async move { Ok(client.bookmark(id).await?) },
+                    |cache| {
+                        cache.bookmark(id)?.ok_or_else(|| {
+                            Failure::not_found(format!("Bookmark {id} is not in the cache")).into()
+                        })
+                    },
+                )
+                .await?;
+                if !cli.dry_run {
+                    cache.snooze(id, until)?;
+                }
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Snoozed until {}",
+                        until.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                    )
+                    .dark_grey()
+                );
+                println!("{}", bookmark.to_format(cli.output_format)?);
+            }
+        }
+        Commands::Wake { ids, inbox_tag } => {
+            let client = create_client(&cli)?;
+            let snoozed = cache::Cache::open_default()?.snoozed()?;
+            if let Some(id) = ids.iter().find(|id| !snoozed.iter().any(|(x, _)| x == *id)) {
+                return Err(Failure::not_found(format!("Bookmark {id} is not snoozed")).into());
+            }
+            let woken = snooze::wake(&client, ids, inbox_tag, cli.dry_run).await?;
+            println!("{}", woken.to_format(cli.output_format)?);
+        }
+        Commands::Yank { id } => {
+            let client = create_client(&cli)?;
+            let bookmark = client.bookmark(*id).await?;
+            clipboard::write_text(bookmark.url.as_str())?;
+            println!("{}", bookmark.to_format(cli.output_format)?);
+        }
+        Commands::Qr { id, png, invert } => {
+            let bookmark = online_or_cached(
+                &cli,
+                |client| async move { Ok(client.bookmark(*id).await?) },
+                |cache| {
+                    cache.bookmark(*id)?.ok_or_else(|| {
+                        Failure::not_found(format!("Bookmark {id} is not in the cache")).into()
+                    })
+                },
+            )
+            .await?;
+            let code = qr::encode(bookmark.url.as_str())?;
+            match png {
+                Some(path) => {
+                    qr::write_png(&code, path)?;
+                    eprintln!("Wrote {}", path.display());
+                }
+                None => println!("{}", qr::to_unicode(&code, *invert)),
+            }
+        }
+        Commands::Tags { all, limit, offset } => {
+            let tags = online_or_cached(
+                &cli,
+                |client| async move { get_tags(&client, *all, *limit, *offset).await },
+                |cache| Ok(paginate(cache.tags()?, *all, *limit, *offset)),
+            )
+            .await?;
+            println!("{}", tags.to_format(cli.output_format)?);
+        }
+        Commands::Bundles => {
+            let client = create_client(&cli)?;
+            let bundles = client.all_bundles(Default::default()).await?;
+            println!("{}", bundles.to_format(cli.output_format)?);
+        }
+        Commands::NativeHost { caller: _ } => {
+            let client = create_client(&cli)?;
+            native::serve(&client).await?;
+        }
+        Commands::Menu {
+            backend,
+            action,
+            query,
+            prefix_tags,
+            max_age,
+            refresh,
+        } => {
+            let host = host(&cli)?.to_string();
+            let max_age = Duration::from_secs(*max_age);
+            let cached = (!refresh)
+                .then(|| menu::cached(&host, query.as_deref(), Some(max_age)))
+                .flatten();
+            let bookmarks = match cached {
+                Some(bookmarks) => bookmarks,
+                None => {
+                    let params = BookmarksRequest {
+                        query: query.clone(),
+                        ..Default::default()
+                    };
+                    match create_client(&cli)?.all_bookmarks(params).await {
+                        Ok(bookmarks) => {
+                            menu::store(&host, query.as_deref(), &bookmarks);
+                            bookmarks
+                        }
+                        // An old list beats no menu when the server is away.
+                        Err(err) if err.is_connection_error() => {
+                            match menu::cached(&host, query.as_deref(), None) {
+                                Some(bookmarks) => {
+                                    eprintln!("{} {err}", "Server unreachable:".yellow());
+                                    bookmarks
+                                }
+                                None => return Err(err.into()),
+                            }
+                        }
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+            };
+            let Some(bookmark) = menu::choose(*backend, &bookmarks, *prefix_tags)? else {
+                return Ok(());
+            };
+            match action {
+                menu::MenuAction::Open => open::that(bookmark.url.as_str())?,
+                menu::MenuAction::Yank => clipboard::write_text(bookmark.url.as_str())?,
+                menu::MenuAction::Print => println!("{}", bookmark.url),
+            }
+        }
+        Commands::Serve { listen } => serve::serve(*listen).await?,
+        Commands::Status {
+            porcelain,
+            inbox_tag,
+            max_age,
+        } => {
+            let host = host(&cli)?.to_string();
+            let cached =
+                max_age.and_then(|x| status::cached(&host, inbox_tag, Duration::from_secs(x)));
+            let status = match cached {
+                Some(status) => status,
+                None => {
+                    let client = create_client(&cli)?;
+                    let status = status::Status::fetch(&client, inbox_tag).await?;
+                    if max_age.is_some() {
+                        status::store(&host, inbox_tag, &status);
+                    }
+                    status
+                }
+            };
+            match porcelain {
+                true => println!("{}", status.porcelain()),
+                false => println!("{}", status.to_format(cli.output_format)?),
+            }
+        }
+        Commands::Watch {
+            interval,
+            query,
+            unread,
+            exec,
+            no_notify,
+            wake,
+            inbox_tag,
+        } => {
+            let watcher = watch::Watcher {
+                client: create_client(&cli)?,
+                host: host(&cli)?.to_string(),
+                query: join_query([query.clone(), unread.then(|| "!unread".to_string())]),
+                notify: !no_notify,
+                exec: exec.clone(),
+                wake: wake.then(|| inbox_tag.clone()),
+            };
+            watcher.run(*interval).await?;
+        }
+        Commands::Feed {
+            kind,
+            format,
+            feed_token,
+            url,
+            limit,
+        } => {
+            let client = create_client(&cli)?;
+            if *url || matches!(format, feed::FeedFormat::Rss) {
+                let token = match (kind, feed_token) {
+                    (FeedKind::Public, _) => "",
+                    (_, Some(token)) => token,
+                    (_, None) => {
+                        return Err(Failure::usage(
+                            "The server's feeds need --feed-token or DING_FEED_TOKEN, \
+                             found in its integration settings",
+                        )
+                        .into())
+                    }
+                };
+                match url {
+                    true => println!("{}", client.feed_url(*kind, token)?),
+                    false => print!("{}", client.feed(*kind, token).await?),
+                }
+                return Ok(());
+            }
+            let params = BookmarksRequest {
+                limit: Some(*limit),
+                sort: Some(BookmarkSort::AddedDesc),
+                ..Default::default()
+            };
+            let bookmarks = match kind {
+                FeedKind::All => client.bookmarks(params).await?.results,
+                FeedKind::Unread => {
+                    let params = BookmarksRequest {
+                        query: Some("!unread".to_string()),
+                        ..params
+                    };
+                    client.bookmarks(params).await?.results
+                }
+                FeedKind::Shared => {
+                    let params = BookmarksRequest {
+                        limit: None,
+                        ..params
+                    };
+                    let mut bookmarks = client.all_bookmarks(params).await?;
+                    bookmarks.retain(|x| x.shared);
+                    bookmarks.truncate(*limit as usize);
+                    bookmarks
+                }
+                FeedKind::Public => {
+                    return Err(Failure::usage("The public feed is only available as rss").into())
+                }
+            };
+            let info = feed::FeedInfo {
+                title: format!("ding: {} bookmarks", kind.as_str()),
+                link: host(&cli)?.clone(),
+            };
+            match format {
+                feed::FeedFormat::Atom => print!("{}", feed::atom(&info, &bookmarks)),
+                _ => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&feed::json_feed(&info, &bookmarks))?
+                ),
+            }
+        }
+        Commands::Server => {
+            let client = create_client(&cli)?;
+            let capabilities = client.capabilities().await?;
+            println!("{}", capabilities.to_format(cli.output_format)?);
         }
         Commands::AddTag { name } => {
             let client = create_client(&cli)?;
@@ -275,26 +1484,403 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         Commands::Add {
             url,
-            title,
-            description,
-            notes,
-            is_archived,
-            unread,
-            shared,
-            tag_names,
+            clipboard,
+            json,
+            fields,
+            strip_tracking,
+            update_if_exists,
+            interactive_tags,
+            scrape,
+            expand,
         } => {
+            let picked_tags = if *interactive_tags {
+                if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+                    return Err(Failure::usage("--interactive-tags needs a terminal").into());
+                }
+                let tags = online_or_cached(
+                    &cli,
+                    |client| async move { Ok(client.all_tags(Default::default()).await?) },
+                    |cache| cache.tags(),
+                )
+                .await?;
+                let tags = tags.into_iter().map(|x| x.name).collect();
+                let selected = fields.tag_names.clone().unwrap_or_default();
+                match tagpicker::pick_tags(tags, selected)? {
+                    Some(picked) => Some(picked),
+                    None => {
+                        eprintln!("Cancelled, nothing added");
+                        return Ok(());
+                    }
+                }
+            } else {
+                None
+            };
+            let requests = if let Some(path) = json {
+                let req = read_json_request(path)?;
+                if req.url.is_none() {
+                    return Err(Failure::usage("JSON bookmark has no url").into());
+                }
+                vec![req]
+            } else if *clipboard {
+                vec![BookmarkRequest::new(clipboard::read_url()?)]
+            } else {
+                url.iter().cloned().map(BookmarkRequest::new).collect()
+            };
+            let mut requests = requests
+                .into_iter()
+                .map(|req| {
+                    let mut req = fields.apply(req);
+                    if let Some(picked) = &picked_tags {
+                        req.tag_names = Some(picked.clone());
+                    }
+                    req
+                })
+                .collect::<Vec<_>>();
+            if *expand || config.add.expand_short_links {
+                let expander = shortlink::Expander::new(
+                    config.add.shorteners.as_deref(),
+                    cli.timeout.unwrap_or(Duration::from_secs(10)),
+                )?;
+                requests = stream::iter(requests)
+                    .map(|req| expander.apply(req))
+                    .buffered(ADD_CONCURRENCY)
+                    .collect()
+                    .await;
+            }
+            let rules = rules::Rules::new(&config.rules)?;
+            let requests = requests.into_iter().map(|req| {
+                let req = if *strip_tracking || config.add.strip_tracking {
+                    req.normalized_with(&config.tracking.params())
+                } else {
+                    req
+                };
+                rules.apply_new(req)
+            });
+            if cli.offline {
+                if *update_if_exists {
+                    return Err(Failure::usage("--update-if-exists needs the server").into());
+                }
+                if *scrape {
+                    return Err(Failure::usage("--scrape cannot be used with --offline").into());
+                }
+                let cache = cache::Cache::open_default()?;
+                for request in requests {
+                    cache.queue(&cache::PendingOp::Create { request })?;
+                }
+                eprintln!("{}", "Offline, queued for the next sync".yellow());
+                return Ok(());
+            }
             let client = create_client(&cli)?;
+            let results = stream::iter(requests)
+                .map(|req| {
+                    let client = &client;
+                    async move {
+                        let req = match *scrape {
+                            true => scrape_request(client, req).await,
+                            false => req,
+                        };
+                        let result = match *update_if_exists {
+                            true => client.upsert_bookmark(req.clone()).await,
+                            false => client
+                                .create_bookmark(req.clone())
+                                .await
+                                .map(Upserted::Created),
+                        };
+                        (req, result)
+                    }
+                })
+                .buffered(ADD_CONCURRENCY)
+                .collect::<Vec<_>>()
+                .await;
+            let total = results.len();
+            let mut bookmarks = vec![];
+            let mut queued = 0;
+            for (request, result) in results {
+                match result {
+                    Ok(upserted) => {
+                        if *update_if_exists {
+                            let status = match upserted.is_created() {
+                                true => "Created".green(),
+                                false => "Updated".yellow(),
+                            };
+                            eprintln!("{status} {}", upserted.bookmark().url);
+                        }
+                        hooks.run(Event::Add, upserted.bookmark()).await;
+                        bookmarks.push(upserted.into_bookmark());
+                    }
+                    Err(err) if err.is_connection_error() && !update_if_exists && !cli.dry_run => {
+                        cache::Cache::open_default()?
+                            .queue(&cache::PendingOp::Create { request })?;
+                        queued += 1;
+                    }
+                    Err(err) => eprintln!(
+                        "{} {}: {err}",
+                        "Failed to add".red(),
+                        request.url.expect("url is always set")
+                    ),
+                }
+            }
+            if queued > 0 {
+                eprintln!(
+                    "{}",
+                    format!("Server unreachable, queued {queued} bookmarks for the next sync")
+                        .yellow()
+                );
+            }
+            if let [bookmark] = bookmarks.as_slice() {
+                println!("{}", bookmark.to_format(cli.output_format)?);
+            } else if !bookmarks.is_empty() {
+                println!("{}", bookmarks.to_format(cli.output_format)?);
+            }
+            if bookmarks.len() + queued < total {
+                let failed = total - bookmarks.len() - queued;
+                return Err(Failure::partial(failed, total, "bookmarks").into());
+            }
+        }
+        Commands::Edit {
+            id,
+            url,
+            json,
+            fields,
+        } => {
+            let mut req = match json {
+                Some(path) => read_json_request(path)?,
+                None => Default::default(),
+            };
+            if url.is_some() {
+                req.url = url.clone();
+            }
+            let req = fields.apply(req);
+            let bookmark = online_or_queued(
+                &cli,
+                |client| {
+                    let req = req.clone();
+                    async move {
+                        // Only send what actually changes.
+                        let bookmark = client.bookmark(*id).await?;
+                        let patch = bookmark.diff(&req);
+                        if patch.is_empty() {
+                            return Ok(bookmark);
+                        }
+                        Ok(client.update_bookmark(*id, patch).await?)
+                    }
+                },
+                cache::PendingOp::Update {
+                    id: *id,
+                    request: req.clone(),
+                },
+            )
+            .await?;
+            print_optional(bookmark, cli.output_format)?;
+        }
+        Commands::Tag { id, changes } => {
+            let client = create_client(&cli)?;
+            let bookmark = client.bookmark(*id).await?;
             let req = BookmarkRequest {
-                url: Some(url.clone()),
-                title: title.clone(),
-                description: description.clone(),
-                notes: notes.clone(),
-                is_archived: *is_archived,
-                unread: *unread,
-                shared: *shared,
-                tag_names: tag_names.clone(),
+                tag_names: Some(apply_tag_changes(&bookmark.tag_names, changes)),
+                ..Default::default()
             };
-            let bookmark = client.create_bookmark(req).await?;
+            let bookmark = client.update_bookmark(*id, req).await?;
+            println!("{}", bookmark.to_format(cli.output_format)?);
+        }
+        Commands::Retag {
+            query,
+            archived,
+            add_tags,
+            remove_tags,
+            concurrency,
+        } => {
+            let client = create_client(&cli)?;
+            let params = BookmarksRequest {
+                query: query.clone(),
+                ..Default::default()
+            };
+            let bookmarks = if *archived {
+                client.all_archived(params).await?
+            } else {
+                client.all_bookmarks(params).await?
+            };
+            let changes = add_tags
+                .iter()
+                .map(|x| format!("+{x}"))
+                .chain(remove_tags.iter().map(|x| format!("-{x}")))
+                .collect::<Vec<_>>();
+            let retagged = bookmarks
+                .into_iter()
+                .map(|mut x| {
+                    let tag_names = apply_tag_changes(&x.tag_names, &changes);
+                    let changed = tag_names != x.tag_names;
+                    x.tag_names = tag_names;
+                    (x, changed)
+                })
+                .collect::<Vec<_>>();
+            let mut report = bulk::Bulk::new("Retagging")
+                .concurrency(*concurrency)
+                .run(
+                    retagged,
+                    |(x, _)| x.id.to_string(),
+                    |(x, changed)| {
+                        let client = &client;
+                        async move {
+                            if !changed {
+                                return Ok(bulk::Outcome::Skipped("tags unchanged".to_string()));
+                            }
+                            let req = BookmarkRequest {
+                                tag_names: Some(x.tag_names),
+                                ..Default::default()
+                            };
+                            Ok(bulk::Outcome::Done(
+                                client.update_bookmark(x.id, req).await?,
+                            ))
+                        }
+                    },
+                )
+                .await;
+            report.succeeded.sort_by_key(|x| x.id);
+            println!("{}", report.succeeded.to_format(cli.output_format)?);
+            report.finish("Updated", "bookmarks")?;
+        }
+        Commands::Maintain { command } => {
+            let client = create_client(&cli)?;
+            match command {
+                MaintainCommands::AutoArchive { filter } => {
+                    let mut bookmarks = client.all_bookmarks(filter.request()).await?;
+                    bookmarks.retain(|x| filter.matches(x));
+                    bulk_by_id(&cli, bookmarks, "Archiving", "Archived", |id| {
+                        archive_bookmark(&client, hooks, id)
+                    })
+                    .await?;
+                }
+                MaintainCommands::PurgeArchived { filter, yes } => {
+                    let mut bookmarks = client.all_archived(filter.request()).await?;
+                    bookmarks.retain(|x| filter.matches(x));
+                    if !yes && !cli.dry_run && !bookmarks.is_empty() {
+                        return Err(Failure::usage(format!(
+                            "{} archived bookmarks match, add --yes to delete them",
+                            bookmarks.len()
+                        ))
+                        .into());
+                    }
+                    bulk_by_id(&cli, bookmarks, "Deleting", "Deleted", |id| {
+                        delete_bookmark(&client, hooks, id)
+                    })
+                    .await?;
+                }
+                MaintainCommands::MarkRead { filter } => {
+                    let mut bookmarks = client.all_bookmarks(filter.request()).await?;
+                    bookmarks.retain(|x| x.unread && filter.matches(x));
+                    bulk_by_id(&cli, bookmarks, "Updating", "Marked as read", |id| {
+                        let client = &client;
+                        async move {
+                            let req = BookmarkRequest {
+                                unread: Some(false),
+                                ..Default::default()
+                            };
+                            Ok(client.update_bookmark(id, req).await?)
+                        }
+                    })
+                    .await?;
+                }
+            }
+        }
+        Commands::Rules {
+            command: RulesCommands::Apply { query, archived },
+        } => {
+            let client = create_client(&cli)?;
+            let rules = rules::Rules::new(&config.rules)?;
+            let params = BookmarksRequest {
+                query: query.clone(),
+                ..Default::default()
+            };
+            let bookmarks = if *archived {
+                client.all_archived(params).await?
+            } else {
+                client.all_bookmarks(params).await?
+            };
+            let changes = bookmarks
+                .into_iter()
+                .map(|x| {
+                    let req = rules.apply_existing(&x);
+                    (x, req)
+                })
+                .collect::<Vec<_>>();
+            let report = bulk::Bulk::new("Applying rules")
+                .run(
+                    changes,
+                    |(x, _)| format!("{} ({})", x.id, x.url),
+                    |(x, req)| {
+                        let client = &client;
+                        async move {
+                            let Some(req) = req else {
+                                return Ok(bulk::Outcome::Skipped(
+                                    "no rule changes them".to_string(),
+                                ));
+                            };
+                            Ok(bulk::Outcome::Done(
+                                client.update_bookmark(x.id, req).await?,
+                            ))
+                        }
+                    },
+                )
+                .await;
+            println!("{}", report.succeeded.to_format(cli.output_format)?);
+            report.finish("Updated", "bookmarks")?;
+        }
+        Commands::Sync { force, full } => {
+            if cli.dry_run {
+                return Err(Failure::usage(
+                    "sync cannot be dry-run, it replays the offline queue and rewrites the cache",
+                )
+                .into());
+            }
+            let client = create_client(&cli)?;
+            let mut cache = cache::Cache::open_default()?;
+            let report = sync::sync(&client, &mut cache, *force, *full).await?;
+            eprintln!(
+                "Replayed {} offline changes, {} {} bookmarks and {} tags",
+                report.replayed,
+                if report.incremental {
+                    "updated"
+                } else {
+                    "synced"
+                },
+                report.bookmarks,
+                report.tags
+            );
+            if report.conflicts > 0 {
+                eprintln!("Rerun with --force to apply the conflicting changes anyway");
+                return Err(Failure::partial(
+                    report.conflicts,
+                    report.replayed + report.conflicts,
+                    "offline changes",
+                )
+                .into());
+            }
+        }
+        Commands::Search {
+            terms,
+            archived,
+            fuzzy,
+            limit,
+        } => {
+            let cache = cache::Cache::open_default()?;
+            if cache.last_sync()?.is_none() {
+                anyhow::bail!("Search index is empty, run `sync` first");
+            }
+            let hits = cache.search(terms, *archived, *fuzzy, *limit)?;
+            println!("{}", hits.to_format(cli.output_format)?);
+        }
+        Commands::Show { id } => {
+            let bookmark = online_or_cached(
+                &cli,
+                |client| async move { Ok(client.bookmark(*id).await?) },
+                |cache| {
+                    cache.bookmark(*id)?.ok_or_else(|| {
+                        Failure::not_found(format!("Bookmark {id} is not in the cache")).into()
+                    })
+                },
+            )
+            .await?;
             println!("{}", bookmark.to_format(cli.output_format)?);
         }
         Commands::Bookmarks {
@@ -303,67 +1889,864 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             offset,
             all,
             archived,
+            domain,
+            sort,
+            bundle,
+            dates,
+            group_by,
+            count,
+            ids,
         } => {
-            let client = create_client(&cli)?;
-            let bookmarks = match (all, archived) {
-                (true, true) => {
-                    client
-                        .all_archived(BookmarksRequest {
-                            query: query.clone(),
-                            ..Default::default()
-                        })
-                        .await?
-                }
-                (true, false) => {
-                    client
-                        .all_bookmarks(BookmarksRequest {
-                            query: query.clone(),
-                            ..Default::default()
-                        })
-                        .await?
-                }
-                (false, false) => {
-                    client
-                        .bookmarks(BookmarksRequest {
-                            query: query.clone(),
+            let query = domain_query(query, domain);
+            if *count {
+                let total = online_or_cached(
+                    &cli,
+                    |client| {
+                        let query = query.clone();
+                        async move {
+                            let params = BookmarksRequest {
+                                query,
+                                bundle: *bundle,
+                                modified_since: dates.modified_after,
+                                added_since: dates.added_after,
+                                ..Default::default()
+                            };
+                            if domain.is_some() || !dates.is_empty() {
+                                // These are checked here too, so every match
+                                // has to be loaded.
+                                let bookmarks = match archived {
+                                    true => client.all_archived(params).await?,
+                                    false => client.all_bookmarks(params).await?,
+                                };
+                                let mut bookmarks = filter_by_domain(bookmarks, domain.as_deref());
+                                bookmarks.retain(|x| dates.matches(x));
+                                return Ok(bookmarks.len() as u64);
+                            }
+                            let params = BookmarksRequest {
+                                limit: Some(1),
+                                ..params
+                            };
+                            let page = match archived {
+                                true => client.archived(params).await?,
+                                false => client.bookmarks(params).await?,
+                            };
+                            Ok(page.count)
+                        }
+                    },
+                    |cache| {
+                        if bundle.is_some() {
+                            anyhow::bail!("Bundles are not available offline");
+                        }
+                        let bookmarks = cache.bookmarks(*archived, query.as_deref())?;
+                        let mut bookmarks = filter_by_domain(bookmarks, domain.as_deref());
+                        bookmarks.retain(|x| dates.matches(x));
+                        Ok(bookmarks.len() as u64)
+                    },
+                )
+                .await?;
+                println!("{total}");
+                return Ok(());
+            }
+            let bookmarks = online_or_cached(
+                &cli,
+                |client| {
+                    let query = query.clone();
+                    async move {
+                        let params = BookmarksRequest {
+                            query,
                             limit: *limit,
                             offset: *offset,
+                            sort: *sort,
+                            bundle: *bundle,
+                            modified_since: dates.modified_after,
+                            added_since: dates.added_after,
+                        };
+                        Ok(match (all, archived) {
+                            (true, true) => client.all_archived(params).await?,
+                            (true, false) => client.all_bookmarks(params).await?,
+                            (false, false) => client.bookmarks(params).await?.results,
+                            (false, true) => client.archived(params).await?.results,
                         })
-                        .await?
-                        .results
+                    }
+                },
+                |cache| {
+                    if bundle.is_some() {
+                        anyhow::bail!("Bundles are not available offline");
+                    }
+                    let mut bookmarks = cache.bookmarks(*archived, query.as_deref())?;
+                    bookmarks.retain(|x| dates.matches(x));
+                    if let Some(sort) = sort {
+                        sort.sort(&mut bookmarks);
+                    }
+                    Ok(paginate(bookmarks, *all, *limit, *offset))
+                },
+            )
+            .await?;
+            let mut bookmarks = filter_by_domain(bookmarks, domain.as_deref());
+            bookmarks.retain(|x| dates.matches(x));
+            if *ids {
+                for bookmark in &bookmarks {
+                    println!("{}", bookmark.id);
                 }
-                (false, true) => {
-                    client
-                        .archived(BookmarksRequest {
-                            query: query.clone(),
-                            limit: *limit,
-                            offset: *offset,
+                return Ok(());
+            }
+            match group_by {
+                Some(by) => println!(
+                    "{}",
+                    group::group(bookmarks, *by).to_format(cli.output_format)?
+                ),
+                None => println!("{}", bookmarks.to_format(cli.output_format)?),
+            }
+        }
+        Commands::Domains { query, archived } => {
+            let client = create_client(&cli)?;
+            let params = BookmarksRequest {
+                query: query.clone(),
+                ..Default::default()
+            };
+            let bookmarks = if *archived {
+                client.all_archived(params).await?
+            } else {
+                client.all_bookmarks(params).await?
+            };
+            println!(
+                "{}",
+                count_domains(&bookmarks).to_format(cli.output_format)?
+            );
+        }
+        Commands::Dedupe {
+            auto,
+            strip_tracking,
+        } => {
+            let client = create_client(&cli)?;
+            let mut bookmarks = client.all_bookmarks(Default::default()).await?;
+            bookmarks.extend(client.all_archived(Default::default()).await?);
+            let mut merged = vec![];
+            let tracking = strip_tracking.then(|| config.tracking.params());
+            for group in dedupe::find_duplicates(bookmarks, tracking.as_ref()) {
+                if !auto {
+                    println!("{}", group.to_human_format()?);
+                    let question = format!("Merge into bookmark {}?", group[0].id);
+                    match dedupe::confirm(&question)? {
+                        dedupe::Answer::Yes => {}
+                        dedupe::Answer::No => continue,
+                        dedupe::Answer::Quit => break,
+                    }
+                }
+                merged.push(dedupe::merge_group(&client, &group).await?);
+            }
+            println!("{}", merged.to_format(cli.output_format)?);
+        }
+        Commands::Check { url } => {
+            let client = create_client(&cli)?;
+            let check = client.check_bookmark(url).await?;
+            match check.bookmark {
+                Some(bookmark) => {
+                    eprintln!("{}", "Already bookmarked".yellow());
+                    println!("{}", bookmark.to_format(cli.output_format)?);
+                }
+                None => println!("{}", to_colored_json_auto(&check)?),
+            }
+        }
+        Commands::Pick {
+            query,
+            archived,
+            action,
+            picker,
+            builtin,
+        } => {
+            let bookmarks = online_or_cached(
+                &cli,
+                |client| {
+                    let params = BookmarksRequest {
+                        query: query.clone(),
+                        ..Default::default()
+                    };
+                    async move {
+                        Ok(match archived {
+                            true => client.all_archived(params).await?,
+                            false => client.all_bookmarks(params).await?,
                         })
+                    }
+                },
+                |cache| cache.bookmarks(*archived, query.as_deref()),
+            )
+            .await?;
+            let ids = if *builtin {
+                selector::select(&bookmarks)?
+            } else {
+                let command = match picker {
+                    Some(picker) => picker.split_whitespace().map(String::from).collect(),
+                    None => config.pick.command.clone(),
+                };
+                pick::pick(&command, &bookmarks)?
+            };
+            let selected = bookmarks
+                .into_iter()
+                .filter(|x| ids.contains(&x.id))
+                .collect::<Vec<_>>();
+            for bookmark in selected {
+                let id = bookmark.id;
+                let bookmark = match action {
+                    pick::PickAction::Print => Some(bookmark),
+                    pick::PickAction::Open => {
+                        open::that(bookmark.url.as_str())?;
+                        Some(bookmark)
+                    }
+                    pick::PickAction::Yank => {
+                        clipboard::write_text(bookmark.url.as_str())?;
+                        Some(bookmark)
+                    }
+                    pick::PickAction::Archive => {
+                        online_or_queued(
+                            &cli,
+                            |client| async move { archive_bookmark(&client, hooks, id).await },
+                            cache::PendingOp::Archive { id },
+                        )
                         .await?
-                        .results
+                    }
+                    pick::PickAction::Edit => {
+                        let Some(request) = pick::edit(&bookmark)? else {
+                            continue;
+                        };
+                        online_or_queued(
+                            &cli,
+                            |client| {
+                                let request = request.clone();
+                                async move { Ok(client.update_bookmark(id, request).await?) }
+                            },
+                            cache::PendingOp::Update {
+                                id,
+                                request: request.clone(),
+                            },
+                        )
+                        .await?
+                    }
+                };
+                print_optional(bookmark, cli.output_format.clone())?;
+            }
+        }
+        Commands::Import { source, resume } => {
+            let (file, requests, profile) = match source {
+                ImportSource::Chrome { path } => {
+                    let path = path
+                        .clone()
+                        .or_else(import::chrome::default_path)
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "No Chrome profile found, pass the path to the Bookmarks file"
+                            )
+                        })?;
+                    let requests = import::read_file(&import::chrome::Chrome, &path)?;
+                    (path, requests, true)
                 }
+                ImportSource::Wallabag { file } => (
+                    file.clone(),
+                    import::read_file(&import::wallabag::Wallabag, file)?,
+                    false,
+                ),
+                ImportSource::Shiori { file } => (
+                    file.clone(),
+                    import::read_file(&import::shiori::Shiori, file)?,
+                    false,
+                ),
+                ImportSource::Shaarli { file } => (
+                    file.clone(),
+                    import::read_file(&import::shaarli::Shaarli, file)?,
+                    false,
+                ),
+                ImportSource::Netscape { file } => (
+                    file.clone(),
+                    import::read_file(&import::netscape::Netscape, file)?,
+                    false,
+                ),
+                ImportSource::Firefox { places, folders } => {
+                    let path = places
+                        .clone()
+                        .or_else(import::firefox::default_path)
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "No Firefox profile found, pass the path to places.sqlite"
+                            )
+                        })?;
+                    let requests = import::firefox::read(&path, *folders)?;
+                    (path, requests, true)
+                }
+            };
+            let checkpoint = match profile {
+                true => import::resume::Checkpoint::for_profile(&file, &requests, *resume),
+                false => import::resume::Checkpoint::new(&file, *resume)?,
+            };
+            if *resume {
+                match checkpoint.skip {
+                    0 if checkpoint.lost => eprintln!(
+                        "The bookmark the interrupted import of {} stopped at is gone, \
+                         starting from the first row",
+                        file.display()
+                    ),
+                    0 => eprintln!(
+                        "No interrupted import of {}, starting from the first row",
+                        file.display()
+                    ),
+                    rows => eprintln!("Resuming after row {rows} of {}", requests.len()),
+                }
+            }
+            import_requests(&cli, &config, hooks, requests, checkpoint).await?;
+        }
+        Commands::Export {
+            command:
+                ExportCommands::Vault {
+                    dir,
+                    query,
+                    archived,
+                    full,
+                    prune,
+                },
+        } => {
+            let client = create_client(&cli)?;
+            let bookmarks = export_bookmarks(&client, query, *archived).await?;
+            let report = export::vault::export(&bookmarks, dir, *full, *prune)?;
+            eprintln!(
+                "Wrote {} files, {} unchanged, {} removed",
+                report.written, report.unchanged, report.removed
+            );
+        }
+        Commands::Export {
+            command:
+                ExportCommands::Feed {
+                    query,
+                    archived,
+                    format,
+                    output,
+                    title,
+                    link,
+                    limit,
+                },
+        } => {
+            let client = create_client(&cli)?;
+            let mut bookmarks = export_bookmarks(&client, query, *archived).await?;
+            bookmarks.sort_by_key(|x| std::cmp::Reverse((x.date_added, x.id)));
+            bookmarks.truncate(limit.unwrap_or(usize::MAX));
+            let info = feed::FeedInfo {
+                title: title.clone(),
+                link: match link {
+                    Some(link) => link.clone(),
+                    None => host(&cli)?.clone(),
+                },
+            };
+            let feed = export::feed::render(*format, &info, &bookmarks)?;
+            write_export(output.as_deref(), &feed, bookmarks.len())?;
+        }
+        Commands::Export {
+            command:
+                ExportCommands::SiteData {
+                    query,
+                    archived,
+                    format,
+                    group_by,
+                    output,
+                },
+        } => {
+            let client = create_client(&cli)?;
+            let mut bookmarks = export_bookmarks(&client, query, *archived).await?;
+            bookmarks.sort_by_key(|x| std::cmp::Reverse((x.date_added, x.id)));
+            let data = export::site::render(&bookmarks, *group_by, *format)?;
+            write_export(output.as_deref(), &data, bookmarks.len())?;
+        }
+        Commands::Linkcheck {
+            query,
+            archived,
+            concurrency,
+            show_ok,
+            tag_broken,
+        } => {
+            let client = create_client(&cli)?;
+            let params = BookmarksRequest {
+                query: query.clone(),
+                ..Default::default()
+            };
+            let bookmarks = if *archived {
+                client.all_archived(params).await?
+            } else {
+                client.all_bookmarks(params).await?
             };
-            println!("{}", bookmarks.to_format(cli.output_format)?);
+            let checker = linkcheck::LinkChecker::new(
+                cli.timeout.unwrap_or(Duration::from_secs(10)),
+                *concurrency,
+            )?;
+            let reports = checker.check_all(&bookmarks).await;
+            if let Some(tag) = tag_broken {
+                linkcheck::tag_dead_links(&client, &bookmarks, &reports, tag).await?;
+            }
+            let reports = reports
+                .into_iter()
+                .filter(|x| *show_ok || !x.is_ok())
+                .collect::<Vec<_>>();
+            println!("{}", reports.to_format(cli.output_format)?);
+        }
+        Commands::ArchiveContent {
+            ids,
+            query,
+            archived,
+            upload,
+            force,
+        } => {
+            if cli.dry_run {
+                return Err(Failure::usage(
+                    "archive-content cannot be dry-run, it saves the pages as local files",
+                )
+                .into());
+            }
+            let client = create_client(&cli)?;
+            let bookmarks = bookmarks_by_ids_or_query(&client, ids, query, *archived).await?;
+            let downloader =
+                readable::Downloader::new(cli.timeout.unwrap_or(Duration::from_secs(30)))?;
+            let mut reports = vec![];
+            for bookmark in &bookmarks {
+                let result = match downloader.save(&client, bookmark, *upload, *force).await {
+                    Ok(result) => result,
+                    Err(err) => readable::ArchiveResult::Failed {
+                        message: format!("{err:#}"),
+                    },
+                };
+                reports.push(readable::ArchiveReport {
+                    id: bookmark.id,
+                    url: bookmark.url.clone(),
+                    result,
+                });
+            }
+            println!("{}", reports.to_format(cli.output_format)?);
+            let failed = reports
+                .iter()
+                .filter(|x| matches!(x.result, readable::ArchiveResult::Failed { .. }))
+                .count();
+            if failed > 0 {
+                return Err(Failure::partial(failed, reports.len(), "bookmarks").into());
+            }
+        }
+        Commands::Wayback {
+            ids,
+            query,
+            archived,
+            force,
+            max_wait,
+            poll_interval,
+        } => {
+            if cli.dry_run {
+                return Err(Failure::usage(
+                    "wayback cannot be dry-run, the Wayback Machine is not reached through the server",
+                )
+                .into());
+            }
+            let client = create_client(&cli)?;
+            let bookmarks = bookmarks_by_ids_or_query(&client, ids, query, *archived).await?;
+            let wayback = wayback::Wayback::new(*max_wait, *poll_interval)?;
+            let mut reports = vec![];
+            // One at a time, the Wayback Machine limits snapshot requests.
+            for bookmark in &bookmarks {
+                let result = match wayback.archive(&client, bookmark, *force).await {
+                    Ok(result) => result,
+                    Err(err) => wayback::WaybackResult::Failed {
+                        message: format!("{err:#}"),
+                    },
+                };
+                reports.push(wayback::WaybackReport {
+                    id: bookmark.id,
+                    url: bookmark.url.clone(),
+                    result,
+                });
+            }
+            println!("{}", reports.to_format(cli.output_format)?);
+            let failed = reports
+                .iter()
+                .filter(|x| matches!(x.result, wayback::WaybackResult::Failed { .. }))
+                .count();
+            if failed > 0 {
+                return Err(Failure::partial(failed, reports.len(), "bookmarks").into());
+            }
+        }
+        Commands::Backup { file } => {
+            let client = create_client(&cli)?;
+            let backup = backup::create(&client).await?;
+            backup::write(&backup, file)?;
+            eprintln!(
+                "Saved {} bookmarks and {} tags to {}",
+                backup.bookmarks.len(),
+                backup.tags.len(),
+                file.display()
+            );
+        }
+        Commands::Restore { file } => {
+            let client = create_client(&cli)?;
+            let backup = backup::read(file)?;
+            let report = backup::restore(&client, &backup).await?;
+            eprintln!(
+                "Restored {} bookmarks and {} tags from {}",
+                report.bookmarks,
+                report.tags,
+                backup.created.format("%Y-%m-%d %H:%M UTC")
+            );
         }
         Commands::Completion { shell } => {
             let mut cmd = Cli::command();
             let cmd_name: String = cmd.get_name().into();
-            clap_complete::generate(*shell, &mut cmd, cmd_name, &mut std::io::stdout());
+            shell.generate(&mut cmd, cmd_name, &mut std::io::stdout());
         }
     };
     Ok(())
 }
 
+/// Create imported bookmarks, applying the configured rules first. URLs
+/// already on the server, archived or not, are skipped, as are the rows
+/// `checkpoint` says an earlier run got through. The `on_add` hooks run for
+/// every bookmark created.
+async fn import_requests(
+    cli: &Cli,
+    config: &config::Config,
+    hooks: &Hooks<'_>,
+    requests: Vec<BookmarkRequest>,
+    checkpoint: import::resume::Checkpoint,
+) -> Result<()> {
+    let client = create_client(cli)?;
+    let mut existing = client.all_bookmarks(Default::default()).await?;
+    existing.extend(client.all_archived(Default::default()).await?);
+    let mut seen = existing
+        .iter()
+        .map(|x| dedupe::normalize_url(&x.url, None))
+        .collect::<HashSet<_>>();
+    let rules = rules::Rules::new(&config.rules)?;
+    // Paired with whether the URL is new, in the order of the import.
+    let requests = requests
+        .into_iter()
+        .skip(checkpoint.skip)
+        .map(|req| {
+            let url = req.url.as_ref().expect("imported bookmarks have an url");
+            let new = seen.insert(dedupe::normalize_url(url, None));
+            (rules.apply_new(req), new)
+        })
+        .collect::<Vec<_>>();
+    let mut bulk = bulk::Bulk::new("Importing").concurrency(ADD_CONCURRENCY);
+    if !cli.dry_run {
+        let checkpoint = checkpoint.clone();
+        bulk = bulk.checkpoint(move |rows| checkpoint.save(rows));
+    }
+    let report = bulk
+        .run(
+            requests,
+            |(req, _)| {
+                let url = req.url.as_ref().expect("imported bookmarks have an url");
+                url.to_string()
+            },
+            |(req, new)| {
+                let client = &client;
+                async move {
+                    if !new {
+                        return Ok(bulk::Outcome::Skipped("already on the server".to_string()));
+                    }
+                    let bookmark = client.create_bookmark(req).await?;
+                    hooks.run(Event::Add, &bookmark).await;
+                    Ok(bulk::Outcome::Done(bookmark))
+                }
+            },
+        )
+        .await;
+    let result = report.finish("Imported", "bookmarks");
+    if cli.dry_run {
+        return result;
+    }
+    if report.failed.is_empty() {
+        checkpoint.clear();
+    } else {
+        eprintln!("Run the import again with --resume to retry from the first failure");
+    }
+    result
+}
+
+/// The server, or a usage error if it is not configured.
+fn host(cli: &Cli) -> Result<&Url> {
+    Ok(cli
+        .host
+        .as_ref()
+        .ok_or_else(|| Failure::usage("No server given, set --host or DING_HOST"))?)
+}
+
 fn create_client(cli: &Cli) -> Result<DingClient> {
-    Ok(DingClient::new(
-        cli.host.clone().expect("Not Found URL"),
-        cli.token.clone().expect("Not Found Token").to_string(),
-    ))
+    let host = host(cli)?.clone();
+    let token = cli
+        .token
+        .clone()
+        .ok_or_else(|| Failure::usage("No API token given, set --token or DING_TOKEN"))?;
+    let mut builder = DingClient::builder(host, token);
+    if let Some(rate) = cli.max_rps {
+        builder = builder.max_requests_per_second(rate);
+    }
+    if let Some(timeout) = cli.timeout {
+        builder = builder.timeout(timeout);
+    }
+    let proxy = match &cli.proxy {
+        Some(proxy) if !["http", "https", "socks5", "socks5h"].contains(&proxy.scheme()) => {
+            return Err(Failure::usage(format!(
+                "Unsupported proxy {proxy}, expected an http, https, socks5 or socks5h URL"
+            ))
+            .into());
+        }
+        Some(proxy) => Some(
+            reqwest::Proxy::all(proxy.as_str())
+                .with_context(|| format!("Invalid proxy {proxy}"))?,
+        ),
+        None => None,
+    };
+    if cli.dry_run {
+        let mut http = reqwest::Client::builder();
+        if let Some(proxy) = proxy {
+            http = http.proxy(proxy);
+        }
+        let transport = ReqwestTransport::new(http.build()?);
+        builder = builder.transport(dryrun::DryRun::new(transport));
+    } else if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy);
+    }
+    // The only place retries happen, so that `--retries` is the number of
+    // extra attempts per request, also for bulk commands.
+    builder = builder.retry_policy(RetryPolicy::new(cli.retries).initial_delay(cli.retry_delay));
+    if cli.http_cache {
+        if let Some(dir) = dirs::cache_dir() {
+            builder = builder.http_cache(DiskCache::new(dir.join("ding").join("http")));
+        }
+    }
+    Ok(builder.build())
+}
+
+/// Run `online` against the server, or `offline` against the sync cache when
+/// `--offline` is given or the server can't be reached.
+async fn online_or_cached<T, F>(
+    cli: &Cli,
+    online: impl FnOnce(DingClient) -> F,
+    offline: impl FnOnce(&cache::Cache) -> Result<T>,
+) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    if !cli.offline {
+        match online(create_client(cli)?).await {
+            Ok(result) => return Ok(result),
+            Err(err)
+                if err
+                    .downcast_ref::<DingError>()
+                    .is_some_and(|x| x.is_connection_error()) =>
+            {
+                eprintln!("{} {err}", "Server unreachable:".yellow());
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    let cache = cache::Cache::open_default()?;
+    match cache.last_sync()? {
+        Some(last_sync) => eprintln!(
+            "{} {}",
+            "Offline, cache synced".yellow(),
+            cache::describe_age(last_sync)
+        ),
+        None => return Err(anyhow::anyhow!("Offline cache is empty, run `sync` first")),
+    }
+    offline(&cache)
 }
 
-async fn archive_bookmark(client: &DingClient, id: u64) -> Result<Bookmark> {
+/// Run the write `online`, or queue it for the next sync when `--offline` is
+/// given or the server can't be reached. Offline, the locally updated copy of
+/// the bookmark is returned if it is cached.
+async fn online_or_queued<F>(
+    cli: &Cli,
+    online: impl FnOnce(DingClient) -> F,
+    op: cache::PendingOp,
+) -> Result<Option<Bookmark>>
+where
+    F: Future<Output = Result<Bookmark>>,
+{
+    if !cli.offline {
+        match online(create_client(cli)?).await {
+            Ok(bookmark) => return Ok(Some(bookmark)),
+            Err(err)
+                if !cli.dry_run
+                    && err
+                        .downcast_ref::<DingError>()
+                        .is_some_and(|x| x.is_connection_error()) =>
+            {
+                eprintln!("{} {err}", "Server unreachable:".yellow());
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    let bookmark = cache::Cache::open_default()?.queue(&op)?;
+    eprintln!("{}", "Offline, queued for the next sync".yellow());
+    Ok(bookmark)
+}
+
+fn print_optional(bookmark: Option<Bookmark>, format: OutputFormat) -> Result<()> {
+    if let Some(bookmark) = bookmark {
+        println!("{}", bookmark.to_format(format)?);
+    }
+    Ok(())
+}
+
+/// Show the oldest unread bookmark that was not skipped and remember it as the
+/// one being read.
+async fn show_next(cli: &Cli, queue: &mut queue::Queue, open: bool) -> Result<()> {
+    let mut unread = online_or_cached(
+        cli,
+        |client| async move {
+            let params = BookmarksRequest {
+                query: Some("!unread".to_string()),
+                ..Default::default()
+            };
+            Ok(client.all_bookmarks(params).await?)
+        },
+        |cache| cache.bookmarks(false, Some("!unread")),
+    )
+    .await?;
+    let snoozed = cache::Cache::open_default()?.snoozed()?;
+    unread.retain(|x| !snoozed.iter().any(|(id, _)| *id == x.id));
+    let bookmark = queue.pick(&unread);
+    queue.current = bookmark.map(|x| x.id);
+    queue.save()?;
+    let Some(bookmark) = bookmark else {
+        if queue.skipped.is_empty() {
+            return Err(Failure::not_found("No unread bookmarks left").into());
+        }
+        return Err(Failure::not_found(format!(
+            "Only skipped bookmarks are left, run `ding next --reset` to bring back {}",
+            queue.skipped.len()
+        ))
+        .into());
+    };
+    eprintln!(
+        "{}",
+        format!("{} unread, {} skipped", unread.len(), queue.skipped.len()).dark_grey()
+    );
+    if open {
+        open::that(bookmark.url.as_str())?;
+    }
+    println!("{}", bookmark.to_format(cli.output_format.clone())?);
+    Ok(())
+}
+
+fn paginate<T>(items: Vec<T>, all: bool, limit: Option<u64>, offset: Option<u64>) -> Vec<T> {
+    if all {
+        return items;
+    }
+    items
+        .into_iter()
+        .skip(offset.unwrap_or_default() as usize)
+        .take(limit.map_or(usize::MAX, |x| x as usize))
+        .collect()
+}
+
+/// Fill in the title, description and canonical URL of `req` from its page.
+/// A page that cannot be read is only warned about.
+async fn scrape_request(client: &DingClient, req: BookmarkRequest) -> BookmarkRequest {
+    let url = req.url.clone().expect("url is always set");
+    match client.scrape(&url).await {
+        Ok(page) => req.prefill(&page),
+        Err(err) => {
+            eprintln!("{} {url}: {err}", "Failed to scrape".yellow());
+            req
+        }
+    }
+}
+
+async fn archive_bookmark(client: &DingClient, hooks: &Hooks<'_>, id: u64) -> Result<Bookmark> {
     client.archive_bookmark(id).await?;
-    Ok(client.bookmark(id).await?)
+    let bookmark = client.bookmark(id).await?;
+    hooks.run(Event::Archive, &bookmark).await;
+    Ok(bookmark)
+}
+
+/// The bookmarks matching `query` to export, archived ones too if `archived`.
+async fn export_bookmarks(
+    client: &DingClient,
+    query: &Option<String>,
+    archived: bool,
+) -> Result<Vec<Bookmark>> {
+    let params = BookmarksRequest {
+        query: query.clone(),
+        ..Default::default()
+    };
+    let mut bookmarks = client.all_bookmarks(params.clone()).await?;
+    if archived {
+        bookmarks.extend(client.all_archived(params).await?);
+    }
+    Ok(bookmarks)
+}
+
+/// Write an export of `count` bookmarks to `path`, or to stdout.
+fn write_export(path: Option<&Path>, text: &str, count: usize) -> Result<()> {
+    match path {
+        Some(path) => {
+            std::fs::write(path, text)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!("Wrote {count} bookmarks to {}", path.display());
+        }
+        None => print!("{text}"),
+    }
+    Ok(())
+}
+
+/// The bookmarks with `ids`, or those matching `query` if no ids are given.
+async fn bookmarks_by_ids_or_query(
+    client: &DingClient,
+    ids: &[u64],
+    query: &Option<String>,
+    archived: bool,
+) -> Result<Vec<Bookmark>> {
+    if ids.is_empty() {
+        let params = BookmarksRequest {
+            query: query.clone(),
+            ..Default::default()
+        };
+        return Ok(match archived {
+            true => client.all_archived(params).await?,
+            false => client.all_bookmarks(params).await?,
+        });
+    }
+    let mut bookmarks = vec![];
+    for id in ids {
+        bookmarks.push(client.bookmark(*id).await?);
+    }
+    Ok(bookmarks)
+}
+
+fn query_request(query: &str) -> BookmarksRequest {
+    BookmarksRequest {
+        query: Some(query.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Run `op` on the id of every bookmark in `bookmarks` and print the
+/// bookmarks it returned.
+async fn bulk_by_id<F, Fut>(
+    cli: &Cli,
+    bookmarks: Vec<Bookmark>,
+    label: &str,
+    done: &str,
+    op: F,
+) -> Result<()>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<Bookmark>>,
+{
+    let report = bulk::Bulk::new(label)
+        .run(
+            bookmarks,
+            |x| format!("{} ({})", x.id, x.url),
+            |x| {
+                let result = op(x.id);
+                async move { Ok(bulk::Outcome::Done(result.await?)) }
+            },
+        )
+        .await;
+    println!("{}", report.succeeded.to_format(cli.output_format.clone())?);
+    report.finish(done, "bookmarks")
 }
 
 async fn unarchive_bookmark(client: &DingClient, id: u64) -> Result<Bookmark> {
@@ -371,9 +2754,10 @@ async fn unarchive_bookmark(client: &DingClient, id: u64) -> Result<Bookmark> {
     Ok(client.bookmark(id).await?)
 }
 
-async fn delete_bookmark(client: &DingClient, id: u64) -> Result<Bookmark> {
+async fn delete_bookmark(client: &DingClient, hooks: &Hooks<'_>, id: u64) -> Result<Bookmark> {
     let bookmark = client.bookmark(id).await?;
     client.delete_bookmark(id).await?;
+    hooks.run(Event::Delete, &bookmark).await;
     Ok(bookmark)
 }
 
@@ -389,3 +2773,139 @@ async fn get_tags(
         client.tags(TagsRequest { limit, offset }).await?.results
     })
 }
+
+fn read_json_request(path: &Path) -> Result<BookmarkRequest> {
+    Ok(if path == Path::new("-") {
+        serde_json::from_reader(std::io::stdin().lock())?
+    } else {
+        serde_json::from_reader(std::fs::File::open(path)?)?
+    })
+}
+
+/// Apply `+tag` (or bare `tag`) and `-tag` changes in order. Linkding tags
+/// ignore case, so a tag is only added if no tag differing in case exists,
+/// and removing one removes it in any case.
+fn apply_tag_changes(tags: &[String], changes: &[String]) -> Vec<String> {
+    let mut tags = tags.to_vec();
+    for change in changes {
+        if let Some(name) = change.strip_prefix('-') {
+            tags.retain(|x| !x.eq_ignore_ascii_case(name));
+        } else {
+            let name = change.strip_prefix('+').unwrap_or(change);
+            if !tags.iter().any(|x| x.eq_ignore_ascii_case(name)) {
+                tags.push(name.to_string());
+            }
+        }
+    }
+    tags
+}
+
+fn join_query(parts: impl IntoIterator<Item = Option<String>>) -> Option<String> {
+    let parts = parts.into_iter().flatten().collect::<Vec<_>>();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+fn domain_query(query: &Option<String>, domain: &Option<String>) -> Option<String> {
+    // Linkding matches search terms against the URL too, so the domain narrows
+    // the server-side result before the exact host check.
+    join_query([query.clone(), domain.clone()])
+}
+
+async fn random_bookmark(
+    client: &DingClient,
+    query: Option<String>,
+    archived: bool,
+) -> Result<Option<Bookmark>> {
+    let list = |offset| {
+        let params = BookmarksRequest {
+            query: query.clone(),
+            limit: Some(1),
+            offset: Some(offset),
+            ..Default::default()
+        };
+        async move {
+            if archived {
+                client.archived(params).await
+            } else {
+                client.bookmarks(params).await
+            }
+        }
+    };
+    let count = list(0).await?.count;
+    if count == 0 {
+        return Ok(None);
+    }
+    Ok(list(rand::random_range(0..count))
+        .await?
+        .results
+        .into_iter()
+        .next())
+}
+
+fn matches_domain(url: &Url, domain: &str) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let host = host.to_lowercase();
+    let domain = domain.trim_start_matches("www.").to_lowercase();
+    let host = host.trim_start_matches("www.");
+    host == domain || host.ends_with(&format!(".{domain}"))
+}
+
+fn filter_by_domain(bookmarks: Vec<Bookmark>, domain: Option<&str>) -> Vec<Bookmark> {
+    match domain {
+        Some(domain) => bookmarks
+            .into_iter()
+            .filter(|x| matches_domain(&x.url, domain))
+            .collect(),
+        None => bookmarks,
+    }
+}
+
+fn count_domains(bookmarks: &[Bookmark]) -> Vec<DomainCount> {
+    let mut counts: std::collections::HashMap<String, u64> = Default::default();
+    for bookmark in bookmarks {
+        *counts.entry(group::domain(bookmark)).or_default() += 1;
+    }
+    let mut counts = counts
+        .into_iter()
+        .map(|(domain, count)| DomainCount { domain, count })
+        .collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.domain.cmp(&b.domain)));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn tag_changes() {
+        let cases = [
+            (&["a", "b"][..], &["+c", "d"][..], &["a", "b", "c", "d"][..]),
+            (&["a", "b"], &["-a"], &["b"]),
+            // Removing a tag the bookmark doesn't have changes nothing.
+            (&["a", "b"], &["-c"], &["a", "b"]),
+            (&[], &["-a"], &[]),
+            // Adding one it has neither duplicates nor reorders it.
+            (&["a", "b"], &["+a", "a"], &["a", "b"]),
+            // Changes apply in order.
+            (&["a"], &["-a", "+a"], &["a"]),
+            (&["a"], &["+b", "-b"], &["a"]),
+            // Tags differing only in case are the same tag.
+            (&["Rust", "cli"], &["+rust", "-CLI"], &["Rust"]),
+            (&["Rust", "rust"], &["-RUST"], &[]),
+        ];
+        for (tags, changes, expected) in cases {
+            assert_eq!(
+                apply_tag_changes(&strings(tags), &strings(changes)),
+                strings(expected),
+                "{tags:?} {changes:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! `ding menu`: choose a bookmark in rofi, dmenu or wofi. The bookmark list
+//! is kept for a few minutes so the menu opens without waiting for the
+//! server.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use ding_rs::Bookmark;
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum MenuBackend {
+    #[default]
+    Rofi,
+    Dmenu,
+    Wofi,
+}
+
+impl MenuBackend {
+    fn command(self) -> Command {
+        let (program, args): (_, &[&str]) = match self {
+            MenuBackend::Rofi => ("rofi", &["-dmenu", "-i", "-p", "ding"]),
+            MenuBackend::Dmenu => ("dmenu", &["-i", "-l", "20", "-p", "ding"]),
+            MenuBackend::Wofi => ("wofi", &["--dmenu", "-i", "-p", "ding"]),
+        };
+        let mut command = Command::new(program);
+        command.args(args);
+        command
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum MenuAction {
+    /// Open the URL in the browser
+    #[default]
+    Open,
+    /// Copy the URL to the clipboard
+    Yank,
+    /// Print the URL
+    Print,
+}
+
+/// One menu entry. The URL keeps entries unique, so the selection maps back
+/// to its bookmark.
+pub fn line(bookmark: &Bookmark, prefix_tags: bool) -> String {
+    let title = bookmark
+        .title
+        .as_deref()
+        .or(bookmark.website_title.as_deref())
+        .unwrap_or_default();
+    let line = match title {
+        "" => bookmark.url.to_string(),
+        title => format!("{title} — {}", bookmark.url),
+    };
+    let line = match prefix_tags && !bookmark.tag_names.is_empty() {
+        true => format!("[{}] {line}", bookmark.tag_names.join(" ")),
+        false => line,
+    };
+    line.replace('\n', " ")
+}
+
+/// Show `bookmarks` in the menu of `backend` and return the chosen one, or
+/// `None` if the menu was closed.
+pub fn choose(
+    backend: MenuBackend,
+    bookmarks: &[Bookmark],
+    prefix_tags: bool,
+) -> Result<Option<&Bookmark>> {
+    let lines = bookmarks
+        .iter()
+        .map(|x| line(x, prefix_tags))
+        .collect::<Vec<_>>();
+    let mut child = backend
+        .command()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to run the menu")?;
+    {
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // The menu may exit before reading everything.
+        let _ = stdin.write_all(lines.join("\n").as_bytes());
+    }
+    let output = child.wait_with_output()?;
+    let selected = String::from_utf8_lossy(&output.stdout);
+    let selected = selected.trim_end_matches('\n');
+    Ok(lines
+        .iter()
+        .position(|x| x == selected)
+        .map(|x| &bookmarks[x]))
+}
+
+/// The last fetched bookmark list, with what it was fetched for.
+#[derive(Serialize, Deserialize)]
+struct CachedList {
+    host: String,
+    query: Option<String>,
+    fetched_at: DateTime<Utc>,
+    bookmarks: Vec<Bookmark>,
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|x| x.join("ding").join("menu.json"))
+}
+
+/// The list last stored for `host` and `query`, if it is younger than
+/// `max_age`. Without a `max_age` any stored list is returned.
+pub fn cached(host: &str, query: Option<&str>, max_age: Option<Duration>) -> Option<Vec<Bookmark>> {
+    let data = std::fs::read(cache_path()?).ok()?;
+    let cached: CachedList = serde_json::from_slice(&data).ok()?;
+    let age = (Utc::now() - cached.fetched_at).to_std().ok()?;
+    (cached.host == host && cached.query.as_deref() == query && max_age.is_none_or(|x| age < x))
+        .then_some(cached.bookmarks)
+}
+
+/// Keep `bookmarks` for [`cached`]. Failing to write is not an error; the
+/// next menu just asks the server again.
+pub fn store(host: &str, query: Option<&str>, bookmarks: &[Bookmark]) {
+    let Some(path) = cache_path() else {
+        return;
+    };
+    let cached = CachedList {
+        host: host.to_string(),
+        query: query.map(String::from),
+        fetched_at: Utc::now(),
+        bookmarks: bookmarks.to_vec(),
+    };
+    if let (Some(dir), Ok(data)) = (path.parent(), serde_json::to_vec(&cached)) {
+        if std::fs::create_dir_all(dir).is_ok() {
+            let _ = std::fs::write(path, data);
+        }
+    }
+}
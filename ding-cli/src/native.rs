@@ -0,0 +1,127 @@
+//! `ding native-host`: a native messaging host for Chrome and Firefox, so a
+//! browser extension can save and look up pages through the configured CLI
+//! without holding the API token itself.
+//!
+//! Each message is JSON preceded by its length as a 32-bit integer in native
+//! byte order, in both directions. Requests name an `action` and may carry an
+//! `id` that is sent back with the response:
+//!
+//! ```json
+//! {"id": 1, "action": "add", "url": "https://example.com", "tag_names": ["rust"]}
+//! {"id": 1, "ok": true, "result": {"created": true, "bookmark": {...}}}
+//! {"id": 2, "ok": false, "error": {"kind": "not_found", "message": "..."}}
+//! ```
+//!
+//! Browsers start the host with the extension's origin as argument, so the
+//! manifest's `path` has to be a script running `ding native-host "$@"`.
+//! Hooks are not run here, as their output would end up in the message stream.
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use url::Url;
+
+use ding_rs::{BookmarkRequest, BookmarksRequest, DingClient};
+
+use crate::failure::{self, ErrorKind, Failure};
+
+/// Browsers do not accept larger messages from a host.
+const MAX_RESPONSE: usize = 1024 * 1024;
+const DEFAULT_SEARCH_LIMIT: u64 = 20;
+
+#[derive(Deserialize)]
+struct Message {
+    #[serde(default)]
+    id: Value,
+    #[serde(flatten)]
+    request: Request,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum Request {
+    /// Save a page, merging into the bookmark if the URL is saved already.
+    Add {
+        #[serde(flatten)]
+        bookmark: BookmarkRequest,
+    },
+    /// The bookmark for a URL, if any, and the page's metadata.
+    Check { url: Url },
+    Search {
+        query: Option<String>,
+        limit: Option<u64>,
+    },
+}
+
+/// Answer messages on stdin until the browser closes it.
+pub async fn serve(client: &DingClient) -> Result<()> {
+    let mut stdin = tokio::io::stdin();
+    let mut stdout = tokio::io::stdout();
+    loop {
+        let mut len = [0; 4];
+        match stdin.read_exact(&mut len).await {
+            Ok(_) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err.into()),
+        }
+        let mut data = vec![0; u32::from_ne_bytes(len) as usize];
+        stdin.read_exact(&mut data).await?;
+        let response = respond(client, &data).await;
+        let mut data = serde_json::to_vec(&response)?;
+        if data.len() > MAX_RESPONSE {
+            let err = anyhow!("The response is larger than 1 MiB, ask for fewer bookmarks");
+            data = serde_json::to_vec(&error_response(response["id"].clone(), &err))?;
+        }
+        stdout.write_all(&(data.len() as u32).to_ne_bytes()).await?;
+        stdout.write_all(&data).await?;
+        stdout.flush().await?;
+    }
+}
+
+async fn respond(client: &DingClient, data: &[u8]) -> Value {
+    let message: Message = match serde_json::from_slice(data) {
+        Ok(message) => message,
+        Err(err) => {
+            // Still send the id back if there is one.
+            let id = serde_json::from_slice::<Value>(data)
+                .map(|x| x["id"].clone())
+                .unwrap_or_default();
+            let err = Failure::usage(format!("Invalid message: {err}")).into();
+            return error_response(id, &err);
+        }
+    };
+    match handle(client, message.request).await {
+        Ok(result) => json!({"id": message.id, "ok": true, "result": result}),
+        Err(err) => error_response(message.id, &err),
+    }
+}
+
+async fn handle(client: &DingClient, request: Request) -> Result<Value> {
+    Ok(match request {
+        Request::Add { bookmark } => {
+            if bookmark.url.is_none() {
+                return Err(Failure::usage("`add` needs a `url`").into());
+            }
+            let upserted = client.upsert_bookmark(bookmark).await?;
+            json!({"created": upserted.is_created(), "bookmark": upserted.bookmark()})
+        }
+        Request::Check { url } => serde_json::to_value(client.check_bookmark(&url).await?)?,
+        Request::Search { query, limit } => {
+            let params = BookmarksRequest {
+                query,
+                limit: Some(limit.unwrap_or(DEFAULT_SEARCH_LIMIT)),
+                ..Default::default()
+            };
+            serde_json::to_value(client.bookmarks(params).await?.results)?
+        }
+    })
+}
+
+fn error_response(id: Value, err: &anyhow::Error) -> Value {
+    json!({
+        "id": id,
+        "ok": false,
+        "error": {"kind": ErrorKind::of(err), "message": failure::message(err)},
+    })
+}
@@ -0,0 +1,583 @@
+use std::fmt::Write;
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use clap::ValueEnum;
+use colored_json::to_colored_json_auto;
+use crossterm::style::Stylize;
+use iocraft::ElementExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use ding_rs::{Bookmark, Bundle, Capabilities, Tag};
+
+use crate::cache::SearchHit;
+
+#[derive(ValueEnum, Clone, Default)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+    FlattenJson,
+    Csv,
+    /// Tab separated values with a header row, e.g. for nushell's `from tsv`
+    Tsv,
+    /// Markdown, e.g. for notes; bookmarks are listed as links
+    Markdown,
+}
+
+/// How the human format shows dates, named like the server's
+/// `bookmark_date_display` preference. Structured formats always have the
+/// exact UTC timestamps.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateDisplay {
+    /// E.g. "3 days ago".
+    #[default]
+    Relative,
+    /// Local time, in the configured format.
+    Absolute,
+    Hidden,
+}
+
+/// Bookmark layouts of the human format.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// One line per bookmark: id, flags, title and tags.
+    Oneline,
+    #[default]
+    Normal,
+    /// The normal layout plus the snapshot URL and every date.
+    Long,
+}
+
+/// Settings of the human format, fixed for the run.
+pub struct HumanStyle {
+    pub layout: Layout,
+    pub date_display: DateDisplay,
+    /// strftime format of absolute dates.
+    pub date_format: String,
+}
+
+static HUMAN_STYLE: OnceLock<HumanStyle> = OnceLock::new();
+
+/// Set the human format for the rest of the run. Until called, the normal
+/// layout with relative dates is used.
+pub fn init_human_style(style: HumanStyle) {
+    let _ = HUMAN_STYLE.set(style);
+}
+
+fn layout() -> Layout {
+    HUMAN_STYLE.get().map_or(Layout::Normal, |x| x.layout)
+}
+
+/// `time` as the human format shows it, or `None` if dates are hidden.
+fn display_date(time: DateTime<Utc>) -> Option<String> {
+    let style = HUMAN_STYLE.get();
+    match style.map_or(DateDisplay::Relative, |x| x.date_display) {
+        DateDisplay::Relative => Some(relative_date(time)),
+        DateDisplay::Absolute => {
+            let time = time.with_timezone(&Local);
+            let mut date = String::new();
+            // An invalid configured format fails to write rather than panic.
+            match style.map(|x| write!(date, "{}", time.format(&x.date_format))) {
+                Some(Ok(())) => Some(date),
+                _ => Some(time.format("%Y-%m-%d %H:%M").to_string()),
+            }
+        }
+        DateDisplay::Hidden => None,
+    }
+}
+
+fn relative_date(time: DateTime<Utc>) -> String {
+    let age = Utc::now() - time;
+    let (count, unit) = match age.num_days() {
+        _ if age.num_minutes() < 1 => return "just now".to_string(),
+        _ if age.num_hours() < 1 => (age.num_minutes(), "minute"),
+        0 => (age.num_hours(), "hour"),
+        days @ 1..7 => (days, "day"),
+        days @ 7..30 => (days / 7, "week"),
+        days @ 30..365 => (days / 30, "month"),
+        days => (days / 365, "year"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} {unit}{plural} ago")
+}
+
+pub trait ToOutput: Serialize {
+    fn to_human_format(&self) -> Result<String>;
+    fn to_csv_format(&self) -> Result<String>;
+    fn to_json_format(&self) -> Result<String> {
+        Ok(to_colored_json_auto(&self)?)
+    }
+    fn to_flatten_json_format(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+    fn to_tsv_format(&self) -> Result<String> {
+        to_tsv(serde_json::to_value(self)?)
+    }
+    fn to_markdown_format(&self) -> Result<String> {
+        to_markdown(serde_json::to_value(self)?)
+    }
+    fn to_format(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Human => self.to_human_format(),
+            OutputFormat::Json => self.to_json_format(),
+            OutputFormat::FlattenJson => self.to_flatten_json_format(),
+            OutputFormat::Csv => self.to_csv_format(),
+            OutputFormat::Tsv => self.to_tsv_format(),
+            OutputFormat::Markdown => self.to_markdown_format(),
+        }
+    }
+}
+
+impl ToOutput for Bookmark {
+    fn to_human_format(&self) -> Result<String> {
+        let url = self.url.to_string();
+        let title = match (&self.website_title, &self.title) {
+            (_, Some(title)) => title,
+            (Some(title), None) => title,
+            (None, None) => &url,
+        };
+        let flags = format!(
+            "{}{}",
+            if self.is_archived { ",📦" } else { "" },
+            if self.unread { ",📕" } else { ",📖" }
+        );
+        let layout = layout();
+        if layout == Layout::Oneline {
+            let tags = self
+                .tag_names
+                .iter()
+                .map(|x| format!("#{x}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            return Ok(format!(
+                "{} {} {} {}",
+                self.id.to_string().green().bold(),
+                flags.trim_start_matches(','),
+                title.clone().bold().blue(),
+                tags.dark_grey()
+            ));
+        }
+        let description = match (&self.website_description, &self.description) {
+            (_, Some(description)) => description,
+            (Some(description), None) => description,
+            (None, None) => "-",
+        };
+        let notes = if let Some(notes) = &self.notes {
+            notes
+        } else {
+            "-"
+        };
+        let (width, _) = crossterm::terminal::size()?;
+        let formated_description = iocraft::prelude::element! {
+            iocraft::prelude::Box(
+                border_style: iocraft::prelude::BorderStyle::None,
+                max_width: width,
+                padding_left: iocraft::prelude::Padding::Length(2),
+                padding_right: iocraft::prelude::Padding::Length(2),
+            ) {
+                iocraft::prelude::Text(content: format!("{} {}", "Description:".to_string().magenta(), description))
+            }
+        }.to_string();
+        let formated_notes = iocraft::prelude::element! {
+            iocraft::prelude::Box(
+                border_style: iocraft::prelude::BorderStyle::None,
+                max_width: width,
+                padding_left: iocraft::prelude::Padding::Length(2),
+                padding_right: iocraft::prelude::Padding::Length(2),
+            ) {
+                iocraft::prelude::Text(content: format!("{} {}", "Notes:".to_string().magenta(), notes))
+            }
+        }.to_string();
+        let line = |label: &str, value: Option<String>| {
+            value
+                .map(|x| format!("\n  {} {x}", label.to_string().magenta()))
+                .unwrap_or_default()
+        };
+        let mut details = line("Added:", display_date(self.date_added));
+        if layout == Layout::Long {
+            details += &line("Modified:", display_date(self.date_modified));
+            details += &line(
+                "Snapshot:",
+                Some(
+                    self.web_archive_snapshot_url
+                        .as_ref()
+                        .map_or("-".to_string(), |x| x.to_string()),
+                ),
+            );
+        }
+        Ok(format!(
+            "{} {}\n  {} {}\n  {} {}{}\n{}{}",
+            format!("(ID: {}{flags})", self.id).green().bold(),
+            title.clone().bold().blue(),
+            "Url:".to_string().magenta(),
+            self.url,
+            "Tags:".to_string().magenta(),
+            self.tag_names.join(" "),
+            details,
+            formated_description,
+            formated_notes,
+        ))
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(serde_json::to_value(self)?)
+    }
+    fn to_markdown_format(&self) -> Result<String> {
+        Ok(markdown_item(self))
+    }
+}
+
+impl ToOutput for Vec<Bookmark> {
+    fn to_human_format(&self) -> Result<String> {
+        Ok(self
+            .iter()
+            .map(|x| x.to_human_format())
+            .collect::<Result<Vec<String>>>()?
+            .join("\n"))
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(serde_json::to_value(self)?)
+    }
+    fn to_markdown_format(&self) -> Result<String> {
+        Ok(self
+            .iter()
+            .map(markdown_item)
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+/// `- [title](url) #tag`, a list item linking to the bookmark.
+pub fn markdown_item(bookmark: &Bookmark) -> String {
+    let title = bookmark
+        .title
+        .as_deref()
+        .or(bookmark.website_title.as_deref())
+        .unwrap_or(bookmark.url.as_str())
+        .replace('[', "\\[")
+        .replace(']', "\\]");
+    let mut item = format!("- [{title}](<{}>)", bookmark.url);
+    for tag in &bookmark.tag_names {
+        item += &format!(" #{tag}");
+    }
+    item
+}
+
+impl ToOutput for Vec<Tag> {
+    fn to_human_format(&self) -> Result<String> {
+        Ok(self
+            .iter()
+            .map(|x| x.name.clone())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+    fn to_json_format(&self) -> Result<String> {
+        Ok(to_colored_json_auto(
+            &self.iter().map(|x| x.name.clone()).collect::<Vec<_>>(),
+        )?)
+    }
+    fn to_flatten_json_format(&self) -> Result<String> {
+        Ok(serde_json::to_string(
+            &self.iter().map(|x| x.name.clone()).collect::<Vec<_>>(),
+        )?)
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(serde_json::to_value(self)?)
+    }
+}
+
+impl ToOutput for Vec<Bundle> {
+    fn to_human_format(&self) -> Result<String> {
+        Ok(self
+            .iter()
+            .map(|x| {
+                let filter = [
+                    x.search.clone(),
+                    tag_filter("any", &x.any_tags),
+                    tag_filter("all", &x.all_tags),
+                    tag_filter("not", &x.excluded_tags),
+                ]
+                .into_iter()
+                .filter(|x| !x.is_empty())
+                .collect::<Vec<_>>()
+                .join("  ");
+                format!(
+                    "{} {} {}",
+                    format!("({})", x.id).green().bold(),
+                    x.name.clone().blue().bold(),
+                    filter.dark_grey()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(serde_json::to_value(self)?)
+    }
+}
+
+impl ToOutput for Capabilities {
+    fn to_human_format(&self) -> Result<String> {
+        let flag = |x: bool| match x {
+            true => "yes".green(),
+            false => "no".red(),
+        };
+        Ok([
+            format!(
+                "{} {}",
+                "Version:".magenta(),
+                self.version.as_deref().unwrap_or("unknown")
+            ),
+            format!("{} {}", "Bundles:".magenta(), flag(self.bundles)),
+            format!(
+                "{} {}",
+                "Modified since filter:".magenta(),
+                flag(self.modified_since)
+            ),
+            format!("{} {}", "Assets:".magenta(), flag(self.assets)),
+        ]
+        .join("\n"))
+    }
+    /// One `key,value` row per capability.
+    fn to_csv_format(&self) -> Result<String> {
+        let Value::Object(fields) = serde_json::to_value(self)? else {
+            unreachable!("a struct serializes as an object")
+        };
+        let rows = fields
+            .into_iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect();
+        to_csv(Value::Array(rows))
+    }
+}
+
+fn tag_filter(label: &str, tags: &str) -> String {
+    match tags.trim() {
+        "" => String::new(),
+        tags => format!("{label}: {tags}"),
+    }
+}
+
+#[derive(Serialize)]
+pub struct DomainCount {
+    pub domain: String,
+    pub count: u64,
+}
+
+impl ToOutput for Vec<DomainCount> {
+    fn to_human_format(&self) -> Result<String> {
+        let width = self
+            .iter()
+            .map(|x| x.count.to_string().len())
+            .max()
+            .unwrap_or_default();
+        Ok(self
+            .iter()
+            .map(|x| {
+                format!(
+                    "{} {}",
+                    format!("{:>width$}", x.count).green().bold(),
+                    x.domain
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(serde_json::to_value(self)?)
+    }
+}
+
+impl ToOutput for Vec<SearchHit> {
+    fn to_human_format(&self) -> Result<String> {
+        Ok(self
+            .iter()
+            .map(|x| {
+                let bookmark = x.bookmark.to_human_format()?;
+                Ok(match &x.snippet {
+                    Some(snippet) => format!("{bookmark}\n  {} {snippet}", "Match:".magenta()),
+                    None => bookmark,
+                })
+            })
+            .collect::<Result<Vec<String>>>()?
+            .join("\n"))
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(serde_json::to_value(self)?)
+    }
+}
+
+/// Render records as TSV. Columns follow field order, nested lists are joined
+/// with commas and tabs or newlines inside values are replaced by spaces.
+pub fn to_tsv(value: Value) -> Result<String> {
+    let (columns, rows) = table(value);
+    let mut lines = vec![columns.join("\t")];
+    for row in rows {
+        lines.push(
+            row.iter()
+                .map(|x| x.replace(['\t', '\n', '\r'], " "))
+                .collect::<Vec<_>>()
+                .join("\t"),
+        );
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Render records as a markdown table with the columns of [`to_tsv`].
+pub fn to_markdown(value: Value) -> Result<String> {
+    let row = |cells: &[String]| {
+        let cells = cells
+            .iter()
+            .map(|x| x.replace('|', "\\|").replace(['\n', '\r'], " "))
+            .collect::<Vec<_>>();
+        format!("| {} |", cells.join(" | "))
+    };
+    let (columns, rows) = table(value);
+    let mut lines = vec![
+        row(&columns),
+        format!("|{}", " --- |".repeat(columns.len())),
+    ];
+    lines.extend(rows.iter().map(|x| row(x)));
+    Ok(lines.join("\n"))
+}
+
+/// Render records as CSV with the columns of [`to_tsv`]. Values with commas,
+/// quotes or newlines are quoted.
+pub fn to_csv(value: Value) -> Result<String> {
+    let quote = |x: &str| match x.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", x.replace('"', "\"\"")),
+        false => x.to_string(),
+    };
+    let (columns, rows) = table(value);
+    let mut lines = vec![columns
+        .iter()
+        .map(|x| quote(x))
+        .collect::<Vec<_>>()
+        .join(",")];
+    for row in rows {
+        lines.push(row.iter().map(|x| quote(x)).collect::<Vec<_>>().join(","));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// The columns of records, in field order, and the text of each of their
+/// cells. Nested lists are joined with commas.
+fn table(value: Value) -> (Vec<String>, Vec<Vec<String>>) {
+    let rows = match value {
+        Value::Array(items) => items,
+        value => vec![value],
+    };
+    let rows = rows
+        .into_iter()
+        .map(|x| match x {
+            Value::Object(map) => map,
+            value => Map::from_iter([("value".to_string(), value)]),
+        })
+        .collect::<Vec<_>>();
+    let mut columns: Vec<String> = vec![];
+    for row in &rows {
+        for key in row.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+    let cell = |value: Option<&Value>| -> String {
+        match value {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(x)) => x.clone(),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|x| match x {
+                    Value::String(x) => x.clone(),
+                    x => x.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            Some(x) => x.to_string(),
+        }
+    };
+    let cells = rows
+        .iter()
+        .map(|row| columns.iter().map(|x| cell(row.get(x))).collect())
+        .collect();
+    (columns, cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark() -> Bookmark {
+        serde_json::from_value(json!({
+            "id": 1,
+            "url": "https://example.com/",
+            "title": "Example, \"quoted\"",
+            "description": "",
+            "notes": "",
+            "website_title": null,
+            "website_description": null,
+            "web_archive_snapshot_url": "",
+            "favicon_url": null,
+            "preview_image_url": null,
+            "is_archived": false,
+            "unread": true,
+            "shared": false,
+            "tag_names": ["rust", "cli"],
+            "date_added": "2024-01-01T00:00:00Z",
+            "date_modified": "2024-01-02T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn bookmarks_as_csv() {
+        let csv = vec![bookmark()].to_csv_format().unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("id,url,title,"), "{csv}");
+        assert!(
+            lines[1].starts_with("1,https://example.com/,\"Example, \"\"quoted\"\"\","),
+            "{csv}"
+        );
+        assert!(lines[1].contains(",\"rust,cli\","), "{csv}");
+        assert_eq!(bookmark().to_csv_format().unwrap(), csv);
+    }
+
+    #[test]
+    fn tags_as_csv() {
+        let tags: Vec<Tag> = serde_json::from_value(json!([
+            {"id": 1, "name": "rust", "date_added": "2024-01-01T00:00:00Z"},
+            {"id": 2, "name": "cli", "date_added": "2024-01-02T00:00:00Z"},
+        ]))
+        .unwrap();
+        assert_eq!(
+            tags.to_csv_format().unwrap(),
+            "id,name,date_added\n\
+             1,rust,2024-01-01T00:00:00Z\n\
+             2,cli,2024-01-02T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn domain_counts_as_csv() {
+        let counts = vec![
+            DomainCount {
+                domain: "example.com".to_string(),
+                count: 3,
+            },
+            DomainCount {
+                domain: "docs.rs".to_string(),
+                count: 1,
+            },
+        ];
+        assert_eq!(
+            counts.to_csv_format().unwrap(),
+            "domain,count\nexample.com,3\ndocs.rs,1"
+        );
+    }
+}
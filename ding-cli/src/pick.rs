@@ -0,0 +1,98 @@
+//! Selecting bookmarks with an external fuzzy finder such as fzf.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+
+use ding_rs::{Bookmark, BookmarkPatch, BookmarkRequest};
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum PickAction {
+    /// Print the selected bookmarks
+    #[default]
+    Print,
+    /// Open the URLs in the browser
+    Open,
+    /// Copy the URLs to the clipboard
+    Yank,
+    Archive,
+    /// Edit the bookmarks as JSON in $EDITOR
+    Edit,
+}
+
+/// One tab separated line per bookmark: id, title, URL and tags. The id comes
+/// first so the selection can be mapped back without the rest of the line.
+pub fn line(bookmark: &Bookmark) -> String {
+    let title = bookmark
+        .title
+        .as_deref()
+        .or(bookmark.website_title.as_deref())
+        .unwrap_or_default();
+    let tags = bookmark
+        .tag_names
+        .iter()
+        .map(|x| format!("#{x}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    [
+        &bookmark.id.to_string(),
+        title,
+        bookmark.url.as_str(),
+        &tags,
+    ]
+    .map(|x| x.replace(['\t', '\n'], " "))
+    .join("\t")
+}
+
+/// Run `command` with the lines of `bookmarks` on stdin and return the ids of
+/// the lines it prints. Cancelling the picker selects nothing.
+pub fn pick(command: &[String], bookmarks: &[Bookmark]) -> Result<Vec<u64>> {
+    let Some((program, args)) = command.split_first() else {
+        bail!("The picker command is empty");
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run picker `{program}`"))?;
+    {
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        for bookmark in bookmarks {
+            // The picker may exit before reading everything.
+            if writeln!(stdin, "{}", line(bookmark)).is_err() {
+                break;
+            }
+        }
+    }
+    let output = child.wait_with_output()?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|x| x.split('\t').next()?.trim().parse().ok())
+        .collect())
+}
+
+/// Let the user edit `bookmark` as JSON in their editor. Returns only the
+/// changed fields, or `None` when nothing was changed.
+pub fn edit(bookmark: &Bookmark) -> Result<Option<BookmarkPatch>> {
+    let original = serde_json::to_string_pretty(&BookmarkRequest::from(bookmark))?;
+    let path = std::env::temp_dir().join(format!("ding-edit-{}.json", bookmark.id));
+    std::fs::write(&path, &original)?;
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut editor = editor.split_whitespace();
+    let status = Command::new(editor.next().unwrap_or("vi"))
+        .args(editor)
+        .arg(&path)
+        .status();
+    let edited = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    if !status?.success() {
+        bail!("The editor exited with an error");
+    }
+    let patch = bookmark.diff(&serde_json::from_str(&edited?)?);
+    Ok((!patch.is_empty()).then_some(patch))
+}
@@ -0,0 +1,60 @@
+//! QR codes of bookmark URLs, for opening a link on a phone.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use qrcode::render::unicode::Dense1x2;
+use qrcode::{Color, QrCode};
+
+/// Light modules around the code, as the standard asks for.
+const QUIET_ZONE: usize = 4;
+/// Pixels per module in PNG files.
+const MODULE_SIZE: usize = 8;
+
+pub fn encode(text: &str) -> Result<QrCode> {
+    QrCode::new(text).context("The URL is too long for a QR code")
+}
+
+/// The code drawn with half blocks, two modules per character. The blocks
+/// are the light modules, for terminals with light text on a dark
+/// background; `invert` is for the other way round.
+pub fn to_unicode(code: &QrCode, invert: bool) -> String {
+    let (dark, light) = match invert {
+        false => (Dense1x2::Light, Dense1x2::Dark),
+        true => (Dense1x2::Dark, Dense1x2::Light),
+    };
+    code.render::<Dense1x2>()
+        .dark_color(dark)
+        .light_color(light)
+        .quiet_zone(true)
+        .build()
+}
+
+/// Write the code as a black on white grayscale PNG.
+pub fn write_png(code: &QrCode, path: &Path) -> Result<()> {
+    let width = code.width();
+    let colors = code.to_colors();
+    let size = (width + 2 * QUIET_ZONE) * MODULE_SIZE;
+    let mut pixels = vec![u8::MAX; size * size];
+    for (i, color) in colors.iter().enumerate() {
+        if *color == Color::Light {
+            continue;
+        }
+        let x = (i % width + QUIET_ZONE) * MODULE_SIZE;
+        let y = (i / width + QUIET_ZONE) * MODULE_SIZE;
+        for row in y..y + MODULE_SIZE {
+            pixels[row * size + x..row * size + x + MODULE_SIZE].fill(0);
+        }
+    }
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), size as u32, size as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! The reading queue behind `ding next`, `done` and `skip`: unread bookmarks
+//! are worked through oldest first, one at a time. The bookmark being read
+//! and the skipped ones are kept locally, per server.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use ding_rs::Bookmark;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Queue {
+    host: String,
+    /// The bookmark last shown by `next`.
+    pub current: Option<u64>,
+    pub skipped: Vec<u64>,
+}
+
+fn path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|x| x.join("ding").join("queue.json"))
+}
+
+impl Queue {
+    /// The queue for `host`, empty if there is none yet.
+    pub fn load(host: &str) -> Self {
+        let queue = path()
+            .and_then(|x| std::fs::read(x).ok())
+            .and_then(|x| serde_json::from_slice::<Queue>(&x).ok())
+            .filter(|x| x.host == host);
+        queue.unwrap_or_else(|| Self {
+            host: host.to_string(),
+            ..Default::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = path().context("No state directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, serde_json::to_vec(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The oldest of the `unread` bookmarks that was not skipped. Skips of
+    /// bookmarks that are no longer unread are forgotten.
+    pub fn pick<'a>(&mut self, unread: &'a [Bookmark]) -> Option<&'a Bookmark> {
+        self.skipped.retain(|id| unread.iter().any(|x| x.id == *id));
+        unread
+            .iter()
+            .filter(|x| !self.skipped.contains(&x.id))
+            .min_by_key(|x| (x.date_added, x.id))
+    }
+}
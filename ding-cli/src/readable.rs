@@ -0,0 +1,310 @@
+//! Keeping the readable text of bookmarked pages: scripts, navigation and
+//! other page furniture are dropped, and the paragraphs, headings, lists and
+//! code blocks of the main content are kept as a small standalone HTML file.
+
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use crossterm::style::Stylize;
+use regex::Regex;
+use serde::Serialize;
+use url::Url;
+
+use ding_rs::{Bookmark, DingClient};
+
+use crate::feed::escape;
+use crate::output::{to_csv, ToOutput};
+
+/// Elements dropped with everything inside them.
+const NOISE: [&str; 12] = [
+    "script", "style", "noscript", "svg", "iframe", "nav", "header", "footer", "aside", "form",
+    "button", "template",
+];
+
+static COMMENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+static NOISE_RE: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    NOISE
+        .iter()
+        .map(|x| Regex::new(&format!(r"(?is)<{x}\b[^>]*>.*?</{x}\s*>")).unwrap())
+        .collect()
+});
+static TITLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<title\b[^>]*>(.*?)</title\s*>").unwrap());
+static BLOCK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?is)<(p|h[1-6]|li|pre|blockquote)\b[^>]*>(.*?)</(?:p|h[1-6]|li|pre|blockquote)\s*>",
+    )
+    .unwrap()
+});
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+static SPACE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+").unwrap());
+
+/// Paragraphs shorter than this are usually captions or buttons.
+const MIN_PARAGRAPH: usize = 25;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Block {
+    Heading(String),
+    Paragraph(String),
+    Item(String),
+    Code(String),
+    Quote(String),
+}
+
+pub struct Article {
+    pub title: Option<String>,
+    blocks: Vec<Block>,
+}
+
+impl Article {
+    pub fn extract(html: &str) -> Self {
+        let title = TITLE
+            .captures(html)
+            .map(|x| text(&x[1]))
+            .filter(|x| !x.is_empty());
+        let mut html = COMMENT.replace_all(html, "").into_owned();
+        for noise in NOISE_RE.iter() {
+            html = noise.replace_all(&html, "").into_owned();
+        }
+        let content = ["article", "main", "body"]
+            .iter()
+            .find_map(|x| inner(&html, x))
+            .unwrap_or(&html);
+        let blocks = BLOCK
+            .captures_iter(content)
+            .filter_map(|x| {
+                let tag = x[1].to_lowercase();
+                if tag == "pre" {
+                    let code = decode(&TAG.replace_all(&x[2], ""));
+                    return (!code.trim().is_empty()).then_some(Block::Code(code));
+                }
+                let text = text(&x[2]);
+                match tag.as_str() {
+                    _ if text.is_empty() => None,
+                    "li" => Some(Block::Item(text)),
+                    "blockquote" => Some(Block::Quote(text)),
+                    "p" if text.chars().count() < MIN_PARAGRAPH => None,
+                    "p" => Some(Block::Paragraph(text)),
+                    _ => Some(Block::Heading(text)),
+                }
+            })
+            .collect();
+        Self { title, blocks }
+    }
+
+    pub fn words(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|x| match x {
+                Block::Heading(x)
+                | Block::Paragraph(x)
+                | Block::Item(x)
+                | Block::Code(x)
+                | Block::Quote(x) => x.split_whitespace().count(),
+            })
+            .sum()
+    }
+
+    /// A standalone HTML page with the article and a link to `bookmark`.
+    pub fn to_html(&self, bookmark: &Bookmark) -> String {
+        let title = self
+            .title
+            .as_deref()
+            .or(bookmark.title.as_deref())
+            .unwrap_or(bookmark.url.as_str());
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n<h1>{}</h1>\n<p><a href=\"{}\">{}</a></p>\n",
+            escape(title),
+            escape(title),
+            escape(bookmark.url.as_str()),
+            escape(bookmark.url.as_str()),
+        );
+        let mut in_list = false;
+        for block in &self.blocks {
+            let is_item = matches!(block, Block::Item(_));
+            if is_item != in_list {
+                out += if is_item { "<ul>\n" } else { "</ul>\n" };
+                in_list = is_item;
+            }
+            out += &match block {
+                Block::Heading(x) => format!("<h2>{}</h2>\n", escape(x)),
+                Block::Paragraph(x) => format!("<p>{}</p>\n", escape(x)),
+                Block::Item(x) => format!("<li>{}</li>\n", escape(x)),
+                Block::Code(x) => format!("<pre>{}</pre>\n", escape(x)),
+                Block::Quote(x) => format!("<blockquote>{}</blockquote>\n", escape(x)),
+            };
+        }
+        if in_list {
+            out += "</ul>\n";
+        }
+        out += "</body>\n</html>\n";
+        out
+    }
+}
+
+/// The content of the first `tag` element.
+fn inner<'a>(html: &'a str, tag: &str) -> Option<&'a str> {
+    let re = Regex::new(&format!(r"(?is)<{tag}\b[^>]*>(.*)</{tag}\s*>")).ok()?;
+    re.captures(html)
+        .map(|x| x.get(1).expect("group 1").as_str())
+}
+
+/// Visible text of an HTML fragment, on one line.
+fn text(html: &str) -> String {
+    let text = decode(&TAG.replace_all(html, " "));
+    SPACE.replace_all(&text, " ").trim().to_string()
+}
+
+fn decode(text: &str) -> String {
+    static ENTITY: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|[a-zA-Z]+);").unwrap());
+    ENTITY
+        .replace_all(text, |x: &regex::Captures| {
+            let name = &x[1];
+            let char = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => match name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => name
+                        .strip_prefix('#')
+                        .and_then(|x| x.parse().ok())
+                        .and_then(char::from_u32),
+                },
+            };
+            char.map_or(x[0].to_string(), String::from)
+        })
+        .into_owned()
+}
+
+/// Where the article of bookmark `id` is kept.
+pub fn path(id: u64) -> Option<PathBuf> {
+    dirs::data_dir().map(|x| x.join("ding").join("articles").join(format!("{id}.html")))
+}
+
+pub struct Downloader {
+    client: reqwest::Client,
+}
+
+impl Downloader {
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent(concat!("ding/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self { client })
+    }
+
+    pub async fn fetch(&self, url: &Url) -> Result<Article> {
+        let resp = self.client.get(url.clone()).send().await?;
+        if !resp.status().is_success() {
+            bail!("{url} answered with HTTP status {}", resp.status());
+        }
+        let is_html = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .is_none_or(|x| x.contains("html"));
+        if !is_html {
+            bail!("{url} is not an HTML page");
+        }
+        Ok(Article::extract(&resp.text().await?))
+    }
+
+    /// Save the readable copy of `bookmark`, unless there is one already and
+    /// `force` is not set, and upload it as an asset if `upload` is set.
+    pub async fn save(
+        &self,
+        client: &DingClient,
+        bookmark: &Bookmark,
+        upload: bool,
+        force: bool,
+    ) -> Result<ArchiveResult> {
+        let path = path(bookmark.id).context("No data directory")?;
+        if !force && path.exists() {
+            return Ok(ArchiveResult::Kept { path });
+        }
+        let article = self.fetch(&bookmark.url).await?;
+        if article.blocks.is_empty() {
+            bail!("No readable content found");
+        }
+        let html = article.to_html(bookmark);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, &html)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        if upload {
+            let file_name = format!("readable-{}.html", bookmark.id);
+            client
+                .upload_asset(bookmark.id, &file_name, "text/html", html.as_bytes())
+                .await?;
+        }
+        Ok(ArchiveResult::Saved {
+            path,
+            words: article.words(),
+            uploaded: upload,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ArchiveReport {
+    pub id: u64,
+    pub url: Url,
+    #[serde(flatten)]
+    pub result: ArchiveResult,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ArchiveResult {
+    Saved {
+        path: PathBuf,
+        words: usize,
+        uploaded: bool,
+    },
+    /// Already saved, and not downloaded again.
+    Kept {
+        path: PathBuf,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+impl ToOutput for Vec<ArchiveReport> {
+    fn to_human_format(&self) -> Result<String> {
+        Ok(self
+            .iter()
+            .map(|x| {
+                let result = match &x.result {
+                    ArchiveResult::Saved {
+                        words, uploaded, ..
+                    } => {
+                        let uploaded = if *uploaded { ", uploaded" } else { "" };
+                        format!("saved ({words} words{uploaded})").green()
+                    }
+                    ArchiveResult::Kept { .. } => "already saved".to_string().dark_grey(),
+                    ArchiveResult::Failed { message } => format!("failed: {message}").red(),
+                };
+                format!(
+                    "{} {} {}",
+                    format!("(ID: {})", x.id).green().bold(),
+                    x.url,
+                    result
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(serde_json::to_value(self)?)
+    }
+}
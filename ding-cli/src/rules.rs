@@ -0,0 +1,256 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use ding_rs::{Bookmark, BookmarkRequest};
+
+use crate::config::{RuleConfig, RulesConfig};
+
+struct Rule {
+    domain: Option<Regex>,
+    url: Option<Regex>,
+    title: Option<Regex>,
+    tags: Vec<String>,
+    unread: Option<bool>,
+    shared: Option<bool>,
+}
+
+impl Rule {
+    fn new(config: &RuleConfig) -> Result<Self> {
+        let compile = |pattern: &Option<String>| {
+            pattern
+                .as_deref()
+                .map(|x| Regex::new(x).with_context(|| format!("Invalid rule pattern `{x}`")))
+                .transpose()
+        };
+        Ok(Self {
+            domain: compile(&config.domain)?,
+            url: compile(&config.url)?,
+            title: compile(&config.title)?,
+            tags: config.tags.clone(),
+            unread: config.unread,
+            shared: config.shared,
+        })
+    }
+
+    /// A rule matches when every pattern it defines matches. A missing title
+    /// never matches a title pattern.
+    fn matches(&self, req: &BookmarkRequest, title: Option<&str>) -> bool {
+        let Some(url) = &req.url else {
+            return false;
+        };
+        let domain = url.host_str().unwrap_or_default();
+        self.domain.as_ref().is_none_or(|x| x.is_match(domain))
+            && self.url.as_ref().is_none_or(|x| x.is_match(url.as_str()))
+            && self
+                .title
+                .as_ref()
+                .is_none_or(|x| title.is_some_and(|title| x.is_match(title)))
+    }
+}
+
+pub struct Rules {
+    unread: Option<bool>,
+    shared: Option<bool>,
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    pub fn new(config: &RulesConfig) -> Result<Self> {
+        Ok(Self {
+            unread: config.unread,
+            shared: config.shared,
+            rules: config.rule.iter().map(Rule::new).collect::<Result<_>>()?,
+        })
+    }
+
+    /// Apply matching rules and the defaults to a new bookmark. Values given
+    /// explicitly in `req` always win.
+    pub fn apply_new(&self, req: BookmarkRequest) -> BookmarkRequest {
+        let title = req.title.clone();
+        let mut req = self.apply(req, title.as_deref());
+        req.unread = req.unread.or(self.unread);
+        req.shared = req.shared.or(self.shared);
+        req
+    }
+
+    /// Changes the rules would make to an existing bookmark, if any.
+    pub fn apply_existing(&self, bookmark: &Bookmark) -> Option<BookmarkRequest> {
+        let title = bookmark.title.as_ref().or(bookmark.website_title.as_ref());
+        let req = BookmarkRequest {
+            url: Some(bookmark.url.clone()),
+            tag_names: Some(bookmark.tag_names.clone()),
+            ..Default::default()
+        };
+        let req = self.apply(req, title.map(|x| x.as_str()));
+        let changes = bookmark.diff(&req);
+        (!changes.is_empty()).then_some(changes)
+    }
+
+    fn apply(&self, mut req: BookmarkRequest, title: Option<&str>) -> BookmarkRequest {
+        let matched = self
+            .rules
+            .iter()
+            .filter(|x| x.matches(&req, title))
+            .collect::<Vec<_>>();
+        for rule in matched {
+            let tag_names = req.tag_names.get_or_insert_with(Vec::new);
+            for tag in &rule.tags {
+                if !tag_names.contains(tag) {
+                    tag_names.push(tag.clone());
+                }
+            }
+            req.unread = req.unread.or(rule.unread);
+            req.shared = req.shared.or(rule.shared);
+        }
+        req
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use url::Url;
+
+    use super::*;
+
+    fn rules(config: &str) -> Rules {
+        Rules::new(&toml::from_str(config).unwrap()).unwrap()
+    }
+
+    const CONFIG: &str = r#"
+        unread = true
+
+        [[rule]]
+        domain = '(^|\.)github\.com$'
+        tags = ["code"]
+
+        [[rule]]
+        title = '(?i)\brust\b'
+        tags = ["rust", "code"]
+        shared = true
+
+        [[rule]]
+        url = '\.pdf$'
+        tags = ["pdf"]
+        unread = false
+    "#;
+
+    fn request(url: &str, title: Option<&str>) -> BookmarkRequest {
+        BookmarkRequest {
+            title: title.map(String::from),
+            ..BookmarkRequest::new(Url::parse(url).unwrap())
+        }
+    }
+
+    fn tags(items: &[&str]) -> Option<Vec<String>> {
+        Some(items.iter().map(|x| x.to_string()).collect())
+    }
+
+    #[test]
+    fn new_bookmarks() {
+        let rules = rules(CONFIG);
+        let cases = [
+            (
+                request("https://example.com/", None),
+                BookmarkRequest {
+                    unread: Some(true),
+                    ..request("https://example.com/", None)
+                },
+            ),
+            (
+                request("https://gist.github.com/x", Some("Learning Rust")),
+                BookmarkRequest {
+                    tag_names: tags(&["code", "rust"]),
+                    unread: Some(true),
+                    shared: Some(true),
+                    ..request("https://gist.github.com/x", Some("Learning Rust"))
+                },
+            ),
+            // The domain pattern is matched against the host only, and a
+            // missing title never matches a title pattern.
+            (
+                request("https://notgithub.com/github.com", None),
+                BookmarkRequest {
+                    unread: Some(true),
+                    ..request("https://notgithub.com/github.com", None)
+                },
+            ),
+            (
+                request("https://example.com/paper.pdf", Some("Trusty")),
+                BookmarkRequest {
+                    tag_names: tags(&["pdf"]),
+                    unread: Some(false),
+                    ..request("https://example.com/paper.pdf", Some("Trusty"))
+                },
+            ),
+        ];
+        for (req, expected) in cases {
+            assert_eq!(rules.apply_new(req.clone()), expected, "{req:?}");
+        }
+    }
+
+    #[test]
+    fn explicit_values_win() {
+        let req = BookmarkRequest {
+            tag_names: tags(&["mine", "code"]),
+            unread: Some(false),
+            shared: Some(false),
+            ..request("https://github.com/rust-lang/rust", Some("rust"))
+        };
+        assert_eq!(
+            rules(CONFIG).apply_new(req.clone()),
+            BookmarkRequest {
+                tag_names: tags(&["mine", "code", "rust"]),
+                ..req
+            }
+        );
+    }
+
+    #[test]
+    fn existing_bookmarks() {
+        let bookmark = |url: &str, title: &str, tag_names: &[&str]| -> Bookmark {
+            serde_json::from_value(json!({
+                "id": 1,
+                "url": url,
+                "website_title": title,
+                "tag_names": tag_names,
+                "date_added": "2024-01-01T00:00:00Z",
+                "date_modified": "2024-01-01T00:00:00Z",
+            }))
+            .unwrap()
+        };
+        let rules = rules(CONFIG);
+        // The defaults are only for new bookmarks, the rules' own flags and
+        // tags apply to existing ones too.
+        assert_eq!(
+            rules.apply_existing(&bookmark(
+                "https://doc.rust-lang.org/book/",
+                "The Rust Book",
+                &["docs"]
+            )),
+            Some(BookmarkRequest {
+                tag_names: tags(&["docs", "rust", "code"]),
+                shared: Some(true),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            rules.apply_existing(&bookmark("https://github.com/", "GitHub", &["code"])),
+            None
+        );
+        assert_eq!(
+            rules.apply_existing(&bookmark("https://example.com/", "Trust", &[])),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let config = toml::from_str("[[rule]]\ntitle = '('\ntags = ['x']").unwrap();
+        let err = Rules::new(&config).err().unwrap();
+        assert!(
+            err.to_string().contains("Invalid rule pattern `(`"),
+            "{err}"
+        );
+    }
+}
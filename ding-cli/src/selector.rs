@@ -0,0 +1,188 @@
+//! Built-in fuzzy selector for `ding pick --builtin`, for systems without
+//! fzf.
+
+use std::collections::BTreeSet;
+use std::io::{stderr, Write};
+
+use anyhow::Result;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Print, PrintStyledContent, Stylize};
+use crossterm::terminal::{
+    self, disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+    LeaveAlternateScreen,
+};
+use crossterm::{execute, queue};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+use ding_rs::Bookmark;
+
+use crate::pick::line;
+
+/// Rows used by the prompt and the counter above the list.
+const HEADER: u16 = 2;
+
+struct Selector {
+    lines: Vec<String>,
+    query: String,
+    matches: Vec<usize>,
+    cursor: usize,
+    scroll: usize,
+    selected: BTreeSet<usize>,
+    matcher: SkimMatcherV2,
+}
+
+impl Selector {
+    fn new(lines: Vec<String>) -> Self {
+        let mut selector = Self {
+            lines,
+            query: String::new(),
+            matches: vec![],
+            cursor: 0,
+            scroll: 0,
+            selected: BTreeSet::new(),
+            matcher: SkimMatcherV2::default().ignore_case(),
+        };
+        selector.update_matches();
+        selector
+    }
+
+    /// Lines matching the query, best match first. The id column is left out
+    /// of matching.
+    fn update_matches(&mut self) {
+        let mut matches = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let text = line.split_once('\t').map_or(line.as_str(), |x| x.1);
+                let score = self.matcher.fuzzy_match(text, &self.query)?;
+                Some((score, i))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(score, i)| (-score, *i));
+        self.matches = matches.into_iter().map(|(_, i)| i).collect();
+        self.cursor = 0;
+        self.scroll = 0;
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let last = self.matches.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    fn toggle(&mut self) {
+        if let Some(&i) = self.matches.get(self.cursor) {
+            if !self.selected.remove(&i) {
+                self.selected.insert(i);
+            }
+        }
+    }
+
+    /// Returns the chosen lines once the user is done, `Some(vec![])` when
+    /// cancelled.
+    fn handle(&mut self, key: KeyEvent) -> Option<Vec<usize>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Some(vec![]),
+            KeyCode::Char('c' | 'g') if ctrl => return Some(vec![]),
+            KeyCode::Enter => {
+                if self.selected.is_empty() {
+                    return Some(self.matches.get(self.cursor).copied().into_iter().collect());
+                }
+                return Some(self.selected.iter().copied().collect());
+            }
+            KeyCode::Up => self.move_cursor(-1),
+            KeyCode::Char('p' | 'k') if ctrl => self.move_cursor(-1),
+            KeyCode::Down => self.move_cursor(1),
+            KeyCode::Char('n' | 'j') if ctrl => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-10),
+            KeyCode::PageDown => self.move_cursor(10),
+            KeyCode::Tab => {
+                self.toggle();
+                self.move_cursor(1);
+            }
+            KeyCode::BackTab => {
+                self.toggle();
+                self.move_cursor(-1);
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.update_matches();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update_matches();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.update_matches();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn render(&mut self, out: &mut impl Write) -> Result<()> {
+        let (width, height) = terminal::size()?;
+        let rows = height.saturating_sub(HEADER) as usize;
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + rows {
+            self.scroll = self.cursor + 1 - rows;
+        }
+        queue!(out, Clear(ClearType::All), MoveTo(0, 1))?;
+        let counter = format!(
+            "  {}/{} ({} selected)",
+            self.matches.len(),
+            self.lines.len(),
+            self.selected.len()
+        );
+        queue!(out, PrintStyledContent(counter.dark_grey()))?;
+        for (row, &i) in self.matches.iter().skip(self.scroll).take(rows).enumerate() {
+            let marker = if self.selected.contains(&i) { '*' } else { ' ' };
+            let text = self.lines[i].split_once('\t').map_or("", |x| x.1);
+            let text = format!("{marker} {}", text.replace('\t', "  "));
+            let text = text.chars().take(width as usize).collect::<String>();
+            queue!(out, MoveTo(0, HEADER + row as u16))?;
+            if self.scroll + row == self.cursor {
+                queue!(out, PrintStyledContent(text.reverse()))?;
+            } else {
+                queue!(out, Print(text))?;
+            }
+        }
+        let prompt = format!("ding> {}", self.query);
+        queue!(out, MoveTo(0, 0), Print(&prompt), Show)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Let the user select bookmarks interactively and return their ids. Tab
+/// marks several; Enter without marks picks the one under the cursor.
+pub fn select(bookmarks: &[Bookmark]) -> Result<Vec<u64>> {
+    let mut selector = Selector::new(bookmarks.iter().map(line).collect());
+    // The list goes to stderr so stdout stays free for the selection.
+    let mut out = stderr();
+    enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, Hide)?;
+    let result = run(&mut selector, &mut out);
+    execute!(out, LeaveAlternateScreen, Show)?;
+    disable_raw_mode()?;
+    Ok(result?.into_iter().map(|i| bookmarks[i].id).collect())
+}
+
+fn run(selector: &mut Selector, out: &mut impl Write) -> Result<Vec<usize>> {
+    loop {
+        selector.render(out)?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Release {
+                continue;
+            }
+            if let Some(result) = selector.handle(key) {
+                return Ok(result);
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! `ding serve`: a small read-only HTTP server over the sync cache, for
+//! launchers, scripts and dashboards on the same machine. It needs no server
+//! credentials and has no authentication of its own, so it should only
+//! listen on loopback addresses.
+//!
+//! - `GET /api/bookmarks/?q=&archived=&limit=&offset=`: `{"count", "results"}`
+//!   like the linkding API, with `q` in linkding's search syntax
+//! - `GET /api/bookmarks/<id>/`: one bookmark
+//! - `GET /api/tags/`: all tags
+//! - `GET /`: the bookmarks as an HTML list, taking the same parameters
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use crossterm::style::Stylize;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+use ding_rs::Bookmark;
+
+use crate::cache::Cache;
+use crate::feed::escape;
+
+const MAX_HEAD: usize = 16 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_LIMIT: usize = 100;
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, json!({"detail": message}))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        let mut out = format!(
+            "HTTP/1.1 {} {reason}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        )
+        .into_bytes();
+        out.extend_from_slice(&self.body);
+        out
+    }
+}
+
+/// Serve requests on `listen` until killed.
+pub async fn serve(listen: SocketAddr) -> Result<()> {
+    if Cache::open_default()?.last_sync()?.is_none() {
+        bail!("Offline cache is empty, run `sync` first");
+    }
+    if !listen.ip().is_loopback() {
+        eprintln!(
+            "{} anyone who can reach {listen} can read your bookmarks",
+            "Warning:".yellow()
+        );
+    }
+    let listener = TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("Failed to listen on {listen}"))?;
+    eprintln!(
+        "Serving the sync cache on http://{}/",
+        listener.local_addr()?
+    );
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(err) = handle(stream).await {
+                eprintln!("{} {err:#}", "Request failed:".yellow());
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream) -> Result<()> {
+    let head = tokio::time::timeout(READ_TIMEOUT, read_head(&mut stream))
+        .await
+        .context("Timed out reading the request")??;
+    let mut parts = head.lines().next().unwrap_or_default().split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => match Url::parse("http://localhost/")?.join(target) {
+            Ok(url) => {
+                respond(&url).unwrap_or_else(|err| Response::error(500, &format!("{err:#}")))
+            }
+            Err(_) => Response::error(400, "Invalid request target"),
+        },
+        (Some(_), Some(_)) => Response::error(405, "Only GET requests are supported"),
+        _ => Response::error(400, "Invalid request line"),
+    };
+    stream.write_all(&response.to_bytes()).await?;
+    Ok(())
+}
+
+/// The request line and headers, which are all a GET request has.
+async fn read_head(stream: &mut TcpStream) -> Result<String> {
+    let mut head = vec![];
+    let mut buf = [0; 1024];
+    while !head.windows(4).any(|x| x == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+        if head.len() > MAX_HEAD {
+            bail!("Request headers too large");
+        }
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+fn respond(url: &Url) -> Result<Response> {
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    let cache = Cache::open_default()?;
+    let segments = url
+        .path_segments()
+        .map(|x| x.filter(|x| !x.is_empty()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    Ok(match segments[..] {
+        [] | ["api", "bookmarks"] => {
+            let query = param("q").filter(|x| !x.trim().is_empty());
+            let archived = param("archived").is_some_and(|x| x == "true" || x == "1");
+            let (Ok(limit), Ok(offset)) = (
+                param("limit").map_or(Ok(DEFAULT_LIMIT), |x| x.parse()),
+                param("offset").map_or(Ok(0), |x| x.parse()),
+            ) else {
+                return Ok(Response::error(400, "`limit` and `offset` must be numbers"));
+            };
+            let bookmarks = cache.bookmarks(archived, query.as_deref())?;
+            let count = bookmarks.len();
+            let page = bookmarks
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .collect::<Vec<_>>();
+            if segments.is_empty() {
+                Response {
+                    status: 200,
+                    content_type: "text/html",
+                    body: html_page(query.as_deref(), count, &page).into_bytes(),
+                }
+            } else {
+                Response::json(200, json!({"count": count, "results": page}))
+            }
+        }
+        ["api", "bookmarks", id] => match id.parse().ok().map(|x| cache.bookmark(x)).transpose()? {
+            Some(Some(bookmark)) => Response::json(200, serde_json::to_value(bookmark)?),
+            _ => Response::error(404, "No such bookmark in the cache"),
+        },
+        ["api", "tags"] => Response::json(200, serde_json::to_value(cache.tags()?)?),
+        _ => Response::error(404, "Not found"),
+    })
+}
+
+fn html_page(query: Option<&str>, count: usize, bookmarks: &[Bookmark]) -> String {
+    let query = escape(query.unwrap_or_default());
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>ding</title>\n</head>\n<body>\n<form><input name=\"q\" value=\"{query}\" placeholder=\"Search\" autofocus></form>\n<p>{count} bookmarks</p>\n<ul>\n"
+    );
+    for bookmark in bookmarks {
+        let title = bookmark
+            .title
+            .as_deref()
+            .or(bookmark.website_title.as_deref())
+            .unwrap_or(bookmark.url.as_str());
+        let tags = bookmark
+            .tag_names
+            .iter()
+            .map(|x| format!("#{}", escape(x)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        out += &format!(
+            "<li><a href=\"{}\">{}</a> <small>{tags}</small></li>\n",
+            escape(bookmark.url.as_str()),
+            escape(title)
+        );
+    }
+    out += "</ul>\n</body>\n</html>\n";
+    out
+}
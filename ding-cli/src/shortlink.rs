@@ -0,0 +1,93 @@
+//! Expanding short links such as t.co or bit.ly to the page they redirect to,
+//! so that bookmarks point at the destination. The short link is kept in the
+//! notes.
+
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use crossterm::style::Stylize;
+use url::Url;
+
+use ding_rs::BookmarkRequest;
+
+/// Hosts expanded unless `shorteners` is set in the config.
+pub const SHORTENERS: [&str; 18] = [
+    "t.co",
+    "bit.ly",
+    "bitly.com",
+    "tinyurl.com",
+    "goo.gl",
+    "ow.ly",
+    "buff.ly",
+    "is.gd",
+    "v.gd",
+    "lnkd.in",
+    "dlvr.it",
+    "amzn.to",
+    "t.ly",
+    "tiny.cc",
+    "rebrand.ly",
+    "shorturl.at",
+    "trib.al",
+    "fb.me",
+];
+
+pub struct Expander {
+    client: reqwest::Client,
+    hosts: Vec<String>,
+}
+
+impl Expander {
+    /// Expand links on `hosts`, or on [`SHORTENERS`] if `None`.
+    pub fn new(hosts: Option<&[String]>, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent(concat!("ding/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        let hosts = match hosts {
+            Some(hosts) => hosts.iter().map(|x| x.to_lowercase()).collect(),
+            None => SHORTENERS.iter().map(|x| x.to_string()).collect(),
+        };
+        Ok(Self { client, hosts })
+    }
+
+    pub fn is_short(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+        let host = host.strip_prefix("www.").unwrap_or(&host);
+        self.hosts.iter().any(|x| x == host)
+    }
+
+    /// Where `url` redirects to, or `None` if it is not a short link or does
+    /// not redirect.
+    pub async fn expand(&self, url: &Url) -> Result<Option<Url>> {
+        if !self.is_short(url) {
+            return Ok(None);
+        }
+        let resp = self.client.get(url.clone()).send().await?;
+        if !resp.status().is_success() {
+            bail!("answered with HTTP status {}", resp.status());
+        }
+        Ok(Some(resp.url().clone()).filter(|x| x != url))
+    }
+
+    /// Point `req` at the destination of its short link, noting the short
+    /// link. A link that cannot be followed is kept and warned about.
+    pub async fn apply(&self, mut req: BookmarkRequest) -> BookmarkRequest {
+        let Some(url) = req.url.clone() else {
+            return req;
+        };
+        match self.expand(&url).await {
+            Ok(Some(expanded)) => {
+                let line = format!("Short link: {url}");
+                req.notes = Some(match req.notes.as_deref().map(str::trim_end) {
+                    None | Some("") => line,
+                    Some(notes) => format!("{notes}\n\n{line}"),
+                });
+                req.url = Some(expanded);
+            }
+            Ok(None) => {}
+            Err(err) => eprintln!("{} {url}: {err}", "Failed to expand".yellow()),
+        }
+        req
+    }
+}
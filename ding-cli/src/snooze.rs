@@ -0,0 +1,87 @@
+//! `ding snooze`: hide a bookmark from `ding next` until a date, after which
+//! `ding wake` or `ding watch --wake` tags it for the inbox. The server has no
+//! such concept, so the dates are kept in the local cache.
+
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use crossterm::style::Stylize;
+use serde::Serialize;
+
+use ding_rs::{Bookmark, BookmarkRequest, DingClient};
+
+use crate::cache::Cache;
+use crate::output::{to_csv, ToOutput};
+
+/// Wake the snoozed bookmarks that are due, or those of `ids` whatever their
+/// date, by adding `tag` to them. Bookmarks deleted in the meantime are
+/// forgotten. With `dry_run` the bookmarks stay snoozed.
+pub async fn wake(
+    client: &DingClient,
+    ids: &[u64],
+    tag: &str,
+    dry_run: bool,
+) -> Result<Vec<Bookmark>> {
+    let cache = Cache::open_default()?;
+    let now = Utc::now();
+    let due = cache
+        .snoozed()?
+        .into_iter()
+        .filter(|(id, until)| match ids.is_empty() {
+            true => *until <= now,
+            false => ids.contains(id),
+        })
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    let mut woken = vec![];
+    for id in due {
+        let bookmark = match client.bookmark(id).await {
+            Ok(bookmark) => bookmark,
+            Err(err) if err.status() == Some(404) => {
+                if !dry_run {
+                    cache.unsnooze(id)?;
+                }
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let bookmark = if bookmark.tag_names.iter().any(|x| x == tag) {
+            bookmark
+        } else {
+            let req = BookmarkRequest {
+                tag_names: Some(bookmark.tags_with(&[tag])),
+                ..Default::default()
+            };
+            client.update_bookmark(id, req).await?
+        };
+        if !dry_run {
+            cache.unsnooze(id)?;
+        }
+        woken.push(bookmark);
+    }
+    Ok(woken)
+}
+
+#[derive(Serialize)]
+pub struct Snoozed {
+    pub id: u64,
+    pub until: DateTime<Utc>,
+}
+
+impl ToOutput for Vec<Snoozed> {
+    fn to_human_format(&self) -> Result<String> {
+        Ok(self
+            .iter()
+            .map(|x| {
+                format!(
+                    "{} until {}",
+                    format!("(ID: {})", x.id).green().bold(),
+                    x.until.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(serde_json::to_value(self)?)
+    }
+}
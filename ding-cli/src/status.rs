@@ -0,0 +1,109 @@
+//! Bookmark counters for shell prompts. Each counter is one `limit=1`
+//! request, and results can be reused for a short while so that drawing the
+//! prompt stays fast.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+
+use ding_rs::{BookmarksRequest, DingClient};
+
+use crate::output::{to_csv, ToOutput};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    pub unread: u64,
+    /// Bookmarks carrying the inbox tag.
+    pub inbox: u64,
+    pub total: u64,
+}
+
+impl Status {
+    pub async fn fetch(client: &DingClient, inbox_tag: &str) -> Result<Self> {
+        let count = |query: Option<String>| async move {
+            let params = BookmarksRequest {
+                query,
+                limit: Some(1),
+                ..Default::default()
+            };
+            client.bookmarks(params).await.map(|x| x.count)
+        };
+        let (unread, inbox, total) = futures::try_join!(
+            count(Some("!unread".to_string())),
+            count(Some(format!("#{inbox_tag}"))),
+            count(None),
+        )?;
+        Ok(Self {
+            unread,
+            inbox,
+            total,
+        })
+    }
+
+    /// `unread=3 inbox=1 total=42`, stable for scripts.
+    pub fn porcelain(&self) -> String {
+        format!(
+            "unread={} inbox={} total={}",
+            self.unread, self.inbox, self.total
+        )
+    }
+}
+
+impl ToOutput for Status {
+    fn to_human_format(&self) -> Result<String> {
+        Ok([
+            format!("{} {}", "Unread:".magenta(), self.unread),
+            format!("{} {}", "Inbox:".magenta(), self.inbox),
+            format!("{} {}", "Total:".magenta(), self.total),
+        ]
+        .join("\n"))
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(serde_json::to_value(self)?)
+    }
+}
+
+/// The last fetched status, with what it was fetched for.
+#[derive(Serialize, Deserialize)]
+struct CachedStatus {
+    host: String,
+    inbox_tag: String,
+    fetched_at: DateTime<Utc>,
+    status: Status,
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|x| x.join("ding").join("status.json"))
+}
+
+/// The status last stored for `host` and `inbox_tag`, if it is younger than
+/// `max_age`.
+pub fn cached(host: &str, inbox_tag: &str, max_age: Duration) -> Option<Status> {
+    let data = std::fs::read(cache_path()?).ok()?;
+    let cached: CachedStatus = serde_json::from_slice(&data).ok()?;
+    let age = (Utc::now() - cached.fetched_at).to_std().ok()?;
+    (cached.host == host && cached.inbox_tag == inbox_tag && age < max_age).then_some(cached.status)
+}
+
+/// Keep `status` for [`cached`]. Failing to write is not an error; the next
+/// prompt just asks the server again.
+pub fn store(host: &str, inbox_tag: &str, status: &Status) {
+    let Some(path) = cache_path() else {
+        return;
+    };
+    let cached = CachedStatus {
+        host: host.to_string(),
+        inbox_tag: inbox_tag.to_string(),
+        fetched_at: Utc::now(),
+        status: status.clone(),
+    };
+    if let (Some(dir), Ok(data)) = (path.parent(), serde_json::to_vec(&cached)) {
+        if std::fs::create_dir_all(dir).is_ok() {
+            let _ = std::fs::write(path, data);
+        }
+    }
+}
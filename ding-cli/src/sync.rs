@@ -0,0 +1,134 @@
+//! Two-way sync between the server and the local cache.
+
+use anyhow::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use crossterm::style::Stylize;
+
+use ding_rs::{errors::DingError, Bookmark, BookmarksRequest, DingClient};
+
+use crate::cache::{Cache, Pending, PendingOp};
+
+/// Allowance for clock skew between this machine and the server when asking
+/// for changes since the last sync.
+const CLOCK_SKEW: TimeDelta = TimeDelta::minutes(5);
+
+#[derive(Default)]
+pub struct SyncReport {
+    pub incremental: bool,
+    pub replayed: usize,
+    pub conflicts: usize,
+    pub bookmarks: usize,
+    pub tags: usize,
+}
+
+/// Replay queued offline writes, then refresh the cache from the server.
+///
+/// An op touching a bookmark that changed on the server since it was queued
+/// is a conflict: it stays in the queue unless `force` is set.
+///
+/// Unless `full` is set, only bookmarks modified since the last sync are
+/// downloaded. A full download happens anyway on the first sync, when the
+/// server doesn't support `modified_since`, or when bookmarks were deleted on
+/// the server, which an incremental sync can't see.
+pub async fn sync(
+    client: &DingClient,
+    cache: &mut Cache,
+    force: bool,
+    full: bool,
+) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+    for pending in cache.pending()? {
+        match replay(client, &pending, force).await {
+            Ok(true) => report.replayed += 1,
+            Ok(false) => {
+                report.conflicts += 1;
+                eprintln!(
+                    "{} {:?} changed on the server since it was queued",
+                    "Conflict:".red(),
+                    pending.op
+                );
+                continue;
+            }
+            Err(err) if err.downcast_ref::<DingError>().is_some_and(is_not_found) => {
+                eprintln!(
+                    "{} {:?} refers to a bookmark that no longer exists",
+                    "Dropped:".yellow(),
+                    pending.op
+                );
+            }
+            Err(err) => return Err(err),
+        }
+        cache.remove_pending(pending.seq)?;
+    }
+
+    let started = Utc::now();
+    let tags = client.all_tags(Default::default()).await?;
+    let since = match cache.last_sync()? {
+        Some(last_sync) if !full && client.capabilities().await?.modified_since => {
+            Some(last_sync - CLOCK_SKEW)
+        }
+        _ => None,
+    };
+    if let Some(since) = since {
+        let bookmarks = all_bookmarks(client, Some(since)).await?;
+        cache.update(&bookmarks, &tags, started)?;
+        if cache.bookmark_count()? == server_count(client).await? {
+            report.incremental = true;
+            report.bookmarks = bookmarks.len();
+            report.tags = tags.len();
+            return Ok(report);
+        }
+    }
+    let bookmarks = all_bookmarks(client, None).await?;
+    cache.replace_all(&bookmarks, &tags, started)?;
+    report.bookmarks = bookmarks.len();
+    report.tags = tags.len();
+    Ok(report)
+}
+
+async fn all_bookmarks(
+    client: &DingClient,
+    modified_since: Option<DateTime<Utc>>,
+) -> Result<Vec<Bookmark>> {
+    let params = BookmarksRequest {
+        modified_since,
+        ..Default::default()
+    };
+    let mut bookmarks = client.all_bookmarks(params.clone()).await?;
+    bookmarks.extend(client.all_archived(params).await?);
+    Ok(bookmarks)
+}
+
+async fn server_count(client: &DingClient) -> Result<u64> {
+    let params = BookmarksRequest {
+        limit: Some(1),
+        ..Default::default()
+    };
+    Ok(client.bookmarks(params.clone()).await?.count + client.archived(params).await?.count)
+}
+
+async fn replay(client: &DingClient, pending: &Pending, force: bool) -> Result<bool> {
+    if let (Some(id), Some(base_modified), false) =
+        (pending.op.bookmark_id(), pending.base_modified, force)
+    {
+        if client.bookmark(id).await?.date_modified > base_modified {
+            return Ok(false);
+        }
+    }
+    match &pending.op {
+        PendingOp::Create { request } => {
+            client.create_bookmark(request.clone()).await?;
+        }
+        PendingOp::Update { id, request } => {
+            client.update_bookmark(*id, request.clone()).await?;
+        }
+        PendingOp::Archive { id } => client.archive_bookmark(*id).await?,
+        PendingOp::Unarchive { id } => client.unarchive_bookmark(*id).await?,
+        PendingOp::Delete { id } => client.delete_bookmark(*id).await?,
+    }
+    Ok(true)
+}
+
+fn is_not_found(err: &DingError) -> bool {
+    err.status() == Some(404)
+}
@@ -0,0 +1,234 @@
+//! Inline multi-select of tags for `ding add --interactive-tags`. Typing
+//! filters the existing tags fuzzily and can name a new one; Space or Tab
+//! toggles the tag under the cursor and Enter submits.
+
+use std::io::{stderr, Write};
+
+use anyhow::Result;
+use crossterm::cursor::{MoveToColumn, MoveToNextLine, MoveUp, RestorePosition, SavePosition};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Print, PrintStyledContent, Stylize};
+use crossterm::terminal::{self, disable_raw_mode, enable_raw_mode, Clear, ClearType};
+use crossterm::{execute, queue};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+/// Tags listed below the prompt at a time.
+const ROWS: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Entry {
+    Tag(usize),
+    /// The query as a new tag.
+    Create,
+}
+
+struct TagPicker {
+    tags: Vec<String>,
+    /// In the order they were picked.
+    selected: Vec<String>,
+    query: String,
+    entries: Vec<Entry>,
+    cursor: usize,
+    scroll: usize,
+    matcher: SkimMatcherV2,
+}
+
+impl TagPicker {
+    fn new(tags: Vec<String>, selected: Vec<String>) -> Self {
+        let mut picker = Self {
+            tags,
+            selected,
+            query: String::new(),
+            entries: vec![],
+            cursor: 0,
+            scroll: 0,
+            matcher: SkimMatcherV2::default().ignore_case(),
+        };
+        picker.update_entries();
+        picker
+    }
+
+    /// Tags matching the query, best match first, then the query itself
+    /// when it is not a tag yet.
+    fn update_entries(&mut self) {
+        let mut matches = self
+            .tags
+            .iter()
+            .enumerate()
+            .filter_map(|(i, tag)| Some((self.matcher.fuzzy_match(tag, &self.query)?, i)))
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(score, i)| (-score, *i));
+        let exists = self
+            .tags
+            .iter()
+            .any(|x| x.to_lowercase() == self.query.to_lowercase());
+        self.entries = matches
+            .into_iter()
+            .map(|(_, i)| Entry::Tag(i))
+            .chain((!self.query.is_empty() && !exists).then_some(Entry::Create))
+            .collect();
+        self.cursor = 0;
+        self.scroll = 0;
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    fn name(&self, entry: Entry) -> &str {
+        match entry {
+            Entry::Tag(i) => &self.tags[i],
+            Entry::Create => &self.query,
+        }
+    }
+
+    /// Select or unselect the tag under the cursor, then start a new query.
+    fn toggle(&mut self) {
+        let Some(&entry) = self.entries.get(self.cursor) else {
+            return;
+        };
+        let name = self.name(entry).to_string();
+        if let Some(i) = self.selected.iter().position(|x| *x == name) {
+            self.selected.remove(i);
+        } else {
+            if entry == Entry::Create {
+                self.tags.push(name.clone());
+            }
+            self.selected.push(name);
+        }
+        self.query.clear();
+        self.update_entries();
+    }
+
+    /// Returns the picked tags once the user is done, `Some(None)` when
+    /// cancelled.
+    fn handle(&mut self, key: KeyEvent) -> Option<Option<Vec<String>>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Some(None),
+            KeyCode::Char('c' | 'g') if ctrl => return Some(None),
+            KeyCode::Enter => {
+                // Typing a tag and pressing Enter picks it.
+                if let Some(&entry) = self.entries.get(self.cursor) {
+                    if !self.query.is_empty()
+                        && !self.selected.iter().any(|x| x == self.name(entry))
+                    {
+                        self.toggle();
+                    }
+                }
+                return Some(Some(self.selected.clone()));
+            }
+            KeyCode::Up => self.move_cursor(-1),
+            KeyCode::Char('p' | 'k') if ctrl => self.move_cursor(-1),
+            KeyCode::Down => self.move_cursor(1),
+            KeyCode::Char('n' | 'j') if ctrl => self.move_cursor(1),
+            KeyCode::Tab | KeyCode::Char(' ') => self.toggle(),
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.update_entries();
+            }
+            KeyCode::Backspace if self.query.is_empty() => {
+                self.selected.pop();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update_entries();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.update_entries();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn render(&mut self, out: &mut impl Write) -> Result<()> {
+        let width = terminal::size()?.0 as usize;
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + ROWS {
+            self.scroll = self.cursor + 1 - ROWS;
+        }
+        queue!(out, RestorePosition, Clear(ClearType::FromCursorDown))?;
+        let selected = self
+            .selected
+            .iter()
+            .map(|x| format!("#{x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let status = format!("  {}/{} {selected}", self.entries.len(), self.tags.len());
+        queue!(
+            out,
+            MoveToNextLine(1),
+            PrintStyledContent(fit(&status, width).dark_grey())
+        )?;
+        for (row, &entry) in self.entries.iter().skip(self.scroll).take(ROWS).enumerate() {
+            let name = self.name(entry);
+            let text = match entry {
+                Entry::Create => format!("  + new tag {name}"),
+                Entry::Tag(_) if self.selected.iter().any(|x| x == name) => format!("* {name}"),
+                Entry::Tag(_) => format!("  {name}"),
+            };
+            let text = fit(&text, width);
+            queue!(out, MoveToNextLine(1))?;
+            if self.scroll + row == self.cursor {
+                queue!(out, PrintStyledContent(text.reverse()))?;
+            } else {
+                queue!(out, Print(text))?;
+            }
+        }
+        let prompt = format!("tags> {}", self.query);
+        queue!(
+            out,
+            RestorePosition,
+            Print(&prompt),
+            MoveToColumn(prompt.chars().count() as u16)
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+fn fit(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Let the user pick from `tags`, or name new ones, starting with
+/// `selected`. Returns `None` when cancelled.
+pub fn pick_tags(tags: Vec<String>, selected: Vec<String>) -> Result<Option<Vec<String>>> {
+    let mut picker = TagPicker::new(tags, selected);
+    // Drawn on stderr below the cursor, so stdout stays free for the result.
+    let mut out = stderr();
+    enable_raw_mode()?;
+    // Make room for the list first, so it does not scroll while drawn.
+    let height = ROWS as u16 + 1;
+    let result = execute!(
+        out,
+        Print("\n".repeat(height as usize)),
+        MoveUp(height),
+        MoveToColumn(0),
+        SavePosition
+    )
+    .map_err(anyhow::Error::from)
+    .and_then(|()| run(&mut picker, &mut out));
+    let _ = execute!(out, RestorePosition, Clear(ClearType::FromCursorDown));
+    disable_raw_mode()?;
+    result
+}
+
+fn run(picker: &mut TagPicker, out: &mut impl Write) -> Result<Option<Vec<String>>> {
+    loop {
+        picker.render(out)?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Release {
+                continue;
+            }
+            if let Some(result) = picker.handle(key) {
+                return Ok(result);
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! `ding watch`: poll for new bookmarks and announce each one with a desktop
+//! notification and/or a hook command.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+
+use ding_rs::{Bookmark, BookmarkSort, BookmarksRequest, DingClient};
+
+use crate::{hooks, snooze};
+
+pub struct Watcher {
+    pub client: DingClient,
+    pub host: String,
+    /// Search query new bookmarks have to match, e.g. `!unread`.
+    pub query: Option<String>,
+    pub notify: bool,
+    /// Shell command run for every new bookmark, with it as JSON on stdin.
+    pub exec: Option<String>,
+    /// Tag added to snoozed bookmarks when they are due, if they should be
+    /// woken at every poll.
+    pub wake: Option<String>,
+}
+
+/// When the last poll for a server saw bookmarks up to, kept so that a
+/// restarted watcher picks up where it stopped.
+#[derive(Serialize, Deserialize)]
+struct State {
+    host: String,
+    last_added: DateTime<Utc>,
+}
+
+fn state_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|x| x.join("ding").join("watch.json"))
+}
+
+impl Watcher {
+    /// Poll every `interval` until killed. Failed polls are reported and
+    /// retried at the next interval.
+    pub async fn run(&self, interval: Duration) -> Result<()> {
+        let mut last_added = self.load_state().unwrap_or_else(Utc::now);
+        loop {
+            if let Some(tag) = &self.wake {
+                match snooze::wake(&self.client, &[], tag, false).await {
+                    Ok(woken) => {
+                        for bookmark in &woken {
+                            self.show_notification("Snooze over", bookmark);
+                        }
+                    }
+                    Err(err) => eprintln!("{} {err:#}", "Wake failed:".yellow()),
+                }
+            }
+            match self.poll(last_added).await {
+                Ok(new) => {
+                    for bookmark in &new {
+                        self.announce(bookmark).await;
+                    }
+                    if let Some(newest) = new.iter().map(|x| x.date_added).max() {
+                        last_added = newest;
+                        self.save_state(last_added);
+                    }
+                }
+                Err(err) => eprintln!("{} {err:#}", "Poll failed:".yellow()),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Bookmarks added after `since`, oldest first. Pages are read until one
+    /// reaches back to `since`, so a busy interval loses nothing.
+    async fn poll(&self, since: DateTime<Utc>) -> Result<Vec<Bookmark>> {
+        let mut params = BookmarksRequest {
+            query: self.query.clone(),
+            added_since: Some(since),
+            sort: Some(BookmarkSort::AddedDesc),
+            ..Default::default()
+        };
+        let mut new = vec![];
+        loop {
+            // Servers without `added_since` send everything, newest first.
+            let page = self.client.bookmarks(params.clone()).await?;
+            let count = page.results.len() as u64;
+            let reached = page.results.iter().any(|x| x.date_added <= since);
+            new.extend(page.results.into_iter().filter(|x| x.date_added > since));
+            if reached || count == 0 || page.next.is_none() {
+                break;
+            }
+            params.offset = Some(params.offset.unwrap_or_default() + count);
+        }
+        new.reverse();
+        Ok(new)
+    }
+
+    async fn announce(&self, bookmark: &Bookmark) {
+        self.show_notification("New bookmark", bookmark);
+        if let Some(exec) = &self.exec {
+            if let Err(err) = hooks::run_command(exec, bookmark, None).await {
+                eprintln!("{} {err:#}", "Hook failed:".yellow());
+            }
+        }
+    }
+
+    fn show_notification(&self, summary: &str, bookmark: &Bookmark) {
+        if !self.notify {
+            return;
+        }
+        let title = bookmark
+            .title
+            .as_deref()
+            .or(bookmark.website_title.as_deref())
+            .unwrap_or(bookmark.url.as_str());
+        if let Err(err) = notify(summary, &format!("{title}\n{}", bookmark.url)) {
+            eprintln!("{} {err:#}", "Notification failed:".yellow());
+        }
+    }
+
+    fn load_state(&self) -> Option<DateTime<Utc>> {
+        let data = std::fs::read(state_path()?).ok()?;
+        let state: State = serde_json::from_slice(&data).ok()?;
+        (state.host == self.host).then_some(state.last_added)
+    }
+
+    fn save_state(&self, last_added: DateTime<Utc>) {
+        let Some(path) = state_path() else {
+            return;
+        };
+        let state = State {
+            host: self.host.clone(),
+            last_added,
+        };
+        if let (Some(dir), Ok(data)) = (path.parent(), serde_json::to_vec(&state)) {
+            if std::fs::create_dir_all(dir).is_ok() {
+                let _ = std::fs::write(path, data);
+            }
+        }
+    }
+}
+
+/// Show a desktop notification.
+fn notify(summary: &str, body: &str) -> Result<()> {
+    notify_rust::Notification::new()
+        .appname("ding")
+        .summary(summary)
+        .body(body)
+        .show()
+        .context("Failed to show the notification")?;
+    Ok(())
+}
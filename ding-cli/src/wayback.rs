@@ -0,0 +1,193 @@
+//! Snapshots on the Wayback Machine for bookmarks the server did not archive.
+//!
+//! The API does not let clients set `web_archive_snapshot_url`, so the
+//! snapshot URL is added to the bookmark's notes instead.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use ding_rs::{Bookmark, DingClient};
+
+use crate::output::{to_csv, ToOutput};
+
+const SAVE_URL: &str = "https://web.archive.org/save/";
+const AVAILABLE_URL: &str = "https://archive.org/wayback/available";
+const SNAPSHOT_PREFIX: &str = "https://web.archive.org/web/";
+
+#[derive(Deserialize)]
+struct Available {
+    archived_snapshots: Snapshots,
+}
+
+#[derive(Deserialize)]
+struct Snapshots {
+    closest: Option<Snapshot>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    url: Url,
+    /// `YYYYMMDDhhmmss`, in UTC.
+    timestamp: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WaybackReport {
+    pub id: u64,
+    pub url: Url,
+    #[serde(flatten)]
+    pub result: WaybackResult,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WaybackResult {
+    /// The bookmark has a snapshot already.
+    Existing {
+        snapshot: Url,
+    },
+    Saved {
+        snapshot: Url,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+pub struct Wayback {
+    client: reqwest::Client,
+    /// How long to wait for a requested snapshot to show up.
+    pub max_wait: Duration,
+    pub poll_interval: Duration,
+}
+
+impl Wayback {
+    pub fn new(max_wait: Duration, poll_interval: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(120))
+            .user_agent(concat!("ding/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self {
+            client,
+            max_wait,
+            poll_interval,
+        })
+    }
+
+    /// Snapshot `bookmark` unless it has a snapshot already, or `force` is
+    /// set, and note the snapshot URL on the bookmark.
+    pub async fn archive(
+        &self,
+        client: &DingClient,
+        bookmark: &Bookmark,
+        force: bool,
+    ) -> Result<WaybackResult> {
+        if !force {
+            if let Some(snapshot) = existing_snapshot(bookmark) {
+                return Ok(WaybackResult::Existing { snapshot });
+            }
+        }
+        let snapshot = self.snapshot(&bookmark.url).await?;
+        let notes = bookmark.notes.as_deref().unwrap_or_default();
+        let line = format!("Wayback snapshot: {snapshot}");
+        let notes = match notes.trim_end() {
+            "" => line,
+            notes => format!("{notes}\n\n{line}"),
+        };
+        client.set_notes(bookmark.id, &notes).await?;
+        Ok(WaybackResult::Saved { snapshot })
+    }
+
+    /// Ask the Wayback Machine to save `url` and wait until the new snapshot
+    /// is listed.
+    pub async fn snapshot(&self, url: &Url) -> Result<Url> {
+        let started = Utc::now().format("%Y%m%d%H%M%S").to_string();
+        let resp = self
+            .client
+            .get(format!("{SAVE_URL}{url}"))
+            .send()
+            .await
+            .context("Failed to request a snapshot")?;
+        if !resp.status().is_success() {
+            bail!(
+                "The Wayback Machine answered with HTTP status {}",
+                resp.status()
+            );
+        }
+        let deadline = Instant::now() + self.max_wait;
+        loop {
+            if let Some(snapshot) = self.closest(url, &started).await? {
+                if snapshot.timestamp >= started {
+                    // Listed with `http`, which only redirects.
+                    let mut url = snapshot.url;
+                    let _ = url.set_scheme("https");
+                    return Ok(url);
+                }
+            }
+            if Instant::now() + self.poll_interval > deadline {
+                bail!("No snapshot showed up within {}s", self.max_wait.as_secs());
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// The snapshot of `url` closest to `timestamp`.
+    async fn closest(&self, url: &Url, timestamp: &str) -> Result<Option<Snapshot>> {
+        let available: Available = self
+            .client
+            .get(AVAILABLE_URL)
+            .query(&[("url", url.as_str()), ("timestamp", timestamp)])
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await
+            .map(|x| serde_json::from_slice(&x))??;
+        Ok(available.archived_snapshots.closest)
+    }
+}
+
+/// The snapshot the server made, or one noted by an earlier run.
+fn existing_snapshot(bookmark: &Bookmark) -> Option<Url> {
+    if let Some(snapshot) = &bookmark.web_archive_snapshot_url {
+        return Some(snapshot.clone());
+    }
+    let notes = bookmark.notes.as_deref()?;
+    let start = notes.find(SNAPSHOT_PREFIX)?;
+    let end = notes[start..]
+        .find(char::is_whitespace)
+        .map_or(notes.len(), |x| start + x);
+    Url::parse(&notes[start..end]).ok()
+}
+
+impl ToOutput for Vec<WaybackReport> {
+    fn to_human_format(&self) -> Result<String> {
+        Ok(self
+            .iter()
+            .map(|x| {
+                let result = match &x.result {
+                    WaybackResult::Existing { snapshot } => {
+                        format!("has snapshot {snapshot}").dark_grey()
+                    }
+                    WaybackResult::Saved { snapshot } => format!("saved {snapshot}").green(),
+                    WaybackResult::Failed { message } => format!("failed: {message}").red(),
+                };
+                format!(
+                    "{} {} {}",
+                    format!("(ID: {})", x.id).green().bold(),
+                    x.url,
+                    result
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+    fn to_csv_format(&self) -> Result<String> {
+        to_csv(serde_json::to_value(self)?)
+    }
+}
@@ -0,0 +1,573 @@
+//! A blocking client with the same methods as [`DingClient`], for programs
+//! that do not run an async runtime. Enabled by the `blocking` feature.
+//!
+//! [`DingClient`]: crate::DingClient
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::cancel::CancellationToken;
+use crate::client::{
+    bookmarks_query, check_status, decode, next_page_request, page_query, supported,
+};
+use crate::errors::*;
+use crate::http_cache::{self, HttpCache};
+use crate::rate_limit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::transport::{HttpRequest, HttpResponse, Method, TransportError};
+use crate::types::*;
+
+type Result<T, E = DingError> = std::result::Result<T, E>;
+
+pub struct DingBlockingClient {
+    client: reqwest::blocking::Client,
+    base_url: Url,
+    api_token: String,
+    rate_limiter: RateLimiter,
+    retry_policy: RetryPolicy,
+    http_cache: Option<Arc<dyn HttpCache>>,
+    capabilities: Mutex<Option<Capabilities>>,
+}
+
+impl DingBlockingClient {
+    pub fn new(base_url: Url, api_token: String) -> Self {
+        crate::DingClient::builder(base_url, api_token).build_blocking()
+    }
+
+    pub(crate) fn from_parts(
+        base_url: Url,
+        api_token: String,
+        rate_limiter: RateLimiter,
+        retry_policy: RetryPolicy,
+        timeout: Option<Duration>,
+        http_cache: Option<Arc<dyn HttpCache>>,
+        proxy: Option<reqwest::Proxy>,
+    ) -> Self {
+        // Without a timeout, reqwest's blocking default of 30s applies.
+        let mut client = reqwest::blocking::Client::builder();
+        if let Some(timeout) = timeout {
+            client = client.timeout(timeout);
+        }
+        if let Some(proxy) = proxy {
+            client = client.proxy(proxy);
+        }
+        Self {
+            client: client.build().unwrap_or_default(),
+            base_url: crate::urls::base_url(base_url),
+            api_token,
+            rate_limiter,
+            retry_policy,
+            http_cache,
+            capabilities: Mutex::new(None),
+        }
+    }
+
+    pub fn all_bookmarks(&self, params: BookmarksRequest) -> Result<Vec<Bookmark>> {
+        self.all_bookmarks_cancellable(params, &CancellationToken::new())
+    }
+
+    /// Like [`all_bookmarks`](Self::all_bookmarks), but fails with
+    /// [`DingError::Cancelled`] before the next page once `cancel` is
+    /// cancelled.
+    pub fn all_bookmarks_cancellable(
+        &self,
+        params: BookmarksRequest,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Bookmark>> {
+        self._load_all(params, cancel, |p| self.bookmarks(p))
+    }
+
+    pub fn bookmarks(&self, params: BookmarksRequest) -> Result<BookmarksResponse> {
+        if params.bundle.is_some() {
+            self._require("bundles", |x| x.bundles)?;
+        }
+        let offset = params.offset.unwrap_or(0);
+        let req = self
+            ._request_builder(Method::Get, "api/bookmarks/")?
+            .query(&bookmarks_query(params));
+        self._load_page(req, "api/bookmarks/", offset)
+    }
+
+    pub fn all_archived(&self, params: BookmarksRequest) -> Result<Vec<Bookmark>> {
+        self.all_archived_cancellable(params, &CancellationToken::new())
+    }
+
+    /// Like [`all_archived`](Self::all_archived), but fails with
+    /// [`DingError::Cancelled`] before the next page once `cancel` is
+    /// cancelled.
+    pub fn all_archived_cancellable(
+        &self,
+        params: BookmarksRequest,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Bookmark>> {
+        self._load_all(params, cancel, |p| self.archived(p))
+    }
+
+    pub fn archived(&self, params: BookmarksRequest) -> Result<BookmarksResponse> {
+        if params.bundle.is_some() {
+            self._require("bundles", |x| x.bundles)?;
+        }
+        let offset = params.offset.unwrap_or(0);
+        let req = self
+            ._request_builder(Method::Get, "api/bookmarks/archived/")?
+            .query(&bookmarks_query(params));
+        self._load_page(req, "api/bookmarks/archived/", offset)
+    }
+
+    pub fn bookmark(&self, id: u64) -> Result<Bookmark> {
+        let req = self._request_builder(Method::Get, &format!("api/bookmarks/{id}/"))?;
+        self._send_request_with_json_output(req)
+    }
+
+    pub fn check_bookmark(&self, url: &Url) -> Result<CheckResponse> {
+        let req = self
+            ._request_builder(Method::Get, "api/bookmarks/check/")?
+            .query(&[("url", Some(url.to_string()))]);
+        self._send_request_with_json_output(req)
+    }
+
+    /// Fetch the page at `url` and read its title, description and canonical
+    /// URL, see [`DingClient::scrape`](crate::DingClient::scrape).
+    #[cfg(feature = "scrape")]
+    pub fn scrape(&self, url: &Url) -> Result<crate::scrape::PageMetadata> {
+        let req = HttpRequest::new(Method::Get, url.clone()).header("Accept", "text/html");
+        let resp = check_status(self._execute(req)?)?;
+        let html = String::from_utf8_lossy(&resp.body);
+        Ok(crate::scrape::PageMetadata::parse(&html, url))
+    }
+
+    /// The bookmark saved for `url`, if any. Servers without the check
+    /// endpoint are searched for the URL instead.
+    pub fn bookmark_by_url(&self, url: &Url) -> Result<Option<Bookmark>> {
+        match self.check_bookmark(url) {
+            Ok(check) => return Ok(check.bookmark),
+            Err(err) if err.status() == Some(404) => {}
+            Err(err) => return Err(err),
+        }
+        let params = BookmarksRequest {
+            query: Some(url.to_string()),
+            ..Default::default()
+        };
+        let mut bookmarks = self.all_bookmarks(params.clone())?;
+        bookmarks.extend(self.all_archived(params)?);
+        Ok(bookmarks.into_iter().find(|x| &x.url == url))
+    }
+
+    pub fn create_bookmark(&self, params: BookmarkRequest) -> Result<Bookmark> {
+        params.validate(true)?;
+        let req = self
+            ._request_builder(Method::Post, "api/bookmarks/")?
+            .json(&params);
+        self._send_request_with_json_output(req)
+    }
+
+    pub fn reset_bookmark(&self, id: u64, params: BookmarkRequest) -> Result<Bookmark> {
+        params.validate(true)?;
+        let req = self
+            ._request_builder(Method::Put, &format!("api/bookmarks/{id}/"))?
+            .json(&params);
+        self._send_request_with_json_output(req)
+    }
+
+    /// Create a bookmark, or if its URL is bookmarked already, merge `params`
+    /// into the existing bookmark as described in [`Bookmark::merge`].
+    pub fn upsert_bookmark(&self, params: BookmarkRequest) -> Result<Upserted> {
+        params.validate(true)?;
+        let url = params.url.as_ref().expect("validated requests have a url");
+        let Some(existing) = self.bookmark_by_url(url)? else {
+            return Ok(Upserted::Created(self.create_bookmark(params)?));
+        };
+        let patch = existing.merge(&params);
+        if patch.is_empty() {
+            return Ok(Upserted::Updated(existing));
+        }
+        let bookmark = self.update_bookmark(existing.id, patch)?;
+        Ok(Upserted::Updated(bookmark))
+    }
+
+    pub fn update_bookmark(&self, id: u64, params: BookmarkRequest) -> Result<Bookmark> {
+        params.validate(false)?;
+        let req = self
+            ._request_builder(Method::Patch, &format!("api/bookmarks/{id}/"))?
+            .json(&params);
+        self._send_request_with_json_output(req)
+    }
+
+    pub fn archive_bookmark(&self, id: u64) -> Result<()> {
+        let req = self._request_builder(Method::Post, &format!("api/bookmarks/{id}/archive/"))?;
+        self._send_request_without_output(req)
+    }
+
+    pub fn unarchive_bookmark(&self, id: u64) -> Result<()> {
+        let req = self._request_builder(Method::Post, &format!("api/bookmarks/{id}/unarchive/"))?;
+        self._send_request_without_output(req)
+    }
+
+    /// Mark a bookmark as read or unread.
+    pub fn set_unread(&self, id: u64, unread: bool) -> Result<Bookmark> {
+        let req = BookmarkRequest {
+            unread: Some(unread),
+            ..Default::default()
+        };
+        self.update_bookmark(id, req)
+    }
+
+    pub fn set_shared(&self, id: u64, shared: bool) -> Result<Bookmark> {
+        let req = BookmarkRequest {
+            shared: Some(shared),
+            ..Default::default()
+        };
+        self.update_bookmark(id, req)
+    }
+
+    /// Replace the notes of a bookmark; an empty string removes them.
+    pub fn set_notes(&self, id: u64, notes: &str) -> Result<Bookmark> {
+        let req = BookmarkRequest {
+            notes: Some(notes.to_string()),
+            ..Default::default()
+        };
+        self.update_bookmark(id, req)
+    }
+
+    /// Add tags to a bookmark, keeping its other tags. Nothing is sent if it
+    /// has them all already.
+    pub fn add_tags(&self, id: u64, tags: &[&str]) -> Result<Bookmark> {
+        let bookmark = self.bookmark(id)?;
+        let tag_names = bookmark.tags_with(tags);
+        self._set_tags(bookmark, tag_names)
+    }
+
+    /// Remove tags from a bookmark. Nothing is sent if it has none of them.
+    pub fn remove_tags(&self, id: u64, tags: &[&str]) -> Result<Bookmark> {
+        let bookmark = self.bookmark(id)?;
+        let tag_names = bookmark.tags_without(tags);
+        self._set_tags(bookmark, tag_names)
+    }
+
+    fn _set_tags(&self, bookmark: Bookmark, tag_names: Vec<String>) -> Result<Bookmark> {
+        if tag_names == bookmark.tag_names {
+            return Ok(bookmark);
+        }
+        let req = BookmarkRequest {
+            tag_names: Some(tag_names),
+            ..Default::default()
+        };
+        self.update_bookmark(bookmark.id, req)
+    }
+
+    pub fn delete_bookmark(&self, id: u64) -> Result<()> {
+        let req = self._request_builder(Method::Delete, &format!("api/bookmarks/{id}/"))?;
+        self._send_request_without_output(req)
+    }
+
+    pub fn all_tags(&self, params: TagsRequest) -> Result<Vec<Tag>> {
+        self.all_tags_cancellable(params, &CancellationToken::new())
+    }
+
+    /// Like [`all_tags`](Self::all_tags), but fails with
+    /// [`DingError::Cancelled`] before the next page once `cancel` is
+    /// cancelled.
+    pub fn all_tags_cancellable(
+        &self,
+        params: TagsRequest,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Tag>> {
+        self._load_all(params, cancel, |p| self.tags(p))
+    }
+
+    pub fn tags(&self, params: TagsRequest) -> Result<TagsResponse> {
+        let req = self
+            ._request_builder(Method::Get, "api/tags/")?
+            .query(&page_query(params.limit, params.offset));
+        self._load_page(req, "api/tags/", params.offset.unwrap_or(0))
+    }
+
+    pub fn tag(&self, id: u64) -> Result<Tag> {
+        let req = self._request_builder(Method::Get, &format!("api/tags/{id}/"))?;
+        self._send_request_with_json_output(req)
+    }
+
+    pub fn create_tag(&self, params: TagRequest) -> Result<Tag> {
+        params.validate()?;
+        let req = self
+            ._request_builder(Method::Post, "api/tags/")?
+            .json(&params);
+        self._send_request_with_json_output(req)
+    }
+
+    pub fn all_bundles(&self, params: BundlesRequest) -> Result<Vec<Bundle>> {
+        self.all_bundles_cancellable(params, &CancellationToken::new())
+    }
+
+    /// Like [`all_bundles`](Self::all_bundles), but fails with
+    /// [`DingError::Cancelled`] before the next page once `cancel` is
+    /// cancelled.
+    pub fn all_bundles_cancellable(
+        &self,
+        params: BundlesRequest,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Bundle>> {
+        self._load_all(params, cancel, |p| self.bundles(p))
+    }
+
+    pub fn bundles(&self, params: BundlesRequest) -> Result<BundlesResponse> {
+        self._require("bundles", |x| x.bundles)?;
+        let req = self
+            ._request_builder(Method::Get, "api/bundles/")?
+            .query(&page_query(params.limit, params.offset));
+        self._load_page(req, "api/bundles/", params.offset.unwrap_or(0))
+    }
+
+    pub fn bundle(&self, id: u64) -> Result<Bundle> {
+        self._require("bundles", |x| x.bundles)?;
+        let req = self._request_builder(Method::Get, &format!("api/bundles/{id}/"))?;
+        self._send_request_with_json_output(req)
+    }
+
+    pub fn create_bundle(&self, params: BundleRequest) -> Result<Bundle> {
+        self._require("bundles", |x| x.bundles)?;
+        let req = self
+            ._request_builder(Method::Post, "api/bundles/")?
+            .json(&params);
+        self._send_request_with_json_output(req)
+    }
+
+    pub fn update_bundle(&self, id: u64, params: BundleRequest) -> Result<Bundle> {
+        self._require("bundles", |x| x.bundles)?;
+        let req = self
+            ._request_builder(Method::Patch, &format!("api/bundles/{id}/"))?
+            .json(&params);
+        self._send_request_with_json_output(req)
+    }
+
+    pub fn delete_bundle(&self, id: u64) -> Result<()> {
+        self._require("bundles", |x| x.bundles)?;
+        let req = self._request_builder(Method::Delete, &format!("api/bundles/{id}/"))?;
+        self._send_request_without_output(req)
+    }
+
+    /// What the server supports, probed on the first call and remembered.
+    pub fn capabilities(&self) -> Result<Capabilities> {
+        let mut capabilities = self
+            .capabilities
+            .lock()
+            .expect("capabilities lock poisoned");
+        if let Some(capabilities) = &*capabilities {
+            return Ok(capabilities.clone());
+        }
+        let probed = self._probe_capabilities()?;
+        *capabilities = Some(probed.clone());
+        Ok(probed)
+    }
+
+    fn _probe_capabilities(&self) -> Result<Capabilities> {
+        let version = self._health_version();
+        let bundles = self
+            ._request_builder(Method::Get, "api/bundles/")?
+            .query(&page_query(Some(1), None));
+        let bundles = supported(self._send_request(bundles))?;
+        let first = self
+            ._request_builder(Method::Get, "api/bookmarks/")?
+            .query(&page_query(Some(1), None));
+        let first: Page<Bookmark> = self._load_page(first, "", 0)?;
+
+        // Servers without `modified_since` ignore it, so asking for changes
+        // from the future returns bookmarks only on those.
+        let modified_since = match first.count {
+            0 => true,
+            _ => {
+                let req =
+                    self._request_builder(Method::Get, "api/bookmarks/")?
+                        .query(&bookmarks_query(BookmarksRequest {
+                            limit: Some(1),
+                            modified_since: Some(Utc::now() + TimeDelta::days(1)),
+                            ..Default::default()
+                        }));
+                self._load_page::<Bookmark>(req, "", 0)?.count == 0
+            }
+        };
+        let assets = match first.results.first() {
+            Some(bookmark) => {
+                let path = format!("api/bookmarks/{}/assets/", bookmark.id);
+                supported(self._send_request(self._request_builder(Method::Get, &path)?))?
+            }
+            None => false,
+        };
+        Ok(Capabilities {
+            version,
+            bundles,
+            modified_since,
+            assets,
+        })
+    }
+
+    fn _health_version(&self) -> Option<String> {
+        let url = self.base_url.join("health").ok()?;
+        let resp = self._send_request(HttpRequest::new(Method::Get, url));
+        decode::<Health>(resp.ok()?).ok()?.version
+    }
+
+    fn _require(
+        &self,
+        feature: &'static str,
+        supported: impl Fn(&Capabilities) -> bool,
+    ) -> Result<()> {
+        match supported(&self.capabilities()?) {
+            true => Ok(()),
+            false => Err(DingError::Unsupported { feature }),
+        }
+    }
+
+    pub fn user_profile(&self) -> Result<UserProfile> {
+        let req = self._request_builder(Method::Get, "api/user/profile/")?;
+        self._send_request_with_json_output(req)
+    }
+
+    /// Store a file with a bookmark. Needs a server with assets, see
+    /// [`Capabilities::assets`].
+    pub fn upload_asset(
+        &self,
+        id: u64,
+        file_name: &str,
+        content_type: &str,
+        data: &[u8],
+    ) -> Result<Asset> {
+        self._require("assets", |x| x.assets)?;
+        let req = self
+            ._request_builder(Method::Post, &format!("api/bookmarks/{id}/assets/upload/"))?
+            .multipart_file("file", file_name, content_type, data);
+        self._send_request_with_json_output(req)
+    }
+
+    /// The URL of a feed, for readers that should not get the API token.
+    pub fn feed_url(&self, kind: FeedKind, feed_token: &str) -> Result<Url> {
+        Ok(self.base_url.join(&kind.path(feed_token))?)
+    }
+
+    /// The RSS document of a feed.
+    pub fn feed(&self, kind: FeedKind, feed_token: &str) -> Result<String> {
+        let req = HttpRequest::new(Method::Get, self.feed_url(kind, feed_token)?);
+        let resp = self._send_request(req)?;
+        Ok(String::from_utf8_lossy(&resp.body).into_owned())
+    }
+
+    fn _request_builder(&self, method: Method, api_path: &str) -> Result<HttpRequest> {
+        let url = crate::urls::api_url(&self.base_url, api_path)?;
+        Ok(HttpRequest::new(method, url)
+            .header("Authorization", format!("Token {0}", self.api_token)))
+    }
+
+    fn _send_request(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let mut retry = 1;
+        loop {
+            let resp = self._exchange(request.clone());
+            let Some(delay) = self.retry_policy.delay(request.method, &resp, retry) else {
+                return check_status(resp?);
+            };
+            std::thread::sleep(delay);
+            retry += 1;
+        }
+    }
+
+    /// Send `request` once, without looking at the status.
+    fn _exchange(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let api_path = request
+            .url
+            .path()
+            .strip_prefix(self.base_url.path())
+            .unwrap_or_default();
+        self.rate_limiter.acquire_blocking(api_path);
+        let (request, revalidation) = match &self.http_cache {
+            Some(cache) => http_cache::conditional(cache.as_ref(), request),
+            None => (request, None),
+        };
+        #[cfg(feature = "tracing")]
+        let log = crate::logging::RequestLog::start(&request);
+        let resp = self._execute(request);
+        #[cfg(feature = "tracing")]
+        match &resp {
+            Ok(resp) => log.finish(resp.status),
+            Err(err) => log.fail(err),
+        }
+        let mut resp = resp?;
+        if let (Some(cache), Some(revalidation)) = (&self.http_cache, revalidation) {
+            resp = revalidation.finish(cache.as_ref(), resp);
+        }
+        Ok(resp)
+    }
+
+    fn _execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let mut req = self.client.request(request.method.into(), request.url);
+        for (name, value) in request.headers {
+            req = req.header(name, value);
+        }
+        if let Some(body) = request.body {
+            req = req.body(body);
+        }
+        let resp = req.send()?;
+        let status = resp.status().as_u16();
+        let headers = crate::transport::response_headers(resp.headers());
+        let body = resp.bytes()?.to_vec();
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    fn _send_request_with_json_output<O: DeserializeOwned>(&self, req: HttpRequest) -> Result<O> {
+        decode(self._send_request(req)?)
+    }
+
+    fn _send_request_without_output(&self, req: HttpRequest) -> Result<()> {
+        self._send_request(req)?;
+        Ok(())
+    }
+
+    /// Load the page after `page`, see [`Page::fetch_next_blocking`].
+    pub(crate) fn next_page<T: DeserializeOwned>(&self, page: &Page<T>) -> Result<Option<Page<T>>> {
+        let Some(offset) = page.next_offset() else {
+            return Ok(None);
+        };
+        let req = next_page_request(self._request_builder(Method::Get, &page.endpoint)?, page);
+        self._load_page(req, &page.endpoint, offset).map(Some)
+    }
+
+    fn _load_page<T: DeserializeOwned>(
+        &self,
+        req: HttpRequest,
+        endpoint: &str,
+        offset: u64,
+    ) -> Result<Page<T>> {
+        let page = self._send_request_with_json_output(req)?;
+        Ok(Page {
+            offset,
+            endpoint: endpoint.to_string(),
+            ..page
+        })
+    }
+
+    fn _load_all<O, P: IterableRequest>(
+        &self,
+        params: P,
+        cancel: &CancellationToken,
+        call: impl Fn(P) -> Result<Page<O>>,
+    ) -> Result<Vec<O>> {
+        let params = params.limit(None).offset(None);
+        let mut results = vec![];
+        loop {
+            cancel.check()?;
+            let mut resp = call(params.offset(Some(results.len() as u64)))?;
+            results.append(&mut resp.results);
+            if !resp.has_next() {
+                break;
+            }
+        }
+        Ok(results)
+    }
+}
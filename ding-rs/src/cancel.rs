@@ -0,0 +1,180 @@
+//! Stopping long operations, such as loading every page of bookmarks, from
+//! another task or thread.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures::future::{self, Either};
+
+use crate::errors::DingError;
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    waiters: Mutex<Waiters>,
+}
+
+/// The wakers of the [`Cancelled`] futures waiting on a token, by a key each
+/// future removes its waker with when it is dropped.
+#[derive(Default)]
+struct Waiters {
+    next: u64,
+    wakers: HashMap<u64, Waker>,
+}
+
+/// Cancels every operation it was given to once [`cancel`](Self::cancel) is
+/// called on any clone of it. Async operations drop their requests in
+/// flight; blocking ones stop before the next request.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        let wakers = std::mem::take(&mut self.lock().wakers);
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the token is cancelled.
+    pub fn cancelled(&self) -> Cancelled<'_> {
+        Cancelled {
+            token: self,
+            key: None,
+        }
+    }
+
+    /// Run `fut` until it finishes or the token is cancelled, whichever
+    /// comes first. `fut` is dropped on cancellation.
+    pub async fn run<T>(&self, fut: impl Future<Output = T>) -> Result<T, DingError> {
+        if self.is_cancelled() {
+            return Err(DingError::Cancelled);
+        }
+        match future::select(std::pin::pin!(fut), self.cancelled()).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right(_) => Err(DingError::Cancelled),
+        }
+    }
+
+    /// Fail with [`DingError::Cancelled`] if the token was cancelled.
+    pub fn check(&self) -> Result<(), DingError> {
+        match self.is_cancelled() {
+            true => Err(DingError::Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Waiters> {
+        self.inner
+            .waiters
+            .lock()
+            .expect("cancellation lock poisoned")
+    }
+}
+
+/// Future returned by [`CancellationToken::cancelled`].
+pub struct Cancelled<'a> {
+    token: &'a CancellationToken,
+    /// Where the waker is stored, once polled.
+    key: Option<u64>,
+}
+
+impl Future for Cancelled<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+        let mut waiters = self.token.lock();
+        let key = *self.key.get_or_insert_with(|| {
+            waiters.next += 1;
+            waiters.next
+        });
+        match waiters.wakers.get_mut(&key) {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            Some(waker) => waker.clone_from(cx.waker()),
+            None => {
+                waiters.wakers.insert(key, cx.waker().clone());
+            }
+        }
+        drop(waiters);
+        // `cancel` may have run before the waker was stored.
+        match self.token.is_cancelled() {
+            true => Poll::Ready(()),
+            false => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for Cancelled<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.token.lock().wakers.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::channel::oneshot;
+    use futures::task::noop_waker_ref;
+    use futures::FutureExt;
+
+    use super::*;
+
+    fn waiting(token: &CancellationToken) -> usize {
+        token.lock().wakers.len()
+    }
+
+    #[test]
+    fn finished_run_forgets_its_waker() {
+        let token = CancellationToken::new();
+        let (tx, rx) = oneshot::channel::<u32>();
+        let mut cx = Context::from_waker(noop_waker_ref());
+        {
+            let mut run = std::pin::pin!(token.run(rx));
+            assert!(run.as_mut().poll(&mut cx).is_pending());
+            assert_eq!(waiting(&token), 1);
+            assert!(run.as_mut().poll(&mut cx).is_pending());
+            assert_eq!(waiting(&token), 1);
+            tx.send(7).unwrap();
+            assert!(matches!(run.poll(&mut cx), Poll::Ready(Ok(Ok(7)))));
+        }
+        assert_eq!(waiting(&token), 0);
+    }
+
+    #[test]
+    fn cancelled_run_forgets_its_waker() {
+        let token = CancellationToken::new();
+        let (_tx, rx) = oneshot::channel::<u32>();
+        let mut cx = Context::from_waker(noop_waker_ref());
+        {
+            let mut run = std::pin::pin!(token.run(rx));
+            assert!(run.as_mut().poll(&mut cx).is_pending());
+            assert_eq!(waiting(&token), 1);
+            token.cancel();
+            assert!(matches!(
+                run.poll(&mut cx),
+                Poll::Ready(Err(DingError::Cancelled))
+            ));
+        }
+        assert_eq!(waiting(&token), 0);
+        assert!(token.run(async { 1 }).now_or_never().unwrap().is_err());
+    }
+}
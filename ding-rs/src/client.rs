@@ -1,211 +1,974 @@
 // use anyhow::Result;
-use reqwest::{RequestBuilder, Response, Url};
+use chrono::{SecondsFormat, TimeDelta, Utc};
+use futures::{stream, StreamExt};
 use serde::de::DeserializeOwned;
 use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
 
+use crate::cancel::CancellationToken;
+use crate::coalesce::InFlight;
 use crate::errors::*;
+use crate::http_cache::{self, HttpCache};
+use crate::rate_limit::RateLimiter;
+use crate::retry::{self, RetryPolicy};
+use crate::transport::{HttpRequest, HttpResponse, HttpTransport, Method, TransportError};
 use crate::types::*;
 
 type Result<T, E = DingError> = std::result::Result<T, E>;
 
+/// Pages fetched at the same time by the `all_*` methods unless changed with
+/// [`DingClientBuilder::page_concurrency`].
+pub const DEFAULT_PAGE_CONCURRENCY: usize = 4;
+
+/// Identical GET requests made while one is in flight share its response.
 pub struct DingClient {
-    client: reqwest::Client,
+    transport: Arc<dyn HttpTransport>,
+    in_flight: InFlight,
+    capabilities: futures::lock::Mutex<Option<Capabilities>>,
+    http_cache: Option<Arc<dyn HttpCache>>,
+    base_url: Url,
+    api_token: String,
+    page_concurrency: usize,
+    rate_limiter: RateLimiter,
+    retry_policy: RetryPolicy,
+    timeout: Option<Duration>,
+}
+
+pub struct DingClientBuilder {
     base_url: Url,
     api_token: String,
+    page_concurrency: usize,
+    rate_limiter: RateLimiter,
+    retry_policy: RetryPolicy,
+    timeout: Option<Duration>,
+    transport: Option<Arc<dyn HttpTransport>>,
+    http_cache: Option<Arc<dyn HttpCache>>,
+    #[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+    proxy: Option<reqwest::Proxy>,
+}
+
+impl DingClientBuilder {
+    /// Number of pages the `all_*` methods fetch at the same time once the
+    /// first page tells how many there are. `1` fetches them one by one.
+    pub fn page_concurrency(mut self, page_concurrency: usize) -> Self {
+        self.page_concurrency = page_concurrency.max(1);
+        self
+    }
+
+    /// Send at most this many requests per second on average. Short bursts of
+    /// up to a second's worth are allowed. Zero means no limit.
+    pub fn max_requests_per_second(mut self, rate: f64) -> Self {
+        self.rate_limiter.set_default(rate);
+        self
+    }
+
+    /// Limit requests to API paths starting with `prefix`, e.g.
+    /// `api/bookmarks/check/`, separately. The longest matching prefix
+    /// replaces the default limit; zero exempts the endpoint from it.
+    pub fn endpoint_requests_per_second(mut self, prefix: &str, rate: f64) -> Self {
+        self.rate_limiter.set_endpoint(prefix, rate);
+        self
+    }
+
+    /// Send failed requests again as `policy` allows.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Give up on a request that got no response within `timeout`. Each
+    /// retry gets the full time again.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Connect to the server through `proxy` instead of the proxies set in
+    /// `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY`. `socks5://` proxies need
+    /// the `socks` feature. Ignored if a [`transport`](Self::transport) is
+    /// set.
+    #[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Send requests through `transport` instead of the default
+    /// [`ReqwestTransport`](crate::transport::ReqwestTransport).
+    pub fn transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Revalidate GET responses with the server instead of downloading them
+    /// again when they have not changed. See [`crate::http_cache`].
+    pub fn http_cache(mut self, cache: impl HttpCache + 'static) -> Self {
+        self.http_cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Without the `reqwest` feature, if no [`transport`](Self::transport)
+    /// was set.
+    pub fn build(self) -> DingClient {
+        DingClient {
+            transport: match self.transport {
+                Some(transport) => transport,
+                None => default_transport(&self),
+            },
+            in_flight: InFlight::default(),
+            capabilities: Default::default(),
+            http_cache: self.http_cache,
+            base_url: crate::urls::base_url(self.base_url),
+            api_token: self.api_token,
+            page_concurrency: self.page_concurrency,
+            rate_limiter: self.rate_limiter,
+            retry_policy: self.retry_policy,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Build a blocking client instead. Page concurrency does not apply to it.
+    #[cfg(feature = "blocking")]
+    pub fn build_blocking(self) -> crate::DingBlockingClient {
+        crate::DingBlockingClient::from_parts(
+            self.base_url,
+            self.api_token,
+            self.rate_limiter,
+            self.retry_policy,
+            self.timeout,
+            self.http_cache,
+            self.proxy,
+        )
+    }
 }
 
 impl DingClient {
     pub fn new(base_url: Url, api_token: String) -> Self {
-        Self {
-            client: reqwest::Client::new(),
+        Self::builder(base_url, api_token).build()
+    }
+
+    pub fn builder(base_url: Url, api_token: String) -> DingClientBuilder {
+        DingClientBuilder {
             base_url,
             api_token,
+            page_concurrency: DEFAULT_PAGE_CONCURRENCY,
+            rate_limiter: RateLimiter::default(),
+            retry_policy: RetryPolicy::default(),
+            timeout: None,
+            transport: None,
+            http_cache: None,
+            #[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+            proxy: None,
         }
     }
 
     pub async fn all_bookmarks(&self, params: BookmarksRequest) -> Result<Vec<Bookmark>> {
-        self._load_all(params, |p| async { self.bookmarks(p).await })
+        self.all_bookmarks_cancellable(params, &CancellationToken::new())
             .await
     }
 
+    /// Like [`all_bookmarks`](Self::all_bookmarks), but fails with
+    /// [`DingError::Cancelled`] as soon as `cancel` is cancelled.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/"))
+    )]
+    pub async fn all_bookmarks_cancellable(
+        &self,
+        params: BookmarksRequest,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Bookmark>> {
+        self._load_all(params, cancel, |p| async { self.bookmarks(p).await })
+            .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(
+                endpoint = "api/bookmarks/",
+                offset = params.offset,
+                limit = params.limit,
+            )
+        )
+    )]
     pub async fn bookmarks(&self, params: BookmarksRequest) -> Result<BookmarksResponse> {
-        let req = self._bookmarks_request_builder("api/bookmarks/", params)?;
-        self._send_request_with_json_output(req).await
+        if params.bundle.is_some() {
+            self._require("bundles", |x| x.bundles).await?;
+        }
+        let offset = params.offset.unwrap_or(0);
+        let req = self
+            ._request_builder(Method::Get, "api/bookmarks/")?
+            .query(&bookmarks_query(params));
+        self._load_page(req, "api/bookmarks/", offset).await
     }
 
     pub async fn all_archived(&self, params: BookmarksRequest) -> Result<Vec<Bookmark>> {
-        self._load_all(params, |p| async { self.archived(p).await })
+        self.all_archived_cancellable(params, &CancellationToken::new())
+            .await
+    }
+
+    /// Like [`all_archived`](Self::all_archived), but fails with
+    /// [`DingError::Cancelled`] as soon as `cancel` is cancelled.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/archived/"))
+    )]
+    pub async fn all_archived_cancellable(
+        &self,
+        params: BookmarksRequest,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Bookmark>> {
+        self._load_all(params, cancel, |p| async { self.archived(p).await })
             .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(
+                endpoint = "api/bookmarks/archived/",
+                offset = params.offset,
+                limit = params.limit,
+            )
+        )
+    )]
     pub async fn archived(&self, params: BookmarksRequest) -> Result<BookmarksResponse> {
-        let req = self._bookmarks_request_builder("api/bookmarks/archived/", params)?;
-        self._send_request_with_json_output(req).await
+        if params.bundle.is_some() {
+            self._require("bundles", |x| x.bundles).await?;
+        }
+        let offset = params.offset.unwrap_or(0);
+        let req = self
+            ._request_builder(Method::Get, "api/bookmarks/archived/")?
+            .query(&bookmarks_query(params));
+        self._load_page(req, "api/bookmarks/archived/", offset)
+            .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/", id = id))
+    )]
     pub async fn bookmark(&self, id: u64) -> Result<Bookmark> {
-        let req = self._request_builder(reqwest::Method::GET, &format!("api/bookmarks/{id}/"))?;
+        let req = self._request_builder(Method::Get, &format!("api/bookmarks/{id}/"))?;
         self._send_request_with_json_output(req).await
     }
 
+    /// Look up `url`: the bookmark saved for it, if any, and the metadata the
+    /// server scrapes from the page.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/check/", url = %url))
+    )]
+    pub async fn check_bookmark(&self, url: &Url) -> Result<CheckResponse> {
+        let req = self
+            ._request_builder(Method::Get, "api/bookmarks/check/")?
+            .query(&[("url", Some(url.to_string()))]);
+        self._send_request_with_json_output(req).await
+    }
+
+    /// Fetch the page at `url` and read its title, description and canonical
+    /// URL. The request goes to the site, not the server, and carries no
+    /// token, but uses the client's transport and timeout.
+    #[cfg(feature = "scrape")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(url = %url))
+    )]
+    pub async fn scrape(&self, url: &Url) -> Result<crate::scrape::PageMetadata> {
+        let req = HttpRequest::new(Method::Get, url.clone()).header("Accept", "text/html");
+        let resp = retry::with_timeout(self.timeout, self.transport.send(req)).await?;
+        let resp = check_status(resp)?;
+        let html = String::from_utf8_lossy(&resp.body);
+        Ok(crate::scrape::PageMetadata::parse(&html, url))
+    }
+
+    /// The bookmark saved for `url`, if any. Servers without the check
+    /// endpoint are searched for the URL instead.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/check/", url = %url))
+    )]
+    pub async fn bookmark_by_url(&self, url: &Url) -> Result<Option<Bookmark>> {
+        match self.check_bookmark(url).await {
+            Ok(check) => return Ok(check.bookmark),
+            Err(err) if err.status() == Some(404) => {}
+            Err(err) => return Err(err),
+        }
+        let params = BookmarksRequest {
+            query: Some(url.to_string()),
+            ..Default::default()
+        };
+        let mut bookmarks = self.all_bookmarks(params.clone()).await?;
+        bookmarks.extend(self.all_archived(params).await?);
+        Ok(bookmarks.into_iter().find(|x| &x.url == url))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/"))
+    )]
     pub async fn create_bookmark(&self, params: BookmarkRequest) -> Result<Bookmark> {
-        assert!(params.url.is_some(), "url need to be specified!");
+        params.validate(true)?;
         let req = self
-            ._request_builder(reqwest::Method::POST, "api/bookmarks/")?
+            ._request_builder(Method::Post, "api/bookmarks/")?
             .json(&params);
         self._send_request_with_json_output(req).await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/", id = id))
+    )]
     pub async fn reset_bookmark(&self, id: u64, params: BookmarkRequest) -> Result<Bookmark> {
-        assert!(params.url.is_some(), "url need to be specified!");
+        params.validate(true)?;
         let req = self
-            ._request_builder(reqwest::Method::PUT, &format!("api/bookmarks/{id}/"))?
+            ._request_builder(Method::Put, &format!("api/bookmarks/{id}/"))?
             .json(&params);
         self._send_request_with_json_output(req).await
     }
 
+    /// Create a bookmark, or if its URL is bookmarked already, merge `params`
+    /// into the existing bookmark as described in [`Bookmark::merge`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/"))
+    )]
+    pub async fn upsert_bookmark(&self, params: BookmarkRequest) -> Result<Upserted> {
+        params.validate(true)?;
+        let url = params.url.as_ref().expect("validated requests have a url");
+        let Some(existing) = self.bookmark_by_url(url).await? else {
+            return Ok(Upserted::Created(self.create_bookmark(params).await?));
+        };
+        let patch = existing.merge(&params);
+        if patch.is_empty() {
+            return Ok(Upserted::Updated(existing));
+        }
+        let bookmark = self.update_bookmark(existing.id, patch).await?;
+        Ok(Upserted::Updated(bookmark))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/", id = id))
+    )]
     pub async fn update_bookmark(&self, id: u64, params: BookmarkRequest) -> Result<Bookmark> {
+        params.validate(false)?;
         let req = self
-            ._request_builder(reqwest::Method::PATCH, &format!("api/bookmarks/{id}/"))?
+            ._request_builder(Method::Patch, &format!("api/bookmarks/{id}/"))?
             .json(&params);
         self._send_request_with_json_output(req).await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/archive/", id = id))
+    )]
     pub async fn archive_bookmark(&self, id: u64) -> Result<()> {
-        let req = self._request_builder(
-            reqwest::Method::POST,
-            &format!("api/bookmarks/{id}/archive/"),
-        )?;
+        let req = self._request_builder(Method::Post, &format!("api/bookmarks/{id}/archive/"))?;
         self._send_request_without_output(req).await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/unarchive/", id = id))
+    )]
     pub async fn unarchive_bookmark(&self, id: u64) -> Result<()> {
-        let req = self._request_builder(
-            reqwest::Method::POST,
-            &format!("api/bookmarks/{id}/unarchive/"),
-        )?;
+        let req = self._request_builder(Method::Post, &format!("api/bookmarks/{id}/unarchive/"))?;
         self._send_request_without_output(req).await
     }
 
+    /// Mark a bookmark as read or unread.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/", id = id))
+    )]
+    pub async fn set_unread(&self, id: u64, unread: bool) -> Result<Bookmark> {
+        let req = BookmarkRequest {
+            unread: Some(unread),
+            ..Default::default()
+        };
+        self.update_bookmark(id, req).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/", id = id))
+    )]
+    pub async fn set_shared(&self, id: u64, shared: bool) -> Result<Bookmark> {
+        let req = BookmarkRequest {
+            shared: Some(shared),
+            ..Default::default()
+        };
+        self.update_bookmark(id, req).await
+    }
+
+    /// Replace the notes of a bookmark; an empty string removes them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/", id = id))
+    )]
+    pub async fn set_notes(&self, id: u64, notes: &str) -> Result<Bookmark> {
+        let req = BookmarkRequest {
+            notes: Some(notes.to_string()),
+            ..Default::default()
+        };
+        self.update_bookmark(id, req).await
+    }
+
+    /// Add tags to a bookmark, keeping its other tags. Nothing is sent if it
+    /// has them all already.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/", id = id))
+    )]
+    pub async fn add_tags(&self, id: u64, tags: &[&str]) -> Result<Bookmark> {
+        let bookmark = self.bookmark(id).await?;
+        let tag_names = bookmark.tags_with(tags);
+        self._set_tags(bookmark, tag_names).await
+    }
+
+    /// Remove tags from a bookmark. Nothing is sent if it has none of them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/", id = id))
+    )]
+    pub async fn remove_tags(&self, id: u64, tags: &[&str]) -> Result<Bookmark> {
+        let bookmark = self.bookmark(id).await?;
+        let tag_names = bookmark.tags_without(tags);
+        self._set_tags(bookmark, tag_names).await
+    }
+
+    async fn _set_tags(&self, bookmark: Bookmark, tag_names: Vec<String>) -> Result<Bookmark> {
+        if tag_names == bookmark.tag_names {
+            return Ok(bookmark);
+        }
+        let req = BookmarkRequest {
+            tag_names: Some(tag_names),
+            ..Default::default()
+        };
+        self.update_bookmark(bookmark.id, req).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/", id = id))
+    )]
     pub async fn delete_bookmark(&self, id: u64) -> Result<()> {
-        let req =
-            self._request_builder(reqwest::Method::DELETE, &format!("api/bookmarks/{id}/"))?;
+        let req = self._request_builder(Method::Delete, &format!("api/bookmarks/{id}/"))?;
         self._send_request_without_output(req).await
     }
 
     pub async fn all_tags(&self, params: TagsRequest) -> Result<Vec<Tag>> {
-        self._load_all(params, |p| async { self.tags(p).await })
+        self.all_tags_cancellable(params, &CancellationToken::new())
             .await
     }
 
+    /// Like [`all_tags`](Self::all_tags), but fails with
+    /// [`DingError::Cancelled`] as soon as `cancel` is cancelled.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/tags/"))
+    )]
+    pub async fn all_tags_cancellable(
+        &self,
+        params: TagsRequest,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Tag>> {
+        self._load_all(params, cancel, |p| async { self.tags(p).await })
+            .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(
+                endpoint = "api/tags/",
+                offset = params.offset,
+                limit = params.limit,
+            )
+        )
+    )]
     pub async fn tags(&self, params: TagsRequest) -> Result<TagsResponse> {
         let req = self
-            ._request_builder(reqwest::Method::GET, "api/tags")?
-            .query(&[
-                ("limit", params.limit.map(|x| x.to_string())),
-                ("offset", params.offset.map(|x| x.to_string())),
-            ]);
-        self._send_request_with_json_output(req).await
+            ._request_builder(Method::Get, "api/tags/")?
+            .query(&page_query(params.limit, params.offset));
+        self._load_page(req, "api/tags/", params.offset.unwrap_or(0))
+            .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/tags/{id}/", id = id))
+    )]
     pub async fn tag(&self, id: u64) -> Result<Tag> {
-        let req = self._request_builder(reqwest::Method::GET, &format!("api/tags/{id}/"))?;
+        let req = self._request_builder(Method::Get, &format!("api/tags/{id}/"))?;
         self._send_request_with_json_output(req).await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/tags/"))
+    )]
     pub async fn create_tag(&self, params: TagRequest) -> Result<Tag> {
+        params.validate()?;
         let req = self
-            ._request_builder(reqwest::Method::POST, "api/tags/")?
+            ._request_builder(Method::Post, "api/tags/")?
             .json(&params);
         self._send_request_with_json_output(req).await
     }
 
-    pub async fn user_profile(&self) -> Result<UserProfile> {
-        let req = self._request_builder(reqwest::Method::GET, "api/user/profile/")?;
-        self._send_request_with_json_output(req).await
+    pub async fn all_bundles(&self, params: BundlesRequest) -> Result<Vec<Bundle>> {
+        self.all_bundles_cancellable(params, &CancellationToken::new())
+            .await
     }
 
-    fn _request_builder(
+    /// Like [`all_bundles`](Self::all_bundles), but fails with
+    /// [`DingError::Cancelled`] as soon as `cancel` is cancelled.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bundles/"))
+    )]
+    pub async fn all_bundles_cancellable(
         &self,
-        method: reqwest::Method,
-        api_path: &str,
-    ) -> Result<reqwest::RequestBuilder> {
-        let url = self.base_url.join(api_path)?;
-        Ok(self
-            .client
-            .request(method, url)
-            .header("Authorization", format!("Token {0}", self.api_token)))
+        params: BundlesRequest,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Bundle>> {
+        self._load_all(params, cancel, |p| async { self.bundles(p).await })
+            .await
     }
 
-    fn _bookmarks_request_builder(
+    /// Fails with [`DingError::Unsupported`] on servers without bundles, as do
+    /// the other bundle methods.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(
+                endpoint = "api/bundles/",
+                offset = params.offset,
+                limit = params.limit,
+            )
+        )
+    )]
+    pub async fn bundles(&self, params: BundlesRequest) -> Result<BundlesResponse> {
+        self._require("bundles", |x| x.bundles).await?;
+        let req = self
+            ._request_builder(Method::Get, "api/bundles/")?
+            .query(&page_query(params.limit, params.offset));
+        self._load_page(req, "api/bundles/", params.offset.unwrap_or(0))
+            .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bundles/{id}/", id = id))
+    )]
+    pub async fn bundle(&self, id: u64) -> Result<Bundle> {
+        self._require("bundles", |x| x.bundles).await?;
+        let req = self._request_builder(Method::Get, &format!("api/bundles/{id}/"))?;
+        self._send_request_with_json_output(req).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bundles/"))
+    )]
+    pub async fn create_bundle(&self, params: BundleRequest) -> Result<Bundle> {
+        self._require("bundles", |x| x.bundles).await?;
+        let req = self
+            ._request_builder(Method::Post, "api/bundles/")?
+            .json(&params);
+        self._send_request_with_json_output(req).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bundles/{id}/", id = id))
+    )]
+    pub async fn update_bundle(&self, id: u64, params: BundleRequest) -> Result<Bundle> {
+        self._require("bundles", |x| x.bundles).await?;
+        let req = self
+            ._request_builder(Method::Patch, &format!("api/bundles/{id}/"))?
+            .json(&params);
+        self._send_request_with_json_output(req).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bundles/{id}/", id = id))
+    )]
+    pub async fn delete_bundle(&self, id: u64) -> Result<()> {
+        self._require("bundles", |x| x.bundles).await?;
+        let req = self._request_builder(Method::Delete, &format!("api/bundles/{id}/"))?;
+        self._send_request_without_output(req).await
+    }
+
+    /// What the server supports. Probed with a few requests on the first
+    /// call and remembered for the life of the client.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn capabilities(&self) -> Result<Capabilities> {
+        let mut capabilities = self.capabilities.lock().await;
+        if let Some(capabilities) = &*capabilities {
+            return Ok(capabilities.clone());
+        }
+        let probed = self._probe_capabilities().await?;
+        *capabilities = Some(probed.clone());
+        Ok(probed)
+    }
+
+    async fn _probe_capabilities(&self) -> Result<Capabilities> {
+        let bundles = self._request_builder(Method::Get, "api/bundles/")?;
+        let first = self._request_builder(Method::Get, "api/bookmarks/")?;
+        let (version, bundles, first) = futures::join!(
+            self._health_version(),
+            self._send_request(bundles.query(&page_query(Some(1), None))),
+            self._load_page::<Bookmark>(first.query(&page_query(Some(1), None)), "", 0),
+        );
+        let first = first?;
+
+        // Servers without `modified_since` ignore it, so asking for changes
+        // from the future returns bookmarks only on those.
+        let modified_since =
+            self._request_builder(Method::Get, "api/bookmarks/")?
+                .query(&bookmarks_query(BookmarksRequest {
+                    limit: Some(1),
+                    modified_since: Some(Utc::now() + TimeDelta::days(1)),
+                    ..Default::default()
+                }));
+        let assets = first
+            .results
+            .first()
+            .map(|x| self._request_builder(Method::Get, &format!("api/bookmarks/{}/assets/", x.id)))
+            .transpose()?;
+        let (modified_since, assets) = futures::join!(
+            async {
+                match first.count {
+                    0 => Ok(true),
+                    _ => self
+                        ._load_page::<Bookmark>(modified_since, "", 0)
+                        .await
+                        .map(|x| x.count == 0),
+                }
+            },
+            async {
+                match assets {
+                    Some(req) => supported(self._send_request(req).await),
+                    None => Ok(false),
+                }
+            },
+        );
+        Ok(Capabilities {
+            version,
+            bundles: supported(bundles)?,
+            modified_since: modified_since?,
+            assets: assets?,
+        })
+    }
+
+    /// The version from the health check, which lives next to the API rather
+    /// than in it and may be blocked by a proxy.
+    async fn _health_version(&self) -> Option<String> {
+        let url = self.base_url.join("health").ok()?;
+        let resp = self._send_request(HttpRequest::new(Method::Get, url)).await;
+        decode::<Health>(resp.ok()?).ok()?.version
+    }
+
+    async fn _require(
         &self,
-        api_path: &str,
-        params: BookmarksRequest,
-    ) -> Result<reqwest::RequestBuilder> {
-        Ok(self
-            ._request_builder(reqwest::Method::GET, api_path)?
-            .query(&[
-                ("q", params.query),
-                ("limit", params.limit.map(|x| x.to_string())),
-                ("offset", params.offset.map(|x| x.to_string())),
-            ]))
+        feature: &'static str,
+        supported: impl Fn(&Capabilities) -> bool,
+    ) -> Result<()> {
+        match supported(&self.capabilities().await?) {
+            true => Ok(()),
+            false => Err(DingError::Unsupported { feature }),
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/user/profile/"))
+    )]
+    pub async fn user_profile(&self) -> Result<UserProfile> {
+        let req = self._request_builder(Method::Get, "api/user/profile/")?;
+        self._send_request_with_json_output(req).await
     }
 
-    async fn _send_request<O: DeserializeOwned, SFut>(
+    /// Store a file with a bookmark. Needs a server with assets, see
+    /// [`Capabilities::assets`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(endpoint = "api/bookmarks/{id}/assets/upload/", id = id))
+    )]
+    pub async fn upload_asset(
         &self,
-        req: RequestBuilder,
-        success: impl Fn(Response) -> SFut,
-    ) -> Result<O>
-    where
-        SFut: Future<Output = Result<O>>,
-    {
-        let resp = req.send().await?.error_for_status()?;
-        success(resp).await
+        id: u64,
+        file_name: &str,
+        content_type: &str,
+        data: &[u8],
+    ) -> Result<Asset> {
+        self._require("assets", |x| x.assets).await?;
+        let req = self
+            ._request_builder(Method::Post, &format!("api/bookmarks/{id}/assets/upload/"))?
+            .multipart_file("file", file_name, content_type, data);
+        self._send_request_with_json_output(req).await
     }
 
-    async fn _empty_response_handler(_resp: Response) -> Result<()> {
-        Ok(())
+    /// The URL of a feed, for readers that should not get the API token.
+    pub fn feed_url(&self, kind: FeedKind, feed_token: &str) -> Result<Url> {
+        Ok(self.base_url.join(&kind.path(feed_token))?)
     }
 
-    async fn _json_response_handler<O: DeserializeOwned>(resp: Response) -> Result<O> {
-        Ok(resp.json().await?)
+    /// The RSS document of a feed.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(feed = kind.as_str()))
+    )]
+    pub async fn feed(&self, kind: FeedKind, feed_token: &str) -> Result<String> {
+        let req = HttpRequest::new(Method::Get, self.feed_url(kind, feed_token)?);
+        let resp = self._send_request(req).await?;
+        Ok(String::from_utf8_lossy(&resp.body).into_owned())
+    }
+
+    fn _request_builder(&self, method: Method, api_path: &str) -> Result<HttpRequest> {
+        let url = crate::urls::api_url(&self.base_url, api_path)?;
+        Ok(HttpRequest::new(method, url)
+            .header("Authorization", format!("Token {0}", self.api_token)))
+    }
+
+    /// `attempt` counts from 1 for each time the request is sent.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "send", level = "debug", skip_all, fields(attempt = 1))
+    )]
+    async fn _send_request(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let mut retry = 1;
+        loop {
+            let resp = match request.method {
+                Method::Get => {
+                    let key = request.url.to_string();
+                    self.in_flight
+                        .run(key, || self._exchange(request.clone()))
+                        .await
+                }
+                _ => self._exchange(request.clone()).await,
+            };
+            let Some(delay) = self.retry_policy.delay(request.method, &resp, retry) else {
+                return check_status(resp?);
+            };
+            retry::sleep(delay).await;
+            retry += 1;
+            #[cfg(feature = "tracing")]
+            tracing::Span::current().record("attempt", retry);
+        }
+    }
+
+    /// Send `request` once, without looking at the status.
+    async fn _exchange(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let api_path = request
+            .url
+            .path()
+            .strip_prefix(self.base_url.path())
+            .unwrap_or_default();
+        self.rate_limiter.acquire(api_path).await;
+        let (request, revalidation) = match &self.http_cache {
+            Some(cache) => http_cache::conditional(cache.as_ref(), request),
+            None => (request, None),
+        };
+        #[cfg(feature = "tracing")]
+        let log = crate::logging::RequestLog::start(&request);
+        let resp = retry::with_timeout(self.timeout, self.transport.send(request)).await;
+        #[cfg(feature = "tracing")]
+        match &resp {
+            Ok(resp) => log.finish(resp.status),
+            Err(err) => log.fail(err),
+        }
+        let mut resp = resp?;
+        if let (Some(cache), Some(revalidation)) = (&self.http_cache, revalidation) {
+            resp = revalidation.finish(cache.as_ref(), resp);
+        }
+        Ok(resp)
     }
 
     async fn _send_request_with_json_output<O: DeserializeOwned>(
         &self,
-        req: RequestBuilder,
+        req: HttpRequest,
     ) -> Result<O> {
-        self._send_request(req, DingClient::_json_response_handler)
-            .await
+        decode(self._send_request(req).await?)
     }
 
-    async fn _send_request_without_output(&self, req: RequestBuilder) -> Result<()> {
-        self._send_request(req, DingClient::_empty_response_handler)
-            .await
+    async fn _send_request_without_output(&self, req: HttpRequest) -> Result<()> {
+        self._send_request(req).await?;
+        Ok(())
     }
 
-    async fn _load_all<O, P: IterableRequest, R: IterableResponse<O>, RFut>(
+    /// Load the page after `page`, see [`Page::fetch_next`].
+    pub(crate) async fn next_page<T: DeserializeOwned>(
+        &self,
+        page: &Page<T>,
+    ) -> Result<Option<Page<T>>> {
+        let Some(offset) = page.next_offset() else {
+            return Ok(None);
+        };
+        let req = next_page_request(self._request_builder(Method::Get, &page.endpoint)?, page);
+        self._load_page(req, &page.endpoint, offset).await.map(Some)
+    }
+
+    async fn _load_page<T: DeserializeOwned>(
+        &self,
+        req: HttpRequest,
+        endpoint: &str,
+        offset: u64,
+    ) -> Result<Page<T>> {
+        let page = self._send_request_with_json_output(req).await?;
+        Ok(Page {
+            offset,
+            endpoint: endpoint.to_string(),
+            ..page
+        })
+    }
+
+    async fn _load_all<O, P: IterableRequest, RFut>(
         &self,
         params: P,
+        cancel: &CancellationToken,
         call: impl Fn(P) -> RFut,
     ) -> Result<Vec<O>>
     where
-        RFut: Future<Output = Result<R>>,
+        RFut: Future<Output = Result<Page<O>>>,
     {
         let params = params.limit(None).offset(None);
-        let mut offset: u64 = 0;
         let mut results = vec![];
-        let mut resp = call(params.offset(Some(offset))).await?;
-        loop {
-            results.extend(resp.results());
+        let mut resp = cancel.run(call(params.offset(Some(0)))).await??;
 
-            if resp.next().is_none() {
-                break;
+        // The first page gives the page size and the total, so the remaining
+        // pages can be requested together and put back in order.
+        let page_size = resp.results.len() as u64;
+        if resp.has_next() && page_size > 0 && self.page_concurrency > 1 {
+            let params = params.limit(Some(page_size));
+            let offsets = (1..resp.count.div_ceil(page_size)).map(|page| page * page_size);
+            let mut pages = stream::iter(offsets)
+                .map(|offset| call(params.offset(Some(offset))))
+                .buffered(self.page_concurrency);
+            results.append(&mut resp.results);
+            while let Some(page) = cancel.run(pages.next()).await? {
+                resp = page?;
+                results.append(&mut resp.results);
+            }
+            // Bookmarks added meanwhile show up as a further page.
+            if !resp.has_next() {
+                return Ok(results);
             }
+            resp = cancel
+                .run(call(params.offset(Some(results.len() as u64))))
+                .await??;
+        }
 
-            offset += resp.results().len() as u64;
-            resp = call(params.offset(Some(offset))).await?;
+        loop {
+            let has_next = resp.has_next();
+            results.append(&mut resp.results);
+            if !has_next {
+                break;
+            }
+            resp = cancel
+                .run(call(params.offset(Some(results.len() as u64))))
+                .await??;
         }
         Ok(results)
     }
 }
+
+#[cfg(feature = "reqwest")]
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+fn default_transport(builder: &DingClientBuilder) -> Arc<dyn HttpTransport> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(proxy) = &builder.proxy {
+        // Fails only where `reqwest::Client::new` would panic as well.
+        let client = reqwest::Client::builder()
+            .proxy(proxy.clone())
+            .build()
+            .expect("failed to build the HTTP client");
+        return Arc::new(crate::transport::ReqwestTransport::new(client));
+    }
+    Arc::new(crate::transport::ReqwestTransport::default())
+}
+
+#[cfg(not(feature = "reqwest"))]
+fn default_transport(_builder: &DingClientBuilder) -> Arc<dyn HttpTransport> {
+    panic!("DingClientBuilder::transport must be set without the reqwest feature")
+}
+
+/// Whether a probe found its endpoint: a 404 means it does not exist.
+pub(crate) fn supported<T>(resp: Result<T>) -> Result<bool> {
+    match resp {
+        Ok(_) => Ok(true),
+        Err(err) if err.status() == Some(404) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Turn an error status into [`DingError::Status`].
+pub(crate) fn check_status(resp: HttpResponse) -> Result<HttpResponse> {
+    if resp.is_success() {
+        return Ok(resp);
+    }
+    Err(DingError::Status {
+        status: resp.status,
+        body: String::from_utf8_lossy(&resp.body).into_owned(),
+    })
+}
+
+pub(crate) fn decode<O: DeserializeOwned>(resp: HttpResponse) -> Result<O> {
+    #[cfg(feature = "tracing")]
+    crate::logging::response_body(&resp.body);
+    Ok(serde_json::from_slice(&resp.body)?)
+}
+
+/// Request for the page after `page`: the filters and position from its
+/// `next` link, sent to the client's own base URL in case the server sits
+/// behind a proxy and links to an address the client cannot use.
+pub(crate) fn next_page_request<T>(mut req: HttpRequest, page: &Page<T>) -> HttpRequest {
+    req.url
+        .set_query(page.next.as_ref().and_then(|x| x.query()));
+    req
+}
+
+pub(crate) fn page_query(
+    limit: Option<u64>,
+    offset: Option<u64>,
+) -> [(&'static str, Option<String>); 2] {
+    [
+        ("limit", limit.map(|x| x.to_string())),
+        ("offset", offset.map(|x| x.to_string())),
+    ]
+}
+
+pub(crate) fn bookmarks_query(params: BookmarksRequest) -> [(&'static str, Option<String>); 7] {
+    let [limit, offset] = page_query(params.limit, params.offset);
+    [
+        ("q", params.query),
+        limit,
+        offset,
+        (
+            "modified_since",
+            params
+                .modified_since
+                .map(|x| x.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ),
+        (
+            "added_since",
+            params
+                .added_since
+                .map(|x| x.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ),
+        ("sort", params.sort.map(|x| x.as_str().to_string())),
+        ("bundle", params.bundle.map(|x| x.to_string())),
+    ]
+}
@@ -0,0 +1,89 @@
+//! Sharing one response between identical GET requests that are in flight
+//! at the same time, e.g. a view and a background refresh loading the same
+//! page.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard};
+
+use futures::channel::oneshot;
+
+use crate::transport::{HttpResponse, TransportError};
+
+type Outcome = Result<HttpResponse, TransportError>;
+
+/// Requests being sent, keyed by URL, with the callers waiting for them.
+#[derive(Default)]
+pub(crate) struct InFlight {
+    waiting: Mutex<HashMap<String, Vec<oneshot::Sender<Outcome>>>>,
+}
+
+impl InFlight {
+    /// Run `send` unless a request for `key` is already in flight, in which
+    /// case wait for its outcome instead.
+    pub async fn run<F>(&self, key: String, send: impl FnOnce() -> F) -> Outcome
+    where
+        F: Future<Output = Outcome>,
+    {
+        let waiter = {
+            let mut waiting = self.lock();
+            match waiting.get_mut(&key) {
+                Some(waiters) => {
+                    let (sender, receiver) = oneshot::channel();
+                    waiters.push(sender);
+                    Some(receiver)
+                }
+                None => {
+                    waiting.insert(key.clone(), vec![]);
+                    None
+                }
+            }
+        };
+        if let Some(receiver) = waiter {
+            // The sender is dropped if the first caller gave up; send the
+            // request ourselves then.
+            return match receiver.await {
+                Ok(outcome) => outcome,
+                Err(oneshot::Canceled) => send().await,
+            };
+        }
+
+        let leader = Leader {
+            in_flight: self,
+            key: Some(key),
+        };
+        let outcome = send().await;
+        for waiter in leader.finish() {
+            let _ = waiter.send(outcome.clone());
+        }
+        outcome
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Vec<oneshot::Sender<Outcome>>>> {
+        self.waiting
+            .lock()
+            .expect("in-flight requests lock poisoned")
+    }
+}
+
+/// Removes the entry of the request being sent, also when its future is
+/// dropped halfway, so later callers never wait on it.
+struct Leader<'a> {
+    in_flight: &'a InFlight,
+    key: Option<String>,
+}
+
+impl Leader<'_> {
+    fn finish(mut self) -> Vec<oneshot::Sender<Outcome>> {
+        let key = self.key.take().expect("leader finished twice");
+        self.in_flight.lock().remove(&key).unwrap_or_default()
+    }
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.in_flight.lock().remove(&key);
+        }
+    }
+}
@@ -1,5 +1,9 @@
+use std::fmt;
+
 use thiserror::Error;
 
+use crate::transport::{TransportError, TransportErrorKind};
+
 #[derive(Debug, Error)]
 pub enum DingError {
     #[error(transparent)]
@@ -9,8 +13,83 @@ pub enum DingError {
     },
 
     #[error(transparent)]
-    Request {
+    Transport {
+        #[from]
+        source: TransportError,
+    },
+
+    /// The server answered with a status other than 2xx.
+    #[error("server answered with HTTP status {status}")]
+    Status { status: u16, body: String },
+
+    #[error("invalid request: {}", join(errors))]
+    InvalidRequest { errors: Vec<FieldError> },
+
+    /// The server is too old for `feature`, see
+    /// [`DingClient::capabilities`](crate::DingClient::capabilities).
+    #[error("the server does not support {feature}")]
+    Unsupported { feature: &'static str },
+
+    /// Stopped through a [`CancellationToken`](crate::cancel::CancellationToken).
+    #[error("cancelled")]
+    Cancelled,
+
+    #[error("invalid response body: {source}")]
+    Decode {
         #[from]
-        source: reqwest::Error,
+        source: serde_json::Error,
     },
 }
+
+/// A problem with one field of a request, found before sending it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+fn join(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl DingError {
+    /// Whether the server could not be reached at all, as opposed to
+    /// answering with an error.
+    pub fn is_connection_error(&self) -> bool {
+        match self {
+            DingError::Transport { source } => matches!(
+                source.kind(),
+                TransportErrorKind::Connect | TransportErrorKind::Timeout
+            ),
+            _ => false,
+        }
+    }
+
+    /// The HTTP status the server answered with, if it answered with an
+    /// error.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            DingError::Status { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
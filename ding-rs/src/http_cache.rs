@@ -0,0 +1,153 @@
+//! Conditional GET requests. Responses carrying an `ETag` or `Last-Modified`
+//! header are kept, and the next GET of the same URL asks the server whether
+//! they changed. A `304 Not Modified` answer is served from the cache.
+//!
+//! Entries are keyed by URL only, so a cache must not be shared between API
+//! tokens.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::transport::{HttpRequest, HttpResponse, MaybeSendSync, Method};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Only UTF-8 bodies are kept, which covers every API response.
+    pub body: String,
+}
+
+/// Storage for cached responses, set with
+/// [`DingClientBuilder::http_cache`](crate::DingClientBuilder::http_cache).
+pub trait HttpCache: MaybeSendSync {
+    fn get(&self, url: &str) -> Option<CachedResponse>;
+    fn put(&self, response: CachedResponse);
+}
+
+/// Keeps responses for the life of the client.
+#[derive(Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl HttpCache for MemoryCache {
+    fn get(&self, url: &str) -> Option<CachedResponse> {
+        let entries = self.entries.lock().expect("http cache lock poisoned");
+        entries.get(url).cloned()
+    }
+
+    fn put(&self, response: CachedResponse) {
+        let mut entries = self.entries.lock().expect("http cache lock poisoned");
+        entries.insert(response.url.clone(), response);
+    }
+}
+
+/// Keeps responses as one JSON file per URL in a directory, so they outlive
+/// the process. Unreadable entries count as misses and failed writes are
+/// ignored; the cache only ever saves requests.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DiskCache {
+    dir: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DiskCache {
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, url: &str) -> std::path::PathBuf {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        url.hash(&mut hasher);
+        self.dir.join(format!("{:016x}.json", hasher.finish()))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HttpCache for DiskCache {
+    fn get(&self, url: &str) -> Option<CachedResponse> {
+        let data = std::fs::read(self.path(url)).ok()?;
+        serde_json::from_slice::<CachedResponse>(&data)
+            .ok()
+            // Another URL with the same hash.
+            .filter(|x| x.url == url)
+    }
+
+    fn put(&self, response: CachedResponse) {
+        let Ok(data) = serde_json::to_vec(&response) else {
+            return;
+        };
+        if std::fs::create_dir_all(&self.dir).is_ok() {
+            let _ = std::fs::write(self.path(&response.url), data);
+        }
+    }
+}
+
+/// A GET request sent with the validators of its cached response, if any.
+pub(crate) struct Revalidation {
+    url: String,
+    cached: Option<CachedResponse>,
+}
+
+/// Make a GET request conditional on the cached response for its URL. Other
+/// requests are left alone and give no [`Revalidation`].
+pub(crate) fn conditional(
+    cache: &dyn HttpCache,
+    mut request: HttpRequest,
+) -> (HttpRequest, Option<Revalidation>) {
+    if request.method != Method::Get {
+        return (request, None);
+    }
+    let url = request.url.to_string();
+    let cached = cache.get(&url);
+    if let Some(cached) = &cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header("If-Modified-Since", last_modified);
+        }
+    }
+    (request, Some(Revalidation { url, cached }))
+}
+
+impl Revalidation {
+    /// Serve a `304 Not Modified` answer from the cache, and keep successful
+    /// answers that can be revalidated later.
+    pub fn finish(self, cache: &dyn HttpCache, resp: HttpResponse) -> HttpResponse {
+        if resp.status == 304 {
+            if let Some(cached) = self.cached {
+                return HttpResponse {
+                    status: 200,
+                    headers: resp.headers,
+                    body: cached.body.into_bytes(),
+                };
+            }
+        }
+        let etag = resp.header("ETag").map(str::to_string);
+        let last_modified = resp.header("Last-Modified").map(str::to_string);
+        if resp.is_success() && (etag.is_some() || last_modified.is_some()) {
+            if let Ok(body) = String::from_utf8(resp.body.clone()) {
+                cache.put(CachedResponse {
+                    url: self.url,
+                    etag,
+                    last_modified,
+                    body,
+                });
+            }
+        }
+        resp
+    }
+}
@@ -1,6 +1,25 @@
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod cancel;
 pub mod client;
+mod coalesce;
 pub mod errors;
+pub mod http_cache;
+#[cfg(feature = "tracing")]
+mod logging;
+mod rate_limit;
+pub mod retry;
+#[cfg(feature = "scrape")]
+pub mod scrape;
+pub mod tracking;
+pub mod transport;
 pub mod types;
+mod urls;
 
+#[cfg(feature = "blocking")]
+pub use blocking::DingBlockingClient;
+pub use cancel::CancellationToken;
 pub use client::*;
+pub use retry::RetryPolicy;
+pub use tracking::*;
 pub use types::*;
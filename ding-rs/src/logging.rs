@@ -0,0 +1,85 @@
+//! Request logging for the `tracing` feature. Every request is logged at
+//! info level once it completes; headers and bodies are logged at debug
+//! level with the API token redacted.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use url::Url;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+use crate::transport::{HttpRequest, Method, TransportError};
+
+pub(crate) struct RequestLog {
+    method: Method,
+    url: Url,
+    started: Instant,
+}
+
+impl RequestLog {
+    pub fn start(request: &HttpRequest) -> Self {
+        if tracing::enabled!(tracing::Level::DEBUG) {
+            let headers = request
+                .headers
+                .iter()
+                .map(|(name, value)| {
+                    let value = match name.eq_ignore_ascii_case("authorization") {
+                        true => "Token ***",
+                        false => value,
+                    };
+                    format!("{name}: {value}")
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let body = request
+                .body
+                .as_deref()
+                .map(String::from_utf8_lossy)
+                .unwrap_or_default();
+            tracing::debug!(
+                method = %request.method,
+                url = %request.url,
+                headers,
+                body = %body,
+                "sending request"
+            );
+        }
+        Self {
+            method: request.method,
+            url: request.url.clone(),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn finish(self, status: u16) {
+        tracing::info!(
+            method = %self.method,
+            path = self.path(),
+            status,
+            latency_ms = self.started.elapsed().as_millis() as u64,
+            "request"
+        );
+    }
+
+    pub fn fail(self, error: &TransportError) {
+        tracing::warn!(
+            method = %self.method,
+            path = self.path(),
+            latency_ms = self.started.elapsed().as_millis() as u64,
+            %error,
+            "request failed"
+        );
+    }
+
+    fn path(&self) -> String {
+        match self.url.query() {
+            Some(query) => format!("{}?{query}", self.url.path()),
+            None => self.url.path().to_string(),
+        }
+    }
+}
+
+pub(crate) fn response_body(body: &[u8]) {
+    tracing::debug!(body = %String::from_utf8_lossy(body), "response body");
+}
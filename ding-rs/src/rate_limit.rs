@@ -0,0 +1,115 @@
+//! Token bucket limiting how fast requests are sent.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Allows `rate` requests per second on average, with bursts of up to one
+/// second's worth.
+pub(crate) struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64) -> Self {
+        let burst = rate.max(1.0);
+        Self {
+            rate,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take a token, or return how long to wait until one is available.
+    fn take(&self) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().expect("rate limiter lock poisoned");
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.refilled = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return None;
+        }
+        Some(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+    }
+
+    /// Wait until a request may be sent.
+    pub async fn acquire(&self) {
+        while let Some(wait) = self.take() {
+            #[cfg(not(target_arch = "wasm32"))]
+            tokio::time::sleep(wait).await;
+            #[cfg(target_arch = "wasm32")]
+            gloo_timers::future::sleep(wait).await;
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    pub fn acquire_blocking(&self) {
+        while let Some(wait) = self.take() {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// A default bucket plus buckets for endpoints with their own limit.
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    default: Option<TokenBucket>,
+    /// Keyed by API path prefix, e.g. `api/bookmarks/check/`.
+    endpoints: BTreeMap<String, TokenBucket>,
+}
+
+impl RateLimiter {
+    /// A rate that is not positive removes the limit.
+    pub fn set_default(&mut self, rate: f64) {
+        self.default = (rate > 0.0).then(|| TokenBucket::new(rate));
+    }
+
+    /// A rate that is not positive exempts the endpoint from the default.
+    pub fn set_endpoint(&mut self, prefix: &str, rate: f64) {
+        let prefix = prefix.trim_start_matches('/').to_string();
+        self.endpoints.insert(prefix, TokenBucket::new(rate));
+    }
+
+    /// The bucket for `api_path`. The longest matching endpoint prefix wins
+    /// over the default.
+    fn bucket(&self, api_path: &str) -> Option<&TokenBucket> {
+        self.endpoints
+            .iter()
+            .rev()
+            .find(|(prefix, _)| api_path.starts_with(prefix.as_str()))
+            .map(|(_, bucket)| bucket)
+            .or(self.default.as_ref())
+            .filter(|x| x.rate > 0.0)
+    }
+
+    /// Wait until a request to `api_path` may be sent.
+    pub async fn acquire(&self, api_path: &str) {
+        if let Some(bucket) = self.bucket(api_path) {
+            bucket.acquire().await;
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    pub fn acquire_blocking(&self, api_path: &str) {
+        if let Some(bucket) = self.bucket(api_path) {
+            bucket.acquire_blocking();
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Sending requests again after transient failures, and giving up on slow
+//! ones.
+
+use std::future::Future;
+use std::time::Duration;
+
+use crate::transport::{HttpResponse, Method, TransportError, TransportErrorKind};
+
+/// When failed requests are sent again. Connection failures and `429 Too
+/// Many Requests` are retried for every method; timeouts and `502`, `503`
+/// and `504` only for methods other than POST, which may have taken effect.
+///
+/// The default retries nothing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Send a failed request up to `max_retries` more times.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// Wait before the first retry, doubling for each further one. A
+    /// `Retry-After` header given in seconds takes precedence.
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Never wait longer than this between attempts.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// How long to wait before retry number `retry`, counting from 1, of a
+    /// `method` request that ended with `outcome`, or `None` to give up.
+    pub(crate) fn delay(
+        &self,
+        method: Method,
+        outcome: &Result<HttpResponse, TransportError>,
+        retry: u32,
+    ) -> Option<Duration> {
+        if retry > self.max_retries {
+            return None;
+        }
+        let may_repeat = method != Method::Post;
+        let retry_after = match outcome {
+            Err(err) => match err.kind() {
+                TransportErrorKind::Connect => None,
+                _ if may_repeat => None,
+                _ => return None,
+            },
+            Ok(resp) => match resp.status {
+                429 => resp.header("retry-after"),
+                502..=504 if may_repeat => resp.header("retry-after"),
+                _ => return None,
+            },
+        };
+        let backoff = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(retry - 1));
+        let delay = retry_after
+            .and_then(|x| x.trim().parse().ok())
+            .map_or(backoff, Duration::from_secs);
+        Some(delay.min(self.max_delay))
+    }
+}
+
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::time::sleep(duration).await;
+    #[cfg(target_arch = "wasm32")]
+    gloo_timers::future::sleep(duration).await;
+}
+
+/// Run `send`, failing with a timeout error if it takes longer than
+/// `timeout`.
+pub(crate) async fn with_timeout<F>(
+    timeout: Option<Duration>,
+    send: F,
+) -> Result<HttpResponse, TransportError>
+where
+    F: Future<Output = Result<HttpResponse, TransportError>>,
+{
+    let Some(timeout) = timeout else {
+        return send.await;
+    };
+    let send = std::pin::pin!(send);
+    let timer = std::pin::pin!(sleep(timeout));
+    match futures::future::select(send, timer).await {
+        futures::future::Either::Left((resp, _)) => resp,
+        futures::future::Either::Right(_) => Err(TransportError::new(
+            TransportErrorKind::Timeout,
+            format!("no response within {}s", timeout.as_secs_f64()),
+        )),
+    }
+}
@@ -0,0 +1,194 @@
+//! Reading the title, description and canonical URL of a page on the client,
+//! for servers that have scraping disabled or are blocked by the site. Only
+//! the `<head>` of the page is looked at; there is no full HTML parser.
+
+use url::Url;
+
+use crate::types::BookmarkRequest;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PageMetadata {
+    /// `<title>`, or `og:title` if the page has none.
+    pub title: Option<String>,
+    /// The `description` meta tag, or `og:description`.
+    pub description: Option<String>,
+    /// `<link rel="canonical">`, resolved against the page URL.
+    pub canonical_url: Option<Url>,
+}
+
+impl PageMetadata {
+    /// Extract the metadata from the HTML of the page at `url`.
+    pub fn parse(html: &str, url: &Url) -> Self {
+        let lower = html.to_ascii_lowercase();
+        // The lowercase copy has the same byte offsets.
+        let head = &html[..lower.find("</head").unwrap_or(html.len())];
+        let lower = &lower[..head.len()];
+        let title = lower.find("<title").and_then(|start| {
+            let start = start + lower[start..].find('>')? + 1;
+            let end = start + lower[start..].find("</title")?;
+            Some(&head[start..end])
+        });
+        let mut page = Self {
+            title: title.and_then(text),
+            ..Default::default()
+        };
+        let (mut og_title, mut og_description) = (None, None);
+        for (name, attrs) in tags(head, lower) {
+            let attr = |key: &str| {
+                attrs
+                    .iter()
+                    .find(|(x, _)| x.eq_ignore_ascii_case(key))
+                    .map(|(_, value)| value.as_str())
+            };
+            match name.as_str() {
+                "meta" => {
+                    let key = attr("name").or(attr("property")).unwrap_or_default();
+                    let content = attr("content").and_then(text);
+                    match key.to_ascii_lowercase().as_str() {
+                        "description" => page.description = page.description.or(content),
+                        "og:description" => og_description = og_description.or(content),
+                        "og:title" => og_title = og_title.or(content),
+                        _ => {}
+                    }
+                }
+                "link" => {
+                    let canonical = attr("rel").is_some_and(|x| {
+                        x.split_whitespace()
+                            .any(|x| x.eq_ignore_ascii_case("canonical"))
+                    });
+                    if canonical && page.canonical_url.is_none() {
+                        page.canonical_url = attr("href")
+                            .and_then(|x| url.join(decode(x).trim()).ok())
+                            .filter(|x| matches!(x.scheme(), "http" | "https"));
+                    }
+                }
+                _ => {}
+            }
+        }
+        page.title = page.title.or(og_title);
+        page.description = page.description.or(og_description);
+        page
+    }
+}
+
+impl BookmarkRequest {
+    /// Fill the title and description from `page` where they are not set.
+    /// The URL is replaced by the canonical one only if it is on the same
+    /// site, so that a page cannot redirect the bookmark elsewhere.
+    pub fn prefill(mut self, page: &PageMetadata) -> Self {
+        self.title = self.title.or_else(|| page.title.clone());
+        self.description = self.description.or_else(|| page.description.clone());
+        if let (Some(url), Some(canonical)) = (&self.url, &page.canonical_url) {
+            if same_site(url, canonical) {
+                self.url = Some(canonical.clone());
+            }
+        }
+        self
+    }
+}
+
+fn same_site(a: &Url, b: &Url) -> bool {
+    let host = |x: &Url| {
+        let host = x.host_str().unwrap_or_default().to_ascii_lowercase();
+        host.strip_prefix("www.")
+            .map(str::to_string)
+            .unwrap_or(host)
+    };
+    host(a) == host(b)
+}
+
+/// `<meta>` and `<link>` tags with their attributes.
+fn tags<'a>(
+    html: &'a str,
+    lower: &'a str,
+) -> impl Iterator<Item = (String, Vec<(String, String)>)> + 'a {
+    lower.match_indices('<').filter_map(move |(start, _)| {
+        let name_end = start + 1 + lower[start + 1..].find(|x: char| !x.is_ascii_alphanumeric())?;
+        let name = &lower[start + 1..name_end];
+        if name != "meta" && name != "link" {
+            return None;
+        }
+        let end = name_end + lower[name_end..].find('>')?;
+        Some((name.to_string(), attributes(&html[name_end..end])))
+    })
+}
+
+/// `name="value"`, `name='value'` and `name=value` pairs.
+fn attributes(mut s: &str) -> Vec<(String, String)> {
+    let mut attrs = vec![];
+    loop {
+        s = s.trim_start_matches(|x: char| x.is_whitespace() || x == '/');
+        let name_end = s
+            .find(|x: char| x.is_whitespace() || x == '=' || x == '/')
+            .unwrap_or(s.len());
+        if name_end == 0 {
+            return attrs;
+        }
+        let name = s[..name_end].to_string();
+        s = s[name_end..].trim_start();
+        let Some(rest) = s.strip_prefix('=') else {
+            attrs.push((name, String::new()));
+            continue;
+        };
+        let rest = rest.trim_start();
+        let (value, rest) = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => match rest[1..].find(quote) {
+                Some(end) => (&rest[1..end + 1], &rest[end + 2..]),
+                None => (&rest[1..], ""),
+            },
+            _ => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        attrs.push((name, value.to_string()));
+        s = rest;
+    }
+}
+
+/// Decoded text on one line, or `None` if blank.
+fn text(s: &str) -> Option<String> {
+    let text = decode(s).split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Decode the common named character references and numeric ones.
+fn decode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let name = &rest[1..end];
+            let char = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => match name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => name
+                        .strip_prefix('#')
+                        .and_then(|x| x.parse().ok())
+                        .and_then(char::from_u32),
+                },
+            };
+            Some((char?, end))
+        });
+        match decoded {
+            Some((char, end)) => {
+                out.push(char);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
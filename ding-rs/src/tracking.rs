@@ -0,0 +1,149 @@
+use url::Url;
+
+/// Query parameters dropped by [`TrackingParams::default`]. A trailing `*`
+/// matches any parameter starting with the prefix.
+pub const DEFAULT_TRACKING_PARAMS: &[&str] = &[
+    "utm_*",
+    "fbclid",
+    "gclid",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+    "rb_clickid",
+    "s_cid",
+    "ref_src",
+];
+
+#[derive(Clone, Debug)]
+pub struct TrackingParams {
+    /// Patterns of parameters to remove.
+    pub deny: Vec<String>,
+    /// Patterns of parameters to keep even if they match `deny`.
+    pub allow: Vec<String>,
+}
+
+impl Default for TrackingParams {
+    fn default() -> Self {
+        Self {
+            deny: DEFAULT_TRACKING_PARAMS
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+            allow: vec![],
+        }
+    }
+}
+
+impl TrackingParams {
+    pub fn is_tracking(&self, name: &str) -> bool {
+        let matches = |pattern: &String| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        };
+        self.deny.iter().any(matches) && !self.allow.iter().any(matches)
+    }
+
+    pub fn strip(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        if url.query().is_none() {
+            return url;
+        }
+        let pairs = url
+            .query_pairs()
+            .filter(|(k, _)| !self.is_tracking(k))
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect::<Vec<_>>();
+        if pairs.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+        url
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(url: &str) -> String {
+        TrackingParams::default()
+            .strip(&Url::parse(url).unwrap())
+            .to_string()
+    }
+
+    #[test]
+    fn removes_tracking_params() {
+        let cases = [
+            (
+                "https://example.com/a?utm_source=x&utm_medium=y",
+                "https://example.com/a",
+            ),
+            ("https://example.com/?fbclid=abc", "https://example.com/"),
+            (
+                "https://example.com/?gclid=1&msclkid=2&_hsenc=3",
+                "https://example.com/",
+            ),
+        ];
+        for (url, stripped) in cases {
+            assert_eq!(strip(url), stripped, "{url}");
+        }
+    }
+
+    #[test]
+    fn keeps_other_params_in_order() {
+        assert_eq!(
+            strip("https://example.com/search?q=rust&utm_campaign=x&page=2"),
+            "https://example.com/search?q=rust&page=2"
+        );
+        // Only whole names or prefixes ending in `*` match.
+        assert_eq!(
+            strip("https://example.com/?utm=1&fbclid_x=2&ref=3"),
+            "https://example.com/?utm=1&fbclid_x=2&ref=3"
+        );
+    }
+
+    #[test]
+    fn keeps_the_fragment() {
+        assert_eq!(
+            strip("https://example.com/page?utm_source=x#section"),
+            "https://example.com/page#section"
+        );
+        assert_eq!(
+            strip("https://example.com/page?id=1&fbclid=2#top"),
+            "https://example.com/page?id=1#top"
+        );
+    }
+
+    #[test]
+    fn leaves_urls_without_query_alone() {
+        assert_eq!(strip("https://example.com/a#b"), "https://example.com/a#b");
+    }
+
+    #[test]
+    fn allow_overrides_deny() {
+        let params = TrackingParams {
+            allow: vec!["utm_source".to_string()],
+            ..Default::default()
+        };
+        let url = Url::parse("https://example.com/?utm_source=feed&utm_medium=rss").unwrap();
+        assert_eq!(
+            params.strip(&url).as_str(),
+            "https://example.com/?utm_source=feed"
+        );
+        assert!(params.is_tracking("utm_medium"));
+        assert!(!params.is_tracking("utm_source"));
+    }
+}
@@ -0,0 +1,277 @@
+//! The HTTP layer under [`DingClient`]. The `reqwest` feature, on by default,
+//! provides [`ReqwestTransport`], which works natively and, on
+//! `wasm32-unknown-unknown`, through the browser's `fetch`. Other HTTP
+//! stacks plug in by implementing [`HttpTransport`] and passing it to
+//! [`DingClientBuilder::transport`].
+//!
+//! [`DingClient`]: crate::DingClient
+//! [`DingClientBuilder::transport`]: crate::DingClientBuilder::transport
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use serde::Serialize;
+use thiserror::Error;
+use url::Url;
+
+/// The future returned by [`HttpTransport::send`]. It is `Send` except on
+/// wasm32, where browser futures never are.
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+#[cfg(target_arch = "wasm32")]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// `Send + Sync`, except on wasm32.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSendSync: Send + Sync {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + Sync + ?Sized> MaybeSendSync for T {}
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSendSync {}
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> MaybeSendSync for T {}
+
+/// Sends requests for a client. Implementations only move bytes: auth,
+/// status handling, rate limiting and decoding are done by the client.
+pub trait HttpTransport: MaybeSendSync {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Patch => "PATCH",
+            Method::Delete => "DELETE",
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl HttpRequest {
+    pub fn new(method: Method, url: Url) -> Self {
+        Self {
+            method,
+            url,
+            headers: vec![],
+            body: None,
+        }
+    }
+
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Append query parameters, leaving out the ones without a value.
+    pub fn query(mut self, params: &[(&str, Option<String>)]) -> Self {
+        let params = params
+            .iter()
+            .filter_map(|(name, value)| Some((name, value.as_ref()?)))
+            .collect::<Vec<_>>();
+        if !params.is_empty() {
+            self.url.query_pairs_mut().extend_pairs(params);
+        }
+        self
+    }
+
+    /// Send one file as a `multipart/form-data` body, in the field `field`.
+    pub fn multipart_file(
+        self,
+        field: &str,
+        file_name: &str,
+        content_type: &str,
+        data: &[u8],
+    ) -> Self {
+        use std::hash::{BuildHasher, Hasher};
+
+        // RandomState is seeded randomly, so the boundary is unlikely to
+        // appear in the file.
+        let random = std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish();
+        let boundary = format!("ding-{random:016x}");
+        let file_name = file_name.replace(['"', '\r', '\n'], "_");
+        let mut body = format!(
+            "--{boundary}\r\n\
+             Content-Disposition: form-data; name=\"{field}\"; filename=\"{file_name}\"\r\n\
+             Content-Type: {content_type}\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+        Self {
+            body: Some(body),
+            ..self.header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+        }
+    }
+
+    pub fn json<T: Serialize>(self, body: &T) -> Self {
+        let body = serde_json::to_vec(body).expect("request types always serialize");
+        Self {
+            body: Some(body),
+            ..self.header("Content-Type", "application/json")
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// The first header called `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransportErrorKind {
+    /// The server could not be reached.
+    Connect,
+    Timeout,
+    Other,
+}
+
+/// The request did not get a response. Cheap to clone, so one failure can be
+/// handed to every caller waiting on the same request.
+#[derive(Clone, Debug, Error)]
+#[error("{source}")]
+pub struct TransportError {
+    kind: TransportErrorKind,
+    source: Arc<dyn std::error::Error + Send + Sync>,
+}
+
+impl TransportError {
+    pub fn new(
+        kind: TransportErrorKind,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self {
+            kind,
+            source: Arc::from(source.into()),
+        }
+    }
+
+    pub fn kind(&self) -> TransportErrorKind {
+        self.kind
+    }
+}
+
+/// The default transport, built on a [`reqwest::Client`].
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>> {
+        Box::pin(async move {
+            let mut req = self.client.request(request.method.into(), request.url);
+            for (name, value) in request.headers {
+                req = req.header(name, value);
+            }
+            if let Some(body) = request.body {
+                req = req.body(body);
+            }
+            let resp = req.send().await?;
+            let status = resp.status().as_u16();
+            let headers = response_headers(resp.headers());
+            let body = resp.bytes().await?.to_vec();
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+#[cfg(feature = "reqwest")]
+pub(crate) fn response_headers(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+#[cfg(feature = "reqwest")]
+impl From<Method> for reqwest::Method {
+    fn from(method: Method) -> Self {
+        match method {
+            Method::Get => reqwest::Method::GET,
+            Method::Post => reqwest::Method::POST,
+            Method::Put => reqwest::Method::PUT,
+            Method::Patch => reqwest::Method::PATCH,
+            Method::Delete => reqwest::Method::DELETE,
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for TransportError {
+    fn from(err: reqwest::Error) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let connect = err.is_connect();
+        // reqwest cannot tell on wasm32; fetch only reports a TypeError.
+        #[cfg(target_arch = "wasm32")]
+        let connect = false;
+        let kind = if err.is_timeout() {
+            TransportErrorKind::Timeout
+        } else if connect {
+            TransportErrorKind::Connect
+        } else {
+            TransportErrorKind::Other
+        };
+        Self::new(kind, err)
+    }
+}
@@ -1,27 +1,175 @@
+use std::collections::BTreeMap;
+
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use reqwest::Url;
 use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use url::Url;
+
+use crate::errors::{DingError, FieldError};
+use crate::tracking::TrackingParams;
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct TagRequest {
     pub name: String,
 }
 
-#[derive(Clone, Debug, Default)]
+impl TagRequest {
+    pub fn validate(&self) -> Result<(), DingError> {
+        validate_tag_name("name", &self.name)
+            .map_err(|err| DingError::InvalidRequest { errors: vec![err] })
+    }
+}
+
+/// Tags are separated by whitespace on the server, so names cannot contain
+/// any.
+fn validate_tag_name(field: &'static str, name: &str) -> Result<(), FieldError> {
+    if name.is_empty() {
+        return Err(FieldError::new(field, "empty tag name"));
+    }
+    if name.chars().any(char::is_whitespace) {
+        return Err(FieldError::new(
+            field,
+            format!("tag `{name}` contains whitespace"),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct TagsRequest {
     pub limit: Option<u64>,
     pub offset: Option<u64>,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BundlesRequest {
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct BookmarksRequest {
     pub query: Option<String>,
     pub limit: Option<u64>,
     pub offset: Option<u64>,
+    /// Only bookmarks modified after this time. Older servers ignore it.
+    pub modified_since: Option<DateTime<Utc>>,
+    /// Only bookmarks added after this time. Older servers ignore it.
+    pub added_since: Option<DateTime<Utc>>,
+    pub sort: Option<BookmarkSort>,
+    /// Only bookmarks matching this bundle. Servers without bundles ignore it.
+    pub bundle: Option<u64>,
+}
+
+/// Sort orders supported by the server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BookmarkSort {
+    AddedAsc,
+    AddedDesc,
+    TitleAsc,
+    TitleDesc,
+}
+
+impl BookmarkSort {
+    pub const ALL: [BookmarkSort; 4] = [
+        BookmarkSort::AddedAsc,
+        BookmarkSort::AddedDesc,
+        BookmarkSort::TitleAsc,
+        BookmarkSort::TitleDesc,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookmarkSort::AddedAsc => "added_asc",
+            BookmarkSort::AddedDesc => "added_desc",
+            BookmarkSort::TitleAsc => "title_asc",
+            BookmarkSort::TitleDesc => "title_desc",
+        }
+    }
+
+    /// Sort `bookmarks` locally the way the server would. Titles fall back to
+    /// the website title and then the URL.
+    pub fn sort(&self, bookmarks: &mut [Bookmark]) {
+        let title = |x: &Bookmark| {
+            x.title
+                .as_ref()
+                .or(x.website_title.as_ref())
+                .map_or(x.url.to_string(), |x| x.to_lowercase())
+        };
+        match self {
+            BookmarkSort::AddedAsc => bookmarks.sort_by_key(|x| x.date_added),
+            BookmarkSort::AddedDesc => bookmarks.sort_by_key(|x| std::cmp::Reverse(x.date_added)),
+            BookmarkSort::TitleAsc => bookmarks.sort_by_cached_key(title),
+            BookmarkSort::TitleDesc => {
+                bookmarks.sort_by_cached_key(|x| std::cmp::Reverse(title(x)))
+            }
+        }
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Default)]
+impl std::str::FromStr for BookmarkSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BookmarkSort::ALL
+            .into_iter()
+            .find(|x| x.as_str() == s)
+            .ok_or_else(|| format!("unknown sort order `{s}`"))
+    }
+}
+
+/// The RSS feeds linkding serves. They are read with the user's feed token
+/// from the server's integration settings instead of the API token; the API
+/// does not expose it. `Public` lists every user's public bookmarks and needs
+/// no token at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedKind {
+    All,
+    Unread,
+    Shared,
+    Public,
+}
+
+impl FeedKind {
+    pub const ALL: [FeedKind; 4] = [
+        FeedKind::All,
+        FeedKind::Unread,
+        FeedKind::Shared,
+        FeedKind::Public,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeedKind::All => "all",
+            FeedKind::Unread => "unread",
+            FeedKind::Shared => "shared",
+            FeedKind::Public => "public",
+        }
+    }
+
+    /// Path of the feed below the server URL.
+    pub(crate) fn path(&self, feed_token: &str) -> String {
+        match self {
+            FeedKind::Public => "feeds/shared".to_string(),
+            kind => format!("feeds/{feed_token}/{}", kind.as_str()),
+        }
+    }
+}
+
+impl std::str::FromStr for FeedKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FeedKind::ALL
+            .into_iter()
+            .find(|x| x.as_str() == s)
+            .ok_or_else(|| format!("unknown feed `{s}`"))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub struct BookmarkRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<Url>,
@@ -41,6 +189,26 @@ pub struct BookmarkRequest {
     pub tag_names: Option<Vec<String>>,
 }
 
+/// The fields of a bookmark that changed, as sent in a PATCH request.
+pub type BookmarkPatch = BookmarkRequest;
+
+impl From<&Bookmark> for BookmarkRequest {
+    /// Every editable field of `bookmark`, e.g. to recreate it or to start an
+    /// edit from.
+    fn from(bookmark: &Bookmark) -> Self {
+        Self {
+            url: Some(bookmark.url.clone()),
+            title: bookmark.title.clone(),
+            description: bookmark.description.clone(),
+            notes: bookmark.notes.clone(),
+            is_archived: Some(bookmark.is_archived),
+            unread: Some(bookmark.unread),
+            shared: Some(bookmark.shared),
+            tag_names: Some(bookmark.tag_names.clone()),
+        }
+    }
+}
+
 impl BookmarkRequest {
     pub fn new(url: Url) -> Self {
         Self {
@@ -48,56 +216,286 @@ impl BookmarkRequest {
             ..Default::default()
         }
     }
-}
 
-#[derive(Clone, Debug, Deserialize)]
-pub struct TagsResponse {
-    pub count: u64,
-    pub next: Option<Url>,
-    pub previous: Option<Url>,
-    pub results: Vec<Tag>,
+    /// Whether no field is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the fields that are set. With `require_url`, as for creating or
+    /// replacing a bookmark, a missing URL is an error too.
+    pub fn validate(&self, require_url: bool) -> Result<(), DingError> {
+        let mut errors = vec![];
+        match &self.url {
+            Some(url) if !matches!(url.scheme(), "http" | "https") => errors.push(FieldError::new(
+                "url",
+                format!("unsupported scheme `{}`", url.scheme()),
+            )),
+            None if require_url => errors.push(FieldError::new("url", "is required")),
+            _ => {}
+        }
+        for tag in self.tag_names.iter().flatten() {
+            if let Err(err) = validate_tag_name("tag_names", tag) {
+                errors.push(err);
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(DingError::InvalidRequest { errors }),
+        }
+    }
+
+    /// Copy of the request with tracking parameters removed from the URL.
+    pub fn normalized(&self) -> Self {
+        self.normalized_with(&TrackingParams::default())
+    }
+
+    pub fn normalized_with(&self, params: &TrackingParams) -> Self {
+        Self {
+            url: self.url.as_ref().map(|x| params.strip(x)),
+            ..self.clone()
+        }
+    }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+pub type TagsResponse = Page<Tag>;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Tag {
     pub id: u64,
     pub name: String,
     pub date_added: DateTime<Utc>,
+    #[serde(flatten)]
+    pub extra: UnknownFields,
 }
 
-#[derive(Clone, Debug, Deserialize)]
-pub struct BookmarksResponse {
-    pub count: u64,
-    pub next: Option<Url>,
-    pub previous: Option<Url>,
-    pub results: Vec<Bookmark>,
-}
+/// Fields a server sent that this crate does not know yet. They are kept so
+/// that saving and loading a value again does not lose them.
+pub type UnknownFields = BTreeMap<String, serde_json::Value>;
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+pub type BookmarksResponse = Page<Bookmark>;
+
+/// Fields added in later linkding releases may be missing from older
+/// servers and default to empty.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Bookmark {
     pub id: u64,
     pub url: Url,
-    #[serde(deserialize_with = "empty_str")]
+    #[serde(default, deserialize_with = "empty_str")]
     pub title: Option<String>,
-    #[serde(deserialize_with = "empty_str")]
+    #[serde(default, deserialize_with = "empty_str")]
     pub description: Option<String>,
-    #[serde(deserialize_with = "empty_str")]
+    #[serde(default, deserialize_with = "empty_str")]
     pub notes: Option<String>,
+    #[serde(default, deserialize_with = "empty_str")]
     pub website_title: Option<String>,
+    #[serde(default, deserialize_with = "empty_str")]
     pub website_description: Option<String>,
-    #[serde(deserialize_with = "empty_url")]
+    #[serde(default, deserialize_with = "empty_url")]
     pub web_archive_snapshot_url: Option<Url>,
+    #[serde(default, deserialize_with = "empty_url")]
     pub favicon_url: Option<Url>,
+    #[serde(default, deserialize_with = "empty_url")]
     pub preview_image_url: Option<Url>,
+    #[serde(default)]
     pub is_archived: bool,
+    #[serde(default)]
     pub unread: bool,
+    #[serde(default)]
     pub shared: bool,
+    #[serde(default)]
     pub tag_names: Vec<String>,
     pub date_added: DateTime<Utc>,
     pub date_modified: DateTime<Utc>,
+    #[serde(flatten)]
+    pub extra: UnknownFields,
+}
+
+/// A file stored with a bookmark on the server, such as an HTML snapshot or
+/// an upload.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Asset {
+    pub id: u64,
+    #[serde(rename = "bookmark")]
+    pub bookmark_id: u64,
+    #[serde(default)]
+    pub asset_type: String,
+    #[serde(default)]
+    pub content_type: String,
+    #[serde(default)]
+    pub display_name: String,
+    #[serde(default)]
+    pub file_size: Option<u64>,
+    #[serde(default)]
+    pub status: String,
+    pub date_created: DateTime<Utc>,
+    #[serde(flatten)]
+    pub extra: UnknownFields,
+}
+
+pub type BundlesResponse = Page<Bundle>;
+
+/// A named saved search. Tag lists are space separated, as on the server.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Bundle {
+    pub id: u64,
+    pub name: String,
+    pub search: String,
+    pub any_tags: String,
+    pub all_tags: String,
+    pub excluded_tags: String,
+    pub order: u64,
+    pub date_created: DateTime<Utc>,
+    pub date_modified: DateTime<Utc>,
+    #[serde(flatten)]
+    pub extra: UnknownFields,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BundleRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub any_tags: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all_tags: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluded_tags: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CheckResponse {
+    /// The existing bookmark for the URL, if there is one.
+    pub bookmark: Option<Bookmark>,
+    pub metadata: WebsiteMetadata,
+    /// Tags the server's auto-tagging rules would add.
+    #[serde(default)]
+    pub auto_tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WebsiteMetadata {
+    pub url: Url,
+    #[serde(default, deserialize_with = "empty_str")]
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "empty_str")]
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "empty_url")]
+    pub preview_image: Option<Url>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+impl Bookmark {
+    /// The bookmark's tags plus those of `tags` it does not have yet.
+    pub fn tags_with(&self, tags: &[&str]) -> Vec<String> {
+        let mut tag_names = self.tag_names.clone();
+        for tag in tags {
+            if !tag_names.iter().any(|x| x == tag) {
+                tag_names.push(tag.to_string());
+            }
+        }
+        tag_names
+    }
+
+    /// The bookmark's tags except those in `tags`.
+    pub fn tags_without(&self, tags: &[&str]) -> Vec<String> {
+        let mut tag_names = self.tag_names.clone();
+        tag_names.retain(|x| !tags.contains(&x.as_str()));
+        tag_names
+    }
+
+    /// The changes that fold `request` into this bookmark: its tags are added
+    /// to the current ones, its notes are appended to the current notes and
+    /// its other fields replace the current values. The URL is kept.
+    pub fn merge(&self, request: &BookmarkRequest) -> BookmarkPatch {
+        let tags = request
+            .tag_names
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let notes = match (&self.notes, &request.notes) {
+            (Some(current), Some(new)) if !current.contains(new.as_str()) => {
+                Some(format!("{current}\n\n{new}"))
+            }
+            (Some(current), Some(_)) => Some(current.clone()),
+            (None, new) => new.clone(),
+            (Some(_), None) => None,
+        };
+        self.diff(&BookmarkRequest {
+            url: None,
+            notes,
+            tag_names: Some(self.tags_with(&tags)),
+            ..request.clone()
+        })
+    }
+
+    /// The fields of `request` that would change this bookmark. Empty strings
+    /// and missing texts are the same, and tag order is ignored.
+    pub fn diff(&self, request: &BookmarkRequest) -> BookmarkPatch {
+        fn text(current: &Option<String>, new: &Option<String>) -> Option<String> {
+            let new = new.as_ref()?;
+            (current.as_deref().unwrap_or_default() != new).then(|| new.clone())
+        }
+        fn flag(current: bool, new: Option<bool>) -> Option<bool> {
+            new.filter(|x| *x != current)
+        }
+        let tag_names = request.tag_names.as_ref().filter(|tags| {
+            let mut new = tags.to_vec();
+            let mut current = self.tag_names.clone();
+            new.sort();
+            new.dedup();
+            current.sort();
+            current.dedup();
+            new != current
+        });
+        BookmarkPatch {
+            url: request.url.clone().filter(|x| *x != self.url),
+            title: text(&self.title, &request.title),
+            description: text(&self.description, &request.description),
+            notes: text(&self.notes, &request.notes),
+            is_archived: flag(self.is_archived, request.is_archived),
+            unread: flag(self.unread, request.unread),
+            shared: flag(self.shared, request.shared),
+            tag_names: tag_names.cloned(),
+        }
+    }
+}
+
+/// What [`DingClient::upsert_bookmark`] did.
+///
+/// [`DingClient::upsert_bookmark`]: crate::DingClient::upsert_bookmark
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Upserted {
+    Created(Bookmark),
+    /// The URL was bookmarked already, and the request was merged into it.
+    Updated(Bookmark),
+}
+
+impl Upserted {
+    pub fn bookmark(&self) -> &Bookmark {
+        match self {
+            Upserted::Created(bookmark) | Upserted::Updated(bookmark) => bookmark,
+        }
+    }
+
+    pub fn into_bookmark(self) -> Bookmark {
+        match self {
+            Upserted::Created(bookmark) | Upserted::Updated(bookmark) => bookmark,
+        }
+    }
+
+    pub fn is_created(&self) -> bool {
+        matches!(self, Upserted::Created(_))
+    }
+}
+
+/// Older servers send fewer preferences; missing ones are empty or false.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
 pub struct UserProfile {
     pub theme: String,
     pub bookmark_date_display: String,
@@ -110,38 +508,57 @@ pub struct UserProfile {
     pub display_url: bool,
     pub permanent_notes: bool,
     pub search_preferences: SearchPreferences,
+    #[serde(flatten)]
+    pub extra: UnknownFields,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
 pub struct SearchPreferences {
     pub sort: String,
     pub shared: String,
     pub unread: String,
 }
 
+/// The server sends empty strings for unset text fields. Reads owned
+/// strings, so escaped values and non-borrowing readers work.
 fn empty_str<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s: String = Deserialize::deserialize(deserializer)?;
-    if s.is_empty() {
-        Ok(None)
-    } else {
-        Ok(Some(s))
-    }
+    let s: Option<String> = Deserialize::deserialize(deserializer)?;
+    Ok(s.filter(|x| !x.is_empty()))
 }
 
+/// Like [`empty_str`], for URL fields.
 fn empty_url<'de, D>(deserializer: D) -> Result<Option<Url>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s: &str = Deserialize::deserialize(deserializer)?;
-    if s.is_empty() {
-        Ok(None)
-    } else {
-        let url = Url::parse(s).map_err(D::Error::custom)?;
-        Ok(Some(url))
-    }
+    empty_str(deserializer)?
+        .map(|s| Url::parse(&s).map_err(D::Error::custom))
+        .transpose()
+}
+
+/// What a server supports, found by [`DingClient::capabilities`].
+///
+/// [`DingClient::capabilities`]: crate::DingClient::capabilities
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Version from the server's health check, if it is reachable.
+    pub version: Option<String>,
+    /// The bundles API and the `bundle` bookmark filter.
+    pub bundles: bool,
+    /// The `modified_since` bookmark filter. Always reported on a server
+    /// without bookmarks, where it cannot be told apart.
+    pub modified_since: bool,
+    /// Bookmark assets. Never reported on a server without bookmarks.
+    pub assets: bool,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Health {
+    pub version: Option<String>,
 }
 
 pub trait IterableRequest {
@@ -165,45 +582,430 @@ impl IterableRequest for TagsRequest {
     }
 }
 
+impl IterableRequest for BundlesRequest {
+    fn limit(&self, limit: Option<u64>) -> BundlesRequest {
+        BundlesRequest {
+            offset: self.offset,
+            limit,
+        }
+    }
+
+    fn offset(&self, offset: Option<u64>) -> BundlesRequest {
+        BundlesRequest {
+            offset,
+            limit: self.limit,
+        }
+    }
+}
+
 impl IterableRequest for BookmarksRequest {
     fn limit(&self, limit: Option<u64>) -> BookmarksRequest {
         BookmarksRequest {
-            query: self.query.clone(),
-            offset: self.offset,
             limit,
+            ..self.clone()
         }
     }
 
     fn offset(&self, offset: Option<u64>) -> BookmarksRequest {
         BookmarksRequest {
-            query: self.query.clone(),
             offset,
-            limit: self.limit,
+            ..self.clone()
         }
     }
 }
 
-pub trait IterableResponse<T> {
-    fn next(&self) -> Option<Url>;
-    fn results(&self) -> Vec<T>;
+/// One page of a list endpoint.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Page<T> {
+    /// Number of items over all pages, as reported by the server.
+    pub count: u64,
+    pub next: Option<Url>,
+    pub previous: Option<Url>,
+    pub results: Vec<T>,
+    /// Position of the first item of this page among all of them. Not sent
+    /// by the server but kept when a page is saved and loaded again.
+    #[serde(default)]
+    pub offset: u64,
+    /// API path the page was loaded from, e.g. `api/bookmarks/`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub(crate) endpoint: String,
+}
+
+impl<T> Page<T> {
+    pub fn has_next(&self) -> bool {
+        self.next.is_some()
+    }
+
+    /// Share of all items loaded up to the end of this page, from 0 to 1.
+    pub fn progress(&self) -> f64 {
+        if self.count == 0 {
+            return 1.0;
+        }
+        let loaded = self.offset + self.results.len() as u64;
+        (loaded as f64 / self.count as f64).min(1.0)
+    }
+
+    /// Offset of the next page, taken from the `next` link.
+    pub(crate) fn next_offset(&self) -> Option<u64> {
+        let next = self.next.as_ref()?;
+        let offset = next
+            .query_pairs()
+            .find(|(name, _)| name == "offset")
+            .and_then(|(_, value)| value.parse().ok());
+        Some(offset.unwrap_or(self.offset + self.results.len() as u64))
+    }
 }
 
-impl IterableResponse<Tag> for TagsResponse {
-    fn next(&self) -> Option<Url> {
-        self.next.clone()
+impl<T: serde::de::DeserializeOwned> Page<T> {
+    /// Load the page after this one from the same endpoint, with the same
+    /// filters, or `None` on the last page.
+    pub async fn fetch_next(
+        &self,
+        client: &crate::DingClient,
+    ) -> Result<Option<Page<T>>, DingError> {
+        client.next_page(self).await
     }
 
-    fn results(&self) -> Vec<Tag> {
-        self.results.clone()
+    #[cfg(feature = "blocking")]
+    pub fn fetch_next_blocking(
+        &self,
+        client: &crate::DingBlockingClient,
+    ) -> Result<Option<Page<T>>, DingError> {
+        client.next_page(self)
     }
 }
 
-impl IterableResponse<Bookmark> for BookmarksResponse {
-    fn next(&self) -> Option<Url> {
-        self.next.clone()
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn bookmark_json() -> Value {
+        json!({
+            "id": 1,
+            "url": "https://example.com/",
+            "title": "Example",
+            "description": "",
+            "notes": "",
+            "website_title": "Example Domain",
+            "website_description": "",
+            "web_archive_snapshot_url": "",
+            "favicon_url": "https://example.com/favicon.ico",
+            "preview_image_url": "",
+            "is_archived": false,
+            "unread": false,
+            "shared": false,
+            "tag_names": [],
+            "date_added": "2024-01-01T00:00:00Z",
+            "date_modified": "2024-01-01T00:00:00Z",
+        })
+    }
+
+    fn with(field: &str, value: Value) -> Value {
+        let mut json = bookmark_json();
+        json[field] = value;
+        json
+    }
+
+    #[test]
+    fn empty_strings_are_none() {
+        let bookmark: Bookmark = serde_json::from_value(bookmark_json()).unwrap();
+        assert_eq!(bookmark.description, None);
+        assert_eq!(bookmark.notes, None);
+        assert_eq!(bookmark.website_description, None);
+        assert_eq!(bookmark.web_archive_snapshot_url, None);
+        assert_eq!(bookmark.preview_image_url, None);
+        assert_eq!(bookmark.website_title.as_deref(), Some("Example Domain"));
+        assert_eq!(
+            bookmark.favicon_url.map(String::from).as_deref(),
+            Some("https://example.com/favicon.ico")
+        );
+    }
+
+    #[test]
+    fn empty_website_fields_are_none() {
+        for field in ["website_title", "favicon_url"] {
+            let bookmark: Bookmark = serde_json::from_value(with(field, json!(""))).unwrap();
+            assert_eq!(bookmark.website_title.is_none(), field == "website_title");
+            assert_eq!(bookmark.favicon_url.is_none(), field == "favicon_url");
+        }
+    }
+
+    #[test]
+    fn nulls_are_none() {
+        for field in ["title", "website_title", "favicon_url", "preview_image_url"] {
+            let bookmark: Bookmark = serde_json::from_value(with(field, Value::Null)).unwrap();
+            let json = serde_json::to_value(&bookmark).unwrap();
+            assert_eq!(json[field], Value::Null, "{field}");
+        }
+    }
+
+    #[test]
+    fn escaped_strings() {
+        let data = r#"{"id": 1, "url": "https:\/\/example.com\/", "title": "A \"quoted\" title",
+            "description": "", "notes": "line\nbreak", "website_title": null,
+            "website_description": null, "web_archive_snapshot_url": "https:\/\/web.archive.org\/x",
+            "favicon_url": "https:\/\/example.com\/favicon.ico", "preview_image_url": null,
+            "is_archived": false, "unread": false, "shared": false, "tag_names": [],
+            "date_added": "2024-01-01T00:00:00Z", "date_modified": "2024-01-01T00:00:00Z"}"#;
+        let bookmark: Bookmark = serde_json::from_str(data).unwrap();
+        assert_eq!(bookmark.title.as_deref(), Some("A \"quoted\" title"));
+        assert_eq!(bookmark.notes.as_deref(), Some("line\nbreak"));
+        assert_eq!(
+            bookmark
+                .web_archive_snapshot_url
+                .map(String::from)
+                .as_deref(),
+            Some("https://web.archive.org/x")
+        );
+    }
+
+    #[test]
+    fn from_reader() {
+        let data = serde_json::to_vec(&bookmark_json()).unwrap();
+        let bookmark: Bookmark = serde_json::from_reader(data.as_slice()).unwrap();
+        assert_eq!(bookmark.title.as_deref(), Some("Example"));
+        assert_eq!(bookmark.preview_image_url, None);
+    }
+
+    #[test]
+    fn invalid_url_fails() {
+        let json = with("favicon_url", json!("not a url"));
+        assert!(serde_json::from_value::<Bookmark>(json).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let bookmark: Bookmark = serde_json::from_value(bookmark_json()).unwrap();
+        let json = serde_json::to_string(&bookmark).unwrap();
+        assert_eq!(serde_json::from_str::<Bookmark>(&json).unwrap(), bookmark);
+    }
+
+    fn tagged(tags: &[&str]) -> Bookmark {
+        serde_json::from_value(with("tag_names", json!(tags))).unwrap()
+    }
+
+    fn url(url: &str) -> Option<Url> {
+        Some(Url::parse(url).unwrap())
+    }
+
+    fn strings(items: &[&str]) -> Option<Vec<String>> {
+        Some(items.iter().map(|x| x.to_string()).collect())
+    }
+
+    #[test]
+    fn diff() {
+        let bookmark = tagged(&["a", "b"]);
+        let text = |x: &str| Some(x.to_string());
+        let cases = [
+            (BookmarkRequest::default(), BookmarkPatch::default()),
+            (BookmarkRequest::from(&bookmark), BookmarkPatch::default()),
+            (
+                BookmarkRequest {
+                    url: url("https://example.com/"),
+                    title: text("Example"),
+                    description: text(""),
+                    notes: text(""),
+                    unread: Some(false),
+                    ..Default::default()
+                },
+                BookmarkPatch::default(),
+            ),
+            (
+                BookmarkRequest {
+                    url: url("https://example.org/"),
+                    title: text("New"),
+                    description: text("Text"),
+                    unread: Some(true),
+                    ..Default::default()
+                },
+                BookmarkPatch {
+                    url: url("https://example.org/"),
+                    title: text("New"),
+                    description: text("Text"),
+                    unread: Some(true),
+                    ..Default::default()
+                },
+            ),
+            (
+                BookmarkRequest {
+                    tag_names: strings(&["b", "a", "a"]),
+                    ..Default::default()
+                },
+                BookmarkPatch::default(),
+            ),
+            (
+                BookmarkRequest {
+                    tag_names: strings(&["a"]),
+                    ..Default::default()
+                },
+                BookmarkPatch {
+                    tag_names: strings(&["a"]),
+                    ..Default::default()
+                },
+            ),
+            (
+                BookmarkRequest {
+                    tag_names: strings(&[]),
+                    ..Default::default()
+                },
+                BookmarkPatch {
+                    tag_names: strings(&[]),
+                    ..Default::default()
+                },
+            ),
+        ];
+        for (request, patch) in cases {
+            assert_eq!(bookmark.diff(&request), patch, "{request:?}");
+        }
+    }
+
+    #[test]
+    fn merge() {
+        let text = |x: &str| Some(x.to_string());
+        let noted = |notes: Option<&str>| Bookmark {
+            notes: notes.map(String::from),
+            ..tagged(&["a", "b"])
+        };
+        let cases = [
+            (None, BookmarkRequest::default(), BookmarkPatch::default()),
+            (
+                None,
+                BookmarkRequest {
+                    url: url("https://example.org/"),
+                    title: text("New"),
+                    notes: text("Note"),
+                    tag_names: strings(&["c", "a"]),
+                    ..Default::default()
+                },
+                BookmarkPatch {
+                    title: text("New"),
+                    notes: text("Note"),
+                    tag_names: strings(&["a", "b", "c"]),
+                    ..Default::default()
+                },
+            ),
+            (
+                Some("Old"),
+                BookmarkRequest {
+                    notes: text("Note"),
+                    tag_names: strings(&["b"]),
+                    ..Default::default()
+                },
+                BookmarkPatch {
+                    notes: text("Old\n\nNote"),
+                    ..Default::default()
+                },
+            ),
+            (
+                Some("Old\n\nNote"),
+                BookmarkRequest {
+                    notes: text("Note"),
+                    ..Default::default()
+                },
+                BookmarkPatch::default(),
+            ),
+            (
+                Some("Old"),
+                BookmarkRequest {
+                    notes: None,
+                    unread: Some(true),
+                    ..Default::default()
+                },
+                BookmarkPatch {
+                    unread: Some(true),
+                    ..Default::default()
+                },
+            ),
+        ];
+        for (notes, request, patch) in cases {
+            assert_eq!(noted(notes).merge(&request), patch, "{notes:?} {request:?}");
+        }
+    }
+
+    fn field_errors(result: Result<(), DingError>) -> Vec<(&'static str, String)> {
+        match result {
+            Ok(()) => vec![],
+            Err(DingError::InvalidRequest { errors }) => {
+                errors.into_iter().map(|x| (x.field, x.message)).collect()
+            }
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let error = |field, message: &str| vec![(field, message.to_string())];
+        let cases = [
+            (url("https://example.com/"), None, true, vec![]),
+            (None, None, false, vec![]),
+            (None, None, true, error("url", "is required")),
+            (
+                url("ftp://example.com/"),
+                None,
+                false,
+                error("url", "unsupported scheme `ftp`"),
+            ),
+            (
+                url("http://example.com/"),
+                strings(&["rust", "c++", "ünïcode"]),
+                true,
+                vec![],
+            ),
+            (
+                url("https://example.com/"),
+                strings(&["two words", ""]),
+                true,
+                vec![
+                    (
+                        "tag_names",
+                        "tag `two words` contains whitespace".to_string(),
+                    ),
+                    ("tag_names", "empty tag name".to_string()),
+                ],
+            ),
+        ];
+        for (url, tag_names, require_url, errors) in cases {
+            let request = BookmarkRequest {
+                url,
+                tag_names,
+                ..Default::default()
+            };
+            assert_eq!(
+                field_errors(request.validate(require_url)),
+                errors,
+                "{request:?}"
+            );
+        }
+        let tag = |name: &str| TagRequest {
+            name: name.to_string(),
+        };
+        assert_eq!(field_errors(tag("rust").validate()), vec![]);
+        assert_eq!(
+            field_errors(tag("a\tb").validate()),
+            error("name", "tag `a\tb` contains whitespace")
+        );
     }
 
-    fn results(&self) -> Vec<Bookmark> {
-        self.results.clone()
+    #[test]
+    fn normalized() {
+        let request = BookmarkRequest {
+            title: Some("Example".to_string()),
+            ..BookmarkRequest::new(
+                Url::parse("https://example.com/?id=1&utm_source=x#top").unwrap(),
+            )
+        };
+        assert_eq!(
+            request.normalized(),
+            BookmarkRequest {
+                url: url("https://example.com/?id=1#top"),
+                ..request.clone()
+            }
+        );
+        assert_eq!(
+            BookmarkRequest::default().normalized(),
+            BookmarkRequest::default()
+        );
     }
 }
@@ -0,0 +1,98 @@
+//! Building API URLs from the configured server URL.
+//!
+//! `Url::join` replaces the last path segment unless the base ends with a
+//! slash, so `https://host/linkding` joined with `api/tags/` would lose the
+//! `linkding` prefix of a subpath deployment. The base is normalized once and
+//! API paths are always joined relative to it.
+
+use url::{ParseError, Url};
+
+/// The server URL as a directory: trailing slash added, query and fragment
+/// dropped.
+pub(crate) fn base_url(mut url: Url) -> Url {
+    url.set_query(None);
+    url.set_fragment(None);
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    url
+}
+
+/// The URL of `path` on the server at `base`, which must come from
+/// [`base_url`]. Leading slashes are ignored so the path stays below the
+/// base, and a trailing slash is added as linkding expects on every endpoint.
+pub(crate) fn api_url(base: &Url, path: &str) -> Result<Url, ParseError> {
+    let path = path.trim_start_matches('/');
+    match path.ends_with('/') {
+        true => base.join(path),
+        false => base.join(&format!("{path}/")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(base: &str, path: &str) -> String {
+        let base = base_url(Url::parse(base).unwrap());
+        api_url(&base, path).unwrap().to_string()
+    }
+
+    #[test]
+    fn root_deployment() {
+        assert_eq!(
+            url("https://host", "api/bookmarks/"),
+            "https://host/api/bookmarks/"
+        );
+        assert_eq!(
+            url("https://host/", "api/bookmarks/"),
+            "https://host/api/bookmarks/"
+        );
+    }
+
+    #[test]
+    fn subpath_deployment() {
+        assert_eq!(
+            url("https://host/linkding", "api/tags/"),
+            "https://host/linkding/api/tags/"
+        );
+        assert_eq!(
+            url("https://host/a/linkding/", "api/tags/"),
+            "https://host/a/linkding/api/tags/"
+        );
+    }
+
+    #[test]
+    fn trailing_slash_added() {
+        assert_eq!(url("https://host", "api/tags"), "https://host/api/tags/");
+        assert_eq!(
+            url("https://host/linkding", "api/bookmarks/1"),
+            "https://host/linkding/api/bookmarks/1/"
+        );
+    }
+
+    #[test]
+    fn leading_slash_stays_below_base() {
+        assert_eq!(
+            url("https://host/linkding", "/api/bookmarks/"),
+            "https://host/linkding/api/bookmarks/"
+        );
+    }
+
+    #[test]
+    fn query_and_fragment_dropped() {
+        assert_eq!(
+            url("https://host/linkding?x=1#top", "api/user/profile/"),
+            "https://host/linkding/api/user/profile/"
+        );
+    }
+
+    #[test]
+    fn port_kept() {
+        assert_eq!(
+            url("http://127.0.0.1:9090/ld", "api/bookmarks/check/"),
+            "http://127.0.0.1:9090/ld/api/bookmarks/check/"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! The client's request handling, against a transport that answers from
+//! memory.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ding_rs::http_cache::{CachedResponse, HttpCache, MemoryCache};
+use ding_rs::transport::{BoxFuture, HttpRequest, HttpResponse, HttpTransport, TransportError};
+use ding_rs::DingClient;
+
+const BOOKMARK: &str = r#"{
+    "id": 1,
+    "url": "https://example.com/",
+    "title": "Example",
+    "description": "",
+    "tag_names": ["example"],
+    "date_added": "2021-01-02T10:00:00Z",
+    "date_modified": "2021-01-02T10:00:00Z"
+}"#;
+
+type Respond = dyn Fn(&HttpRequest) -> HttpResponse + Send + Sync;
+
+/// Answers every request with `respond` after `delay`, noting when each was
+/// sent.
+#[derive(Clone)]
+struct Mock {
+    sent: Arc<Mutex<Vec<(HttpRequest, Instant)>>>,
+    delay: Duration,
+    respond: Arc<Respond>,
+}
+
+impl Mock {
+    fn new(
+        delay: Duration,
+        respond: impl Fn(&HttpRequest) -> HttpResponse + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            sent: Default::default(),
+            delay,
+            respond: Arc::new(respond),
+        }
+    }
+
+    fn sent(&self) -> Vec<(HttpRequest, Instant)> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl HttpTransport for Mock {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>> {
+        Box::pin(async move {
+            self.sent
+                .lock()
+                .unwrap()
+                .push((request.clone(), Instant::now()));
+            tokio::time::sleep(self.delay).await;
+            Ok((self.respond)(&request))
+        })
+    }
+}
+
+fn ok(body: &str) -> HttpResponse {
+    HttpResponse {
+        status: 200,
+        headers: vec![],
+        body: body.as_bytes().to_vec(),
+    }
+}
+
+fn header<'a>(request: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    request
+        .headers
+        .iter()
+        .find(|(x, _)| x.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Answers with `BOOKMARK` and `validator`, or `304 Not Modified` if the
+/// request carries `condition`.
+fn revalidating(validator: &'static str, condition: &'static str) -> Mock {
+    Mock::new(Duration::ZERO, move |request| {
+        match header(request, condition) {
+            Some(_) => HttpResponse {
+                status: 304,
+                headers: vec![],
+                body: vec![],
+            },
+            None => HttpResponse {
+                headers: vec![(validator.to_string(), "v1".to_string())],
+                ..ok(BOOKMARK)
+            },
+        }
+    })
+}
+
+/// A cache the test can look into after handing it to the client.
+#[derive(Clone, Default)]
+struct Shared(Arc<MemoryCache>);
+
+impl HttpCache for Shared {
+    fn get(&self, url: &str) -> Option<CachedResponse> {
+        self.0.get(url)
+    }
+
+    fn put(&self, response: CachedResponse) {
+        self.0.put(response)
+    }
+}
+
+fn client(mock: &Mock) -> ding_rs::DingClientBuilder {
+    DingClient::builder("https://ding.test/".parse().unwrap(), "token".to_string())
+        .transport(mock.clone())
+}
+
+#[tokio::test]
+async fn rate_limit_spaces_requests() {
+    let mock = Mock::new(Duration::ZERO, |_| ok(BOOKMARK));
+    let client = client(&mock).max_requests_per_second(10.0).build();
+    // The first second's worth goes out at once, the rest 100ms apart.
+    for _ in 0..13 {
+        client.bookmark(1).await.unwrap();
+    }
+    let sent = mock.sent();
+    // Spaced out, the burst would have taken 900ms.
+    let burst = sent[9].1 - sent[0].1;
+    assert!(burst < Duration::from_millis(300), "{burst:?}");
+    // Two whole tokens and part of another had to refill.
+    let limited = sent[12].1 - sent[9].1;
+    assert!(limited >= Duration::from_millis(200), "{limited:?}");
+}
+
+#[tokio::test]
+async fn endpoint_rate_limit_overrides_the_default() {
+    let mock = Mock::new(Duration::ZERO, |_| ok(BOOKMARK));
+    let client = client(&mock)
+        .max_requests_per_second(1.0)
+        .endpoint_requests_per_second("api/bookmarks/", 0.0)
+        .build();
+    let start = Instant::now();
+    for _ in 0..3 {
+        client.bookmark(1).await.unwrap();
+    }
+    assert!(start.elapsed() < Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn concurrent_gets_share_one_request() {
+    let mock = Mock::new(Duration::from_millis(50), |_| ok(BOOKMARK));
+    let client = client(&mock).build();
+    let (a, b) = tokio::join!(client.bookmark(1), client.bookmark(1));
+    assert_eq!(a.unwrap(), b.unwrap());
+    assert_eq!(mock.sent().len(), 1);
+
+    // Once answered, the next GET is sent again.
+    client.bookmark(1).await.unwrap();
+    assert_eq!(mock.sent().len(), 2);
+}
+
+#[tokio::test]
+async fn concurrent_gets_of_other_urls_are_not_shared() {
+    let mock = Mock::new(Duration::from_millis(50), |_| ok(BOOKMARK));
+    let client = client(&mock).build();
+    let (a, b) = tokio::join!(client.bookmark(1), client.bookmark(2));
+    a.unwrap();
+    b.unwrap();
+    assert_eq!(mock.sent().len(), 2);
+}
+
+#[tokio::test]
+async fn not_modified_is_served_from_the_cache() {
+    for (validator, condition) in [
+        ("ETag", "If-None-Match"),
+        ("Last-Modified", "If-Modified-Since"),
+    ] {
+        let mock = revalidating(validator, condition);
+        let cache = Shared::default();
+        let client = client(&mock).http_cache(cache.clone()).build();
+        let first = client.bookmark(1).await.unwrap();
+        let second = client.bookmark(1).await.unwrap();
+        assert_eq!(first, second);
+        let sent = mock.sent();
+        assert_eq!(header(&sent[0].0, condition), None);
+        assert_eq!(header(&sent[1].0, condition), Some("v1"));
+        let entry = cache.get("https://ding.test/api/bookmarks/1/").unwrap();
+        assert_eq!(entry.body, BOOKMARK);
+    }
+}
+
+#[tokio::test]
+async fn responses_without_validators_are_not_cached() {
+    let mock = Mock::new(Duration::ZERO, |_| ok(BOOKMARK));
+    let cache = Shared::default();
+    let client = client(&mock).http_cache(cache.clone()).build();
+    client.bookmark(1).await.unwrap();
+    client.bookmark(1).await.unwrap();
+    assert!(cache.get("https://ding.test/api/bookmarks/1/").is_none());
+    assert_eq!(header(&mock.sent()[1].0, "If-None-Match"), None);
+}
@@ -0,0 +1,86 @@
+//! Payloads in the shapes older and newer linkding releases send.
+
+use ding_rs::{Bookmark, BookmarksResponse, TagsResponse, UserProfile};
+use serde::de::DeserializeOwned;
+
+fn fixture<T: DeserializeOwned>(name: &str) -> T {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    let data = std::fs::read_to_string(&path).unwrap();
+    serde_json::from_str(&data).unwrap_or_else(|err| panic!("{name}: {err}"))
+}
+
+fn round_trip<T: serde::Serialize + DeserializeOwned + PartialEq + std::fmt::Debug>(value: &T) {
+    let json = serde_json::to_string(value).unwrap();
+    assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), value);
+}
+
+#[test]
+fn bookmarks_minimal() {
+    let page: BookmarksResponse = fixture("bookmarks_minimal.json");
+    let bookmark = &page.results[0];
+    assert_eq!(bookmark.title.as_deref(), Some("Example"));
+    assert_eq!(bookmark.notes, None);
+    assert_eq!(bookmark.favicon_url, None);
+    assert_eq!(bookmark.preview_image_url, None);
+    assert_eq!(bookmark.web_archive_snapshot_url, None);
+    assert!(!bookmark.is_archived && !bookmark.unread && !bookmark.shared);
+    assert!(bookmark.extra.is_empty());
+    round_trip(&page);
+}
+
+#[test]
+fn bookmarks_without_notes() {
+    let page: BookmarksResponse = fixture("bookmarks_without_notes.json");
+    assert!(page.has_next());
+    let bookmark = &page.results[0];
+    assert_eq!(bookmark.title, None);
+    assert_eq!(bookmark.notes, None);
+    assert!(bookmark.unread);
+    assert!(bookmark.favicon_url.is_some());
+    assert_eq!(bookmark.preview_image_url, None);
+    round_trip(&page);
+}
+
+#[test]
+fn bookmarks_unknown_fields_are_kept() {
+    let page: BookmarksResponse = fixture("bookmarks_unknown_fields.json");
+    let bookmark = &page.results[0];
+    assert_eq!(bookmark.notes.as_deref(), Some("Read *later*"));
+    assert_eq!(bookmark.extra["is_pinned"], serde_json::json!(true));
+    assert_eq!(
+        bookmark.extra["reader_mode"],
+        serde_json::json!({"enabled": false})
+    );
+    let json = serde_json::to_value(bookmark).unwrap();
+    assert_eq!(json["is_pinned"], serde_json::json!(true));
+    assert_eq!(serde_json::from_value::<Bookmark>(json).unwrap(), *bookmark);
+}
+
+#[test]
+fn user_profile_minimal() {
+    let profile: UserProfile = fixture("user_profile_minimal.json");
+    assert_eq!(profile.theme, "auto");
+    assert!(!profile.enable_public_sharing);
+    assert!(!profile.permanent_notes);
+    assert_eq!(profile.search_preferences.sort, "");
+    round_trip(&profile);
+}
+
+#[test]
+fn user_profile_current() {
+    let profile: UserProfile = fixture("user_profile_current.json");
+    assert!(profile.display_url);
+    assert_eq!(profile.search_preferences.unread, "yes");
+    assert_eq!(profile.extra["items_per_page"], serde_json::json!(30));
+    round_trip(&profile);
+}
+
+#[test]
+fn tags() {
+    let page: TagsResponse = fixture("tags.json");
+    assert_eq!(
+        page.results.iter().map(|x| &x.name[..]).collect::<Vec<_>>(),
+        ["rust", "lang"]
+    );
+    round_trip(&page);
+}